@@ -8,7 +8,12 @@ use resvg::usvg;
 use std::fmt::Debug;
 use std::path::PathBuf;
 
+use crate::compare_viewer::{CompareViewer, CompareViewerPanel};
+use crate::css_viewer::CssViewerPanel;
+use crate::diff_viewer::{DiffViewer, DiffViewerPanel};
 use crate::document::Document;
+use crate::layers_viewer::LayersViewerPanel;
+use crate::lint_viewer::{LintViewer, LintViewerPanel};
 use crate::settings::AppSettings;
 use crate::string_viewer::StringViewerPanel;
 use crate::svg_render::SvgViewerPanel;
@@ -27,6 +32,12 @@ pub struct GalagoApp {
     pub(crate) tree_viewer: TreeViewer,
     /// `StringViewer` Ui
     pub(crate) string_viewer: StringViewer,
+    /// `DiffViewer` Ui
+    pub(crate) diff_viewer: DiffViewer,
+    /// `CompareViewer` Ui
+    pub(crate) compare_viewer: CompareViewer,
+    /// `LintViewer` Ui
+    pub(crate) lint_viewer: LintViewer,
     /// Grid options
     pub(crate) grid: Grid,
     /// usvg options
@@ -48,6 +59,9 @@ impl Default for GalagoApp {
             settings: AppSettings::default(),
             tree_viewer: TreeViewer::default(),
             string_viewer: StringViewer::default(),
+            diff_viewer: DiffViewer::default(),
+            compare_viewer: CompareViewer::default(),
+            lint_viewer: LintViewer::default(),
             grid: Grid::default(),
             usvg_options,
         }
@@ -93,6 +107,11 @@ impl BladvakApp<'_> for GalagoApp {
         vec![
             Box::new(StringViewerPanel),
             Box::new(TreeViewerPanel),
+            Box::new(LayersViewerPanel),
+            Box::new(CssViewerPanel),
+            Box::new(LintViewerPanel),
+            Box::new(DiffViewerPanel),
+            Box::new(CompareViewerPanel),
             Box::new(SvgViewerPanel),
         ]
     }
@@ -178,6 +197,7 @@ impl BladvakApp<'_> for GalagoApp {
             self.usvg_options.fontdb_mut().load_font_data(file.data);
             for one_document in &mut self.documents {
                 one_document.svg_render.stale_render();
+                one_document.saved_render.stale_render();
             }
             Ok(())
         } else {
@@ -0,0 +1,373 @@
+//! Attribute
+//! Types of the svg attributes, to pick their editor and validate their values
+
+use std::fmt::Write;
+use std::ops::Range;
+use std::str::FromStr;
+
+use svgtypes::{
+    Color, Length, LengthUnit, Number, Paint, Transform, TransformListParser, TransformListToken,
+    ViewBox,
+};
+
+use crate::style::parse_style_with_rest;
+
+/// Type of the value of an attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeKind {
+    /// Color, `none`, `currentColor` or `url(#id)`, e.g. `fill`
+    Paint,
+    /// Color, e.g. `stop-color`
+    Color,
+    /// Number with an optional unit, e.g. `width`
+    Length,
+    /// Number without unit, e.g. `opacity`
+    Number,
+    /// One of the given keywords, e.g. `fill-rule`
+    Enumeration(&'static [&'static str]),
+    /// Transform list
+    Transform,
+    /// `viewBox`, four numbers
+    ViewBox,
+    /// `style`, declarations of properties
+    Style,
+    /// Any other value
+    Text,
+}
+
+/// Units of a length, in the order of their suffix
+pub const LENGTH_UNITS: [LengthUnit; 10] = [
+    LengthUnit::None,
+    LengthUnit::Px,
+    LengthUnit::Em,
+    LengthUnit::Ex,
+    LengthUnit::In,
+    LengthUnit::Cm,
+    LengthUnit::Mm,
+    LengthUnit::Pt,
+    LengthUnit::Pc,
+    LengthUnit::Percent,
+];
+
+/// Type of the attribute `name`
+#[must_use]
+pub fn attribute_kind(name: &str) -> AttributeKind {
+    match name {
+        "fill" | "stroke" => AttributeKind::Paint,
+        "stop-color" | "flood-color" | "lighting-color" | "color" => AttributeKind::Color,
+        "x" | "y" | "width" | "height" | "cx" | "cy" | "r" | "rx" | "ry" | "x1" | "y1" | "x2"
+        | "y2" | "fx" | "fy" | "stroke-width" | "stroke-dashoffset" | "font-size" => {
+            AttributeKind::Length
+        }
+        "opacity" | "fill-opacity" | "stroke-opacity" | "stop-opacity" | "flood-opacity"
+        | "stroke-miterlimit" | "pathLength" => AttributeKind::Number,
+        "stroke-linejoin" => {
+            AttributeKind::Enumeration(&["miter", "round", "bevel", "miter-clip", "arcs"])
+        }
+        "stroke-linecap" => AttributeKind::Enumeration(&["butt", "round", "square"]),
+        "fill-rule" | "clip-rule" => AttributeKind::Enumeration(&["nonzero", "evenodd"]),
+        "visibility" => AttributeKind::Enumeration(&["visible", "hidden", "collapse"]),
+        "display" => AttributeKind::Enumeration(&[
+            "inline",
+            "block",
+            "none",
+            "inline-block",
+            "flex",
+            "inline-flex",
+            "grid",
+            "inline-grid",
+            "flow-root",
+            "contents",
+            "list-item",
+            "run-in",
+            "table",
+            "inline-table",
+            "table-row-group",
+            "table-header-group",
+            "table-footer-group",
+            "table-row",
+            "table-cell",
+            "table-column-group",
+            "table-column",
+            "table-caption",
+            "inherit",
+        ]),
+        "text-anchor" => AttributeKind::Enumeration(&["start", "middle", "end"]),
+        "transform" | "gradientTransform" | "patternTransform" => AttributeKind::Transform,
+        "viewBox" => AttributeKind::ViewBox,
+        "style" => AttributeKind::Style,
+        _ => AttributeKind::Text,
+    }
+}
+
+/// Opacity attribute of the color attribute `name`, that holds the alpha of the color
+#[must_use]
+pub fn opacity_attribute(name: &str) -> Option<&'static str> {
+    match name {
+        "fill" => Some("fill-opacity"),
+        "stroke" => Some("stroke-opacity"),
+        "stop-color" => Some("stop-opacity"),
+        "flood-color" => Some("flood-opacity"),
+        _ => None,
+    }
+}
+
+/// Alpha of an opacity value, opaque if the value is empty or invalid
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+#[allow(clippy::cast_sign_loss)]
+pub fn opacity_alpha(value: &str) -> u8 {
+    Number::from_str(value).map_or(255, |Number(opacity)| {
+        (opacity.clamp(0.0, 1.0) * 255.0).round() as u8
+    })
+}
+
+/// Opacity value of an alpha, rounded to 3 decimals
+#[must_use]
+pub fn format_opacity(alpha: u8) -> String {
+    let opacity = (f64::from(alpha) / 255.0 * 1000.0).round() / 1000.0;
+    opacity.to_string()
+}
+
+/// `url(#id)` references of a value: byte range of the whole `url(...)` and
+/// the referenced id
+#[must_use]
+pub fn url_references(value: &str) -> Vec<(Range<usize>, &str)> {
+    let mut references = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = value[offset..].find("url(").map(|i| offset + i) {
+        let inner_start = start + 4;
+        let Some(end) = value[inner_start..].find(')').map(|i| inner_start + i) else {
+            break;
+        };
+        let inner = value[inner_start..end]
+            .trim()
+            .trim_matches(|c| c == '"' || c == '\'');
+        if let Some(id) = inner.trim().strip_prefix('#') {
+            references.push((start..end + 1, id));
+        }
+        offset = end + 1;
+    }
+    references
+}
+
+/// Rewrite the `url(#id)` references of a value with the ids returned by
+/// `rename`, `None` if no reference changed
+pub fn rename_url_references<'a>(
+    value: &str,
+    rename: impl Fn(&str) -> Option<&'a str>,
+) -> Option<String> {
+    let mut renamed = String::new();
+    let mut copied = 0;
+    for (range, id) in url_references(value) {
+        if let Some(new_id) = rename(id) {
+            renamed.push_str(&value[copied..range.start]);
+            let _ = write!(renamed, "url(#{new_id})");
+            copied = range.end;
+        }
+    }
+    if copied == 0 {
+        return None;
+    }
+    renamed.push_str(&value[copied..]);
+    Some(renamed)
+}
+
+/// Check the value of the attribute `name`
+/// # Errors
+/// Return the parse error if the value is invalid for the attribute type
+pub fn validate_attribute(name: &str, value: &str) -> Result<(), String> {
+    let kind = attribute_kind(name);
+    if value.trim() == "inherit" && !matches!(kind, AttributeKind::ViewBox | AttributeKind::Style) {
+        return Ok(());
+    }
+    match kind {
+        AttributeKind::Paint => Paint::from_str(value)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        AttributeKind::Color => match value.trim() {
+            "currentColor" => Ok(()),
+            value => Color::from_str(value)
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+        },
+        AttributeKind::Length => Length::from_str(value)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        AttributeKind::Number => Number::from_str(value)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        AttributeKind::Enumeration(values) => {
+            if values.contains(&value.trim()) {
+                Ok(())
+            } else {
+                Err(format!("Expected one of: {}", values.join(", ")))
+            }
+        }
+        AttributeKind::Transform => Transform::from_str(value)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        AttributeKind::ViewBox => ViewBox::from_str(value)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        AttributeKind::Style => {
+            let (declarations, rest) = parse_style_with_rest(value);
+            declarations.iter().try_for_each(|d| {
+                validate_attribute(&d.name, &d.value).map_err(|e| format!("{}: {e}", d.name))
+            })?;
+            if rest.is_empty() {
+                Ok(())
+            } else {
+                Err(format!("Invalid declaration: {rest}"))
+            }
+        }
+        AttributeKind::Text => Ok(()),
+    }
+}
+
+/// Write a color as `#rrggbb`, or `#rrggbbaa` if it is not opaque
+#[must_use]
+pub fn format_color(color: Color) -> String {
+    let Color {
+        red,
+        green,
+        blue,
+        alpha,
+    } = color;
+    if alpha == 255 {
+        format!("#{red:02x}{green:02x}{blue:02x}")
+    } else {
+        format!("#{red:02x}{green:02x}{blue:02x}{alpha:02x}")
+    }
+}
+
+/// Suffix of a length unit
+#[must_use]
+pub fn length_unit_suffix(unit: LengthUnit) -> &'static str {
+    match unit {
+        LengthUnit::None => "",
+        LengthUnit::Em => "em",
+        LengthUnit::Ex => "ex",
+        LengthUnit::Px => "px",
+        LengthUnit::In => "in",
+        LengthUnit::Cm => "cm",
+        LengthUnit::Mm => "mm",
+        LengthUnit::Pt => "pt",
+        LengthUnit::Pc => "pc",
+        LengthUnit::Percent => "%",
+    }
+}
+
+/// Write a length with its unit
+#[must_use]
+pub fn format_length(length: Length) -> String {
+    format!("{}{}", length.number, length_unit_suffix(length.unit))
+}
+
+/// Parse a transform list
+///
+/// `rotate(a cx cy)` is split into a translate, a rotate and a translate.
+/// # Errors
+/// Return the parse error if the transform list is invalid
+pub fn parse_transform(value: &str) -> Result<Vec<TransformListToken>, String> {
+    TransformListParser::from(value)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Write a transform list
+#[must_use]
+pub fn format_transform(tokens: &[TransformListToken]) -> String {
+    let mut transform = String::new();
+    for token in tokens {
+        if !transform.is_empty() {
+            transform.push(' ');
+        }
+        // writing to a String cannot fail
+        let _ = match token {
+            TransformListToken::Matrix { a, b, c, d, e, f } => {
+                write!(transform, "matrix({a} {b} {c} {d} {e} {f})")
+            }
+            TransformListToken::Translate { tx, ty } => write!(transform, "translate({tx} {ty})"),
+            TransformListToken::Scale { sx, sy } => write!(transform, "scale({sx} {sy})"),
+            TransformListToken::Rotate { angle } => write!(transform, "rotate({angle})"),
+            TransformListToken::SkewX { angle } => write!(transform, "skewX({angle})"),
+            TransformListToken::SkewY { angle } => write!(transform, "skewY({angle})"),
+        };
+    }
+    transform
+}
+
+/// Write a `viewBox`
+#[must_use]
+pub fn format_view_box(view_box: ViewBox) -> String {
+    format!(
+        "{} {} {} {}",
+        view_box.x, view_box.y, view_box.w, view_box.h
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn test_validate_attribute() {
+        assert!(validate_attribute("fill", "none").is_ok());
+        assert!(validate_attribute("fill", "url(#grad)").is_ok());
+        assert!(validate_attribute("fill", "#12").is_err());
+        assert!(validate_attribute("stop-color", "currentColor").is_ok());
+        assert!(validate_attribute("width", "10mm").is_ok());
+        assert!(validate_attribute("width", "ten").is_err());
+        assert!(validate_attribute("fill-rule", "evenodd").is_ok());
+        assert!(validate_attribute("fill-rule", "odd").is_err());
+        assert!(validate_attribute("stroke-linejoin", "inherit").is_ok());
+        assert!(validate_attribute("viewBox", "0 0 10").is_err());
+        assert!(validate_attribute("transform", "rotate(45").is_err());
+        assert!(validate_attribute("id", "anything").is_ok());
+        assert!(validate_attribute("style", "fill:red;opacity:0.5").is_ok());
+        assert!(validate_attribute("style", "fill:red;opacity:half").is_err());
+        assert!(validate_attribute("style", "fill:red;stroke blue").is_err());
+    }
+
+    #[test]
+    fn test_url_references() {
+        let value = "url(#a) url( '#b' ) url(c) url(#d";
+        let references = url_references(value);
+        assert_eq!(references.len(), 2);
+        assert_eq!(references[0], (0..7, "a"));
+        assert_eq!(&value[references[1].0.clone()], "url( '#b' )");
+        assert_eq!(references[1].1, "b");
+        let swap = |id: &str| match id {
+            "a" => Some("b"),
+            "b" => Some("a"),
+            _ => None,
+        };
+        assert_eq!(
+            rename_url_references(value, swap).unwrap(),
+            "url(#b) url(#a) url(c) url(#d"
+        );
+        assert!(rename_url_references("url(#c)", swap).is_none());
+    }
+
+    #[test]
+    fn test_format_values() {
+        assert_eq!(format_color(Color::from_str("red").unwrap()), "#ff0000");
+        assert_eq!(format_color(Color::new_rgba(1, 2, 3, 128)), "#01020380");
+        assert_eq!(format_opacity(128), "0.502");
+        assert_eq!(format_opacity(255), "1");
+        assert_eq!(opacity_alpha("0.502"), 128);
+        assert_eq!(opacity_alpha(""), 255);
+        assert_eq!(opacity_attribute("stroke"), Some("stroke-opacity"));
+        assert!(validate_attribute("display", "inline-block").is_ok());
+        assert_eq!(format_length(Length::from_str("1.5em").unwrap()), "1.5em");
+        assert_eq!(format_length(Length::from_str("50%").unwrap()), "50%");
+        let tokens = parse_transform("translate(10,20) scale(2)").unwrap();
+        assert_eq!(format_transform(&tokens), "translate(10 20) scale(2 2)");
+        assert_eq!(
+            format_view_box(ViewBox::from_str("0,0 100 50.5").unwrap()),
+            "0 0 100 50.5"
+        );
+    }
+}
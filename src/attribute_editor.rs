@@ -0,0 +1,352 @@
+//! Attribute Editor
+//! Editors of the attribute values, picked from the attribute type
+
+use std::str::FromStr;
+
+use bladvak::eframe::egui::{self, Color32};
+use svgtypes::{Color, Length, Number, Paint, TransformListToken, ViewBox};
+
+use crate::attribute::{
+    AttributeKind, LENGTH_UNITS, attribute_kind, format_color, format_length, format_opacity,
+    format_transform, format_view_box, length_unit_suffix, opacity_alpha, opacity_attribute,
+    parse_transform, validate_attribute,
+};
+use crate::schema::complete;
+use crate::style::{
+    PRESENTATION_ATTRIBUTES, StyleDeclaration, parse_style_with_rest, write_style_with_rest,
+};
+
+/// Maximum number of suggestions shown below a text edit
+const MAX_SUGGESTIONS: usize = 12;
+
+/// Single line text edit suggesting the `candidates` completing its text
+pub(crate) fn autocomplete_edit(
+    ui: &mut egui::Ui,
+    text: &mut String,
+    candidates: &[&str],
+) -> egui::Response {
+    let response = ui.text_edit_singleline(text);
+    let suggestions = complete(text, candidates.iter().copied());
+    let popup = egui::Popup::from_response(&response);
+    // the text edit loses the focus when a suggestion is pressed
+    let is_hovered = popup
+        .get_popup_rect()
+        .zip(ui.ctx().pointer_hover_pos())
+        .is_some_and(|(rect, pointer)| rect.contains(pointer));
+    let is_open = !suggestions.is_empty() && (response.has_focus() || is_hovered);
+    popup.open(is_open).width(response.rect.width()).show(|ui| {
+        for suggestion in suggestions.iter().take(MAX_SUGGESTIONS) {
+            if ui.selectable_label(false, *suggestion).clicked() {
+                *text = (*suggestion).to_string();
+            }
+        }
+    });
+    response
+}
+
+/// Show the editor of the attribute `name`: a typed editor if the value can be
+/// parsed, the raw text and a warning if it is invalid
+///
+/// The color picker writes its alpha to the opacity attribute of the color (see
+/// [`opacity_attribute`]), whose value is given by `opacity_of`. Returns that
+/// attribute and its new value if it was edited.
+pub(crate) fn show_attribute_value(
+    ui: &mut egui::Ui,
+    name: &str,
+    value: &mut String,
+    is_multi_line: bool,
+    opacity_of: impl Fn(&str) -> Option<String>,
+) -> Option<(&'static str, String)> {
+    let opacity_name = opacity_attribute(name);
+    let current_opacity = opacity_name.map(|name| opacity_of(name).unwrap_or_default());
+    let mut opacity = current_opacity.clone();
+    ui.vertical(|ui| {
+        ui.horizontal(|ui| {
+            match attribute_kind(name) {
+                AttributeKind::Paint | AttributeKind::Color => {
+                    color_editor(ui, value, opacity.as_mut())
+                }
+                AttributeKind::Length => length_editor(ui, value),
+                AttributeKind::Number => number_editor(ui, value),
+                AttributeKind::Enumeration(values) => enumeration_editor(ui, name, value, values),
+                AttributeKind::ViewBox => view_box_editor(ui, value),
+                AttributeKind::Transform | AttributeKind::Style | AttributeKind::Text => false,
+            };
+            if is_multi_line {
+                ui.text_edit_multiline(value);
+            } else {
+                ui.text_edit_singleline(value);
+            }
+            if let Err(e) = validate_attribute(name, value) {
+                ui.colored_label(Color32::RED, "⚠").on_hover_text(e);
+            }
+        });
+        match attribute_kind(name) {
+            AttributeKind::Transform => transform_editor(ui, name, value),
+            AttributeKind::Style => style_editor(ui, value, is_multi_line),
+            _ => false,
+        };
+    });
+    opacity_name
+        .zip(opacity)
+        .filter(|(_, opacity)| Some(opacity) != current_opacity.as_ref())
+}
+
+/// Color picker, shown if the value is a color
+///
+/// The alpha is edited only with an `opacity` value, where it is written, the
+/// color keeps its own alpha otherwise.
+fn color_editor(ui: &mut egui::Ui, value: &mut String, opacity: Option<&mut String>) -> bool {
+    let Ok(Paint::Color(color)) = Paint::from_str(value) else {
+        return false;
+    };
+    let Some(opacity) = opacity else {
+        let mut rgb = [color.red, color.green, color.blue];
+        if ui.color_edit_button_srgb(&mut rgb).changed() {
+            let [red, green, blue] = rgb;
+            *value = format_color(Color::new_rgba(red, green, blue, color.alpha));
+            return true;
+        }
+        return false;
+    };
+    let alpha = u16::from(color.alpha) * u16::from(opacity_alpha(opacity)) / 255;
+    let mut rgba = [
+        color.red,
+        color.green,
+        color.blue,
+        u8::try_from(alpha).unwrap_or(u8::MAX),
+    ];
+    if ui.color_edit_button_srgba_unmultiplied(&mut rgba).changed() {
+        let [red, green, blue, alpha] = rgba;
+        *value = format_color(Color::new_rgb(red, green, blue));
+        if alpha != u8::MAX || !opacity.is_empty() {
+            *opacity = format_opacity(alpha);
+        }
+        return true;
+    }
+    false
+}
+
+/// Number and unit, shown if the value is a length
+fn length_editor(ui: &mut egui::Ui, value: &mut String) -> bool {
+    let Ok(mut length) = Length::from_str(value) else {
+        return false;
+    };
+    let mut changed = ui
+        .add(egui::DragValue::new(&mut length.number).speed(0.1))
+        .changed();
+    egui::ComboBox::from_id_salt(ui.next_auto_id())
+        .width(40.0)
+        .selected_text(length_unit_suffix(length.unit))
+        .show_ui(ui, |ui| {
+            for unit in LENGTH_UNITS {
+                let suffix = match length_unit_suffix(unit) {
+                    "" => "(none)",
+                    suffix => suffix,
+                };
+                changed |= ui
+                    .selectable_value(&mut length.unit, unit, suffix)
+                    .changed();
+            }
+        });
+    if changed {
+        *value = format_length(length);
+    }
+    changed
+}
+
+/// Drag value, shown if the value is a number
+fn number_editor(ui: &mut egui::Ui, value: &mut String) -> bool {
+    let Ok(Number(mut number)) = Number::from_str(value) else {
+        return false;
+    };
+    if ui
+        .add(egui::DragValue::new(&mut number).speed(0.01))
+        .changed()
+    {
+        *value = number.to_string();
+        return true;
+    }
+    false
+}
+
+/// Dropdown of the keywords of the attribute
+fn enumeration_editor(ui: &mut egui::Ui, name: &str, value: &mut String, values: &[&str]) -> bool {
+    let mut changed = false;
+    egui::ComboBox::from_id_salt(("enumeration", name, ui.next_auto_id()))
+        .selected_text(value.as_str())
+        .show_ui(ui, |ui| {
+            for keyword in values {
+                if ui.selectable_label(value == keyword, *keyword).clicked() {
+                    *value = (*keyword).to_string();
+                    changed = true;
+                }
+            }
+        });
+    changed
+}
+
+/// `x`, `y`, `width` and `height` of a `viewBox`
+fn view_box_editor(ui: &mut egui::Ui, value: &mut String) -> bool {
+    let Ok(mut view_box) = ViewBox::from_str(value) else {
+        return false;
+    };
+    let ViewBox { x, y, w, h } = &mut view_box;
+    let mut changed = false;
+    for (number, prefix) in [(x, "x: "), (y, "y: "), (w, "w: "), (h, "h: ")] {
+        changed |= ui
+            .add(egui::DragValue::new(number).speed(0.1).prefix(prefix))
+            .changed();
+    }
+    if changed {
+        *value = format_view_box(view_box);
+    }
+    changed
+}
+
+/// Drag values of each transform of the list, with buttons to add and remove transforms
+fn transform_editor(ui: &mut egui::Ui, name: &str, value: &mut String) -> bool {
+    let Ok(mut tokens) = parse_transform(value) else {
+        return false;
+    };
+    let mut changed = false;
+    let mut removed = None;
+    ui.push_id(("transform", name), |ui| {
+        for (idx, token) in tokens.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                let (label, numbers): (&str, Vec<&mut f64>) = match token {
+                    TransformListToken::Matrix { a, b, c, d, e, f } => {
+                        ("matrix", vec![a, b, c, d, e, f])
+                    }
+                    TransformListToken::Translate { tx, ty } => ("translate", vec![tx, ty]),
+                    TransformListToken::Scale { sx, sy } => ("scale", vec![sx, sy]),
+                    TransformListToken::Rotate { angle } => ("rotate", vec![angle]),
+                    TransformListToken::SkewX { angle } => ("skewX", vec![angle]),
+                    TransformListToken::SkewY { angle } => ("skewY", vec![angle]),
+                };
+                ui.label(label);
+                for number in numbers {
+                    changed |= ui.add(egui::DragValue::new(number).speed(0.1)).changed();
+                }
+                if ui
+                    .small_button("🗑")
+                    .on_hover_text("Remove this transform")
+                    .clicked()
+                {
+                    removed = Some(idx);
+                }
+            });
+        }
+        ui.horizontal(|ui| {
+            let added = [
+                (
+                    "+ translate",
+                    TransformListToken::Translate { tx: 0.0, ty: 0.0 },
+                ),
+                ("+ scale", TransformListToken::Scale { sx: 1.0, sy: 1.0 }),
+                ("+ rotate", TransformListToken::Rotate { angle: 0.0 }),
+            ];
+            for (label, token) in added {
+                if ui.small_button(label).clicked() {
+                    tokens.push(token);
+                    changed = true;
+                }
+            }
+        });
+    });
+    if let Some(idx) = removed {
+        tokens.remove(idx);
+        changed = true;
+    }
+    if changed {
+        *value = format_transform(&tokens);
+    }
+    changed
+}
+
+/// Typed editor of each declaration of a `style` attribute
+///
+/// The text after the first invalid declaration is kept as is.
+fn style_editor(ui: &mut egui::Ui, value: &mut String, is_multi_line: bool) -> bool {
+    let (mut declarations, rest) = parse_style_with_rest(value);
+    let rest = rest.to_string();
+    let before = declarations.clone();
+    let mut removed = None;
+    let mut opacity_edit = None;
+    ui.push_id("style", |ui| {
+        for (idx, declaration) in declarations.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                if ui
+                    .small_button("🗑")
+                    .on_hover_text("Remove this declaration")
+                    .clicked()
+                {
+                    removed = Some(idx);
+                }
+                ui.label(&declaration.name);
+                let opacity_of = |name: &str| {
+                    before
+                        .iter()
+                        .find(|d| d.name == name)
+                        .map(|d| d.value.clone())
+                };
+                ui.push_id(idx, |ui| {
+                    if let Some(edit) = show_attribute_value(
+                        ui,
+                        &declaration.name,
+                        &mut declaration.value,
+                        is_multi_line,
+                        opacity_of,
+                    ) {
+                        opacity_edit = Some(edit);
+                    }
+                });
+            });
+        }
+        if !rest.is_empty() {
+            ui.weak(format!("Not parsed, kept as is: {rest}"));
+        }
+        ui.horizontal(|ui| {
+            // name and value of the declaration to add
+            let new_id = ui.id().with("new_declaration");
+            let (mut name, mut new_value) = ui
+                .data_mut(|data| data.get_temp::<(String, String)>(new_id))
+                .unwrap_or_default();
+            autocomplete_edit(ui, &mut name, PRESENTATION_ATTRIBUTES);
+            ui.add(egui::TextEdit::singleline(&mut new_value).hint_text("value"));
+            let is_complete = !name.trim().is_empty() && !new_value.trim().is_empty();
+            if ui
+                .add_enabled(is_complete, egui::Button::new("+ declaration").small())
+                .on_hover_text("Add a declaration")
+                .clicked()
+            {
+                declarations.push(StyleDeclaration {
+                    name: name.trim().to_string(),
+                    value: new_value.trim().to_string(),
+                    important: false,
+                });
+                name.clear();
+                new_value.clear();
+            }
+            ui.data_mut(|data| data.insert_temp(new_id, (name, new_value)));
+        });
+    });
+    if let Some(idx) = removed {
+        declarations.remove(idx);
+    }
+    if let Some((name, opacity)) = opacity_edit {
+        match declarations.iter_mut().find(|d| d.name == name) {
+            Some(declaration) => declaration.value = opacity,
+            None => declarations.push(StyleDeclaration {
+                name: name.to_string(),
+                value: opacity,
+                important: false,
+            }),
+        }
+    }
+    if declarations == before {
+        return false;
+    }
+    *value = write_style_with_rest(&declarations, &rest);
+    true
+}
@@ -1,10 +1,11 @@
 //! Central panel
 use bladvak::{
-    eframe::egui::{self, Pos2, Rect},
+    eframe::egui::{self, Pos2, Rect, Stroke, StrokeKind, Vec2},
     log,
 };
 
 use crate::GalagoApp;
+use crate::svg_render::SvgRender;
 
 impl GalagoApp {
     /// Central panel
@@ -33,10 +34,19 @@ impl GalagoApp {
                 false
             }
         };
+        let onion_skin = self.diff_viewer.onion_skin;
+        if onion_skin {
+            self.update_saved_svg(ui.ctx());
+        }
+        let document_index = self.documents.get_current_index();
         let Some(document) = self.documents.get_current_doc_mut() else {
             return;
         };
+        self.tree_viewer.sync_document(document_index, document);
         document.svg_is_valid = svg_is_valid;
+        let selection_boxes =
+            self.tree_viewer
+                .selection_boxes(ui.ctx(), &document.svg, &self.usvg_options);
         let rect = ui.available_rect_before_wrap();
         let response = egui::Scene::new()
             .max_inner_size([350.0, 1000.0])
@@ -47,16 +57,80 @@ impl GalagoApp {
                 if bg_r.rect.is_finite() {
                     self.grid.draw(&bg_r.rect, painter);
                 }
-                let _response = document.svg_render.show(ui);
+                if let Ok(response) = document.svg_render.show(ui) {
+                    if onion_skin {
+                        document.saved_render.paint_overlay(
+                            ui,
+                            response.rect.min,
+                            self.diff_viewer.onion_opacity,
+                        );
+                    }
+                    // highlight the nodes selected in the tree viewer
+                    let stroke = Stroke::new(1.0, ui.visuals().selection.stroke.color);
+                    for bbox in selection_boxes {
+                        let rect = Rect::from_min_max(
+                            response.rect.min + Vec2::new(bbox.left(), bbox.top()),
+                            response.rect.min + Vec2::new(bbox.right(), bbox.bottom()),
+                        );
+                        ui.painter()
+                            .rect_stroke(rect, 0.0, stroke, StrokeKind::Outside);
+                    }
+                }
                 // if response.clicked() {
                 //     println!("SVG clicked!");
                 // }
             })
             .response;
 
+        paint_render_status(ui, rect, &document.svg_render, &document.svg);
         if document.should_reset_view || response.double_clicked() {
             let real_rect = Rect::from_two_pos(Pos2::ZERO, (rect.max - rect.min).to_pos2());
             document.scene_rect = real_rect;
         }
     }
 }
+
+/// Paint the state of the render over the viewer: parse error, outdated
+/// texture, progress of the render job and downscaling
+fn paint_render_status(ui: &egui::Ui, rect: Rect, render: &SvgRender, svg: &str) {
+    let badge = if let Some(error) = render.error(svg) {
+        let text = if render.texture().is_some() {
+            format!(
+                "Invalid svg (line {}), showing the last valid version",
+                error.line
+            )
+        } else {
+            format!("Invalid svg (line {})", error.line)
+        };
+        Some((text, ui.visuals().error_fg_color))
+    } else if let Some(progress) = render.progress() {
+        // thin bar along the top of the viewer
+        let mut bar = rect;
+        bar.set_height(3.0);
+        bar.set_width(rect.width() * progress);
+        ui.painter()
+            .rect_filled(bar, 0.0, ui.visuals().selection.bg_fill);
+        let text = if render.is_outdated(svg) {
+            "Outdated, rendering"
+        } else {
+            "Rendering"
+        };
+        let percent = (progress * 100.0).round();
+        Some((format!("{text} {percent}%"), ui.visuals().warn_fg_color))
+    } else if render.is_outdated(svg) {
+        Some(("Outdated".to_string(), ui.visuals().warn_fg_color))
+    } else {
+        render
+            .warning()
+            .map(|warning| (warning.to_string(), ui.visuals().warn_fg_color))
+    };
+    if let Some((text, color)) = badge {
+        ui.painter().text(
+            rect.left_top() + Vec2::splat(8.0),
+            egui::Align2::LEFT_TOP,
+            text,
+            egui::FontId::proportional(14.0),
+            color,
+        );
+    }
+}
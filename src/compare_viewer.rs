@@ -0,0 +1,387 @@
+//! Compare Viewer
+//! Compare two open documents
+
+use bladvak::ErrorManager;
+use bladvak::app::BladvakPanel;
+use bladvak::eframe::egui::{
+    self, Color32, ImageData, Rect, Sense, Stroke, TextureHandle, TextureOptions, Vec2, pos2,
+};
+use bladvak::utils::document::DocumentTrait;
+use resvg::usvg;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+
+use crate::GalagoApp;
+use crate::diff_viewer::DiffCache;
+use crate::document::Document;
+use crate::render::{
+    VisualDiff, auto_scaler, common_scale, compare_pixmaps, copy_options, diff_heatmap,
+    pad_to_same_size, render_tree_at,
+};
+use crate::render_job::{BackgroundJob, max_texture_side};
+use crate::svg_render::SvgRender;
+
+/// Kind of comparison shown in the panel
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareMode {
+    /// Different pixels in red over the left render
+    Heatmap,
+    /// Left render on one side of a slider, right render on the other
+    Swipe,
+    /// Added, removed and changed elements and attributes
+    Structure,
+}
+
+/// Heatmap of two renders padded to the same size
+struct HeatmapImage {
+    /// Heatmap texture
+    texture: TextureHandle,
+    /// Scale of the texture, in pixels per svg unit
+    scale: f32,
+    /// Pixel statistics
+    diff: VisualDiff,
+}
+
+/// Heatmap computed in the background, before its texture is loaded
+struct HeatmapPixels {
+    /// Heatmap image
+    image: egui::ColorImage,
+    /// Scale of the image, in pixels per svg unit
+    scale: f32,
+    /// Pixel statistics
+    diff: VisualDiff,
+}
+
+/// Result of the heatmap computation
+type HeatmapResult = Result<HeatmapPixels, String>;
+
+/// Heatmap texture computed once for a pair of svg strings
+struct Heatmap {
+    /// Hash of the compared strings and tolerance
+    key: u64,
+    /// Computation running in the background
+    job: Option<BackgroundJob<HeatmapResult>>,
+    /// Heatmap, or why it cannot be computed, once the job is done
+    result: Option<Result<HeatmapImage, String>>,
+}
+
+impl Heatmap {
+    /// Load the texture of the heatmap once the job is done
+    fn poll(&mut self, ctx: &egui::Context) {
+        let Some(job) = &mut self.job else {
+            return;
+        };
+        let result = match job.poll() {
+            Ok(Some(result)) => result,
+            Ok(None) => return,
+            Err(()) => Err("The heatmap computation stopped".to_string()),
+        };
+        self.job = None;
+        self.result = Some(result.map(|pixels| HeatmapImage {
+            texture: ctx.load_texture(
+                "heatmap",
+                ImageData::Color(Arc::new(pixels.image)),
+                TextureOptions::default(),
+            ),
+            scale: pixels.scale,
+            diff: pixels.diff,
+        }));
+    }
+}
+
+/// `CompareViewer` Struct
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct CompareViewer {
+    /// Index of the left document
+    left: usize,
+    /// Index of the right document
+    right: usize,
+    /// Kind of comparison shown
+    mode: CompareMode,
+    /// Position of the swipe slider, between 0 and 1
+    swipe: f32,
+    /// Channel difference ignored by the heatmap
+    channel_tolerance: u8,
+    /// Render of the left document
+    #[serde(skip)]
+    left_render: SvgRender,
+    /// Render of the right document
+    #[serde(skip)]
+    right_render: SvgRender,
+    /// Heatmap of the differences
+    #[serde(skip)]
+    heatmap: Option<Heatmap>,
+    /// Structural diff between the documents
+    #[serde(skip)]
+    structure: DiffCache,
+}
+
+impl Default for CompareViewer {
+    fn default() -> Self {
+        Self {
+            left: 0,
+            right: 1,
+            mode: CompareMode::Heatmap,
+            swipe: 0.5,
+            channel_tolerance: 16,
+            left_render: SvgRender::default(),
+            right_render: SvgRender::default(),
+            heatmap: None,
+            structure: DiffCache::default(),
+        }
+    }
+}
+
+/// Combo box to pick a document
+fn document_combo(ui: &mut egui::Ui, label: &str, selected: &mut usize, documents: &[&Document]) {
+    let selected_text = documents
+        .get(*selected)
+        .map(|doc| doc.name().to_string())
+        .unwrap_or_default();
+    egui::ComboBox::from_label(label)
+        .selected_text(selected_text)
+        .show_ui(ui, |ui| {
+            for (idx, doc) in documents.iter().enumerate() {
+                ui.selectable_value(selected, idx, doc.name().to_string());
+            }
+        });
+}
+
+/// Render both svg at the same scale and build the heatmap image, the scale
+/// being reduced so that the renders fit in `max_side` pixels per side
+///
+/// The smaller render is padded so that both are compared from their top left corner.
+fn compute_heatmap(
+    left: &str,
+    right: &str,
+    options: &usvg::Options<'_>,
+    channel_tolerance: u8,
+    max_side: u32,
+) -> HeatmapResult {
+    let left = usvg::Tree::from_str(left, options).map_err(|e| format!("Left document: {e}"))?;
+    let right = usvg::Tree::from_str(right, options).map_err(|e| format!("Right document: {e}"))?;
+    let scale = common_scale(&left, &right, auto_scaler(left.size()), max_side);
+    let (left, right) = pad_to_same_size(
+        render_tree_at(&left, scale)?,
+        render_tree_at(&right, scale)?,
+    )?;
+    let diff = compare_pixmaps(&left, &right, channel_tolerance)?;
+    let heatmap = diff_heatmap(&left, &right, channel_tolerance)?;
+    let image = egui::ColorImage::from_rgba_premultiplied(
+        [heatmap.width() as _, heatmap.height() as _],
+        heatmap.data(),
+    );
+    Ok(HeatmapPixels { image, scale, diff })
+}
+
+/// Size of a texture rendered with `scale`, in svg units
+#[allow(clippy::cast_precision_loss)]
+fn svg_size(texture: &TextureHandle, scale: f32) -> Vec2 {
+    let [w, h] = texture.size();
+    Vec2::new(w as f32, h as f32) / scale
+}
+
+/// Allocate a rect of the available width keeping the aspect ratio of `size`,
+/// returns it with the number of points per svg unit
+fn allocate_frame(ui: &mut egui::Ui, size: Vec2) -> (Rect, f32, egui::Response) {
+    let zoom = ui.available_width() / size.x.max(f32::EPSILON);
+    let (rect, response) = ui.allocate_exact_size(size * zoom, Sense::click_and_drag());
+    (rect, zoom, response)
+}
+
+impl CompareViewer {
+    /// Show the heatmap over a faded left render
+    fn show_heatmap(&self, ui: &mut egui::Ui) {
+        let (Some(left), Some(heatmap)) = (self.left_render.texture(), &self.heatmap) else {
+            return;
+        };
+        let HeatmapImage {
+            texture,
+            scale,
+            diff,
+        } = match &heatmap.result {
+            Some(Ok(result)) => result,
+            Some(Err(e)) => {
+                ui.colored_label(Color32::RED, e);
+                return;
+            }
+            None => {
+                ui.spinner();
+                return;
+            }
+        };
+        let Some(left_size) = self.left_render.size() else {
+            return;
+        };
+        ui.label(format!(
+            "{} pixels differ ({:.2}%), max channel difference {}",
+            diff.different_pixels,
+            diff.different_ratio() * 100.0,
+            diff.max_channel_difference
+        ));
+        let (rect, zoom, _response) = allocate_frame(ui, svg_size(texture, *scale));
+        let uv = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+        let painter = ui.painter();
+        painter.rect_filled(rect, 0.0, Color32::WHITE);
+        painter.image(
+            left.id(),
+            Rect::from_min_size(rect.min, left_size * zoom),
+            uv,
+            Color32::WHITE.gamma_multiply(0.3),
+        );
+        painter.image(texture.id(), rect, uv, Color32::WHITE);
+    }
+
+    /// Show the left render before the slider and the right render after it
+    fn show_swipe(&mut self, ui: &mut egui::Ui) {
+        let (Some(left), Some(right)) = (self.left_render.texture(), self.right_render.texture())
+        else {
+            return;
+        };
+        let (Some(left_size), Some(right_size)) =
+            (self.left_render.size(), self.right_render.size())
+        else {
+            return;
+        };
+        ui.add(egui::Slider::new(&mut self.swipe, 0.0..=1.0).text("Swipe"));
+        // both renders keep their own size, from the top left corner of the frame
+        let (rect, zoom, response) = allocate_frame(ui, left_size.max(right_size));
+        if let Some(pos) = response.interact_pointer_pos() {
+            self.swipe = ((pos.x - rect.min.x) / rect.width()).clamp(0.0, 1.0);
+        }
+        let split = rect.min.x + rect.width() * self.swipe;
+        let uv = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+        let painter = ui.painter();
+        painter.rect_filled(rect, 0.0, Color32::WHITE);
+        painter
+            .with_clip_rect(Rect::from_min_max(rect.min, pos2(split, rect.max.y)))
+            .image(
+                left.id(),
+                Rect::from_min_size(rect.min, left_size * zoom),
+                uv,
+                Color32::WHITE,
+            );
+        painter
+            .with_clip_rect(Rect::from_min_max(pos2(split, rect.min.y), rect.max))
+            .image(
+                right.id(),
+                Rect::from_min_size(rect.min, right_size * zoom),
+                uv,
+                Color32::WHITE,
+            );
+        painter.vline(
+            split,
+            rect.y_range(),
+            Stroke::new(2.0, ui.visuals().selection.bg_fill),
+        );
+    }
+}
+
+impl GalagoApp {
+    /// Show the comparison between two documents
+    pub fn show_compare(&mut self, ui: &mut egui::Ui, error_manager: &mut ErrorManager) {
+        let documents = self.documents.iter().collect::<Vec<_>>();
+        if documents.len() < 2 {
+            ui.label("Open at least two documents to compare them");
+            return;
+        }
+        let viewer = &mut self.compare_viewer;
+        document_combo(ui, "Left", &mut viewer.left, &documents);
+        document_combo(ui, "Right", &mut viewer.right, &documents);
+        let (Some(left), Some(right)) = (documents.get(viewer.left), documents.get(viewer.right))
+        else {
+            viewer.left = 0;
+            viewer.right = 1;
+            return;
+        };
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut viewer.mode, CompareMode::Heatmap, "Heatmap");
+            ui.selectable_value(&mut viewer.mode, CompareMode::Swipe, "Swipe");
+            ui.selectable_value(&mut viewer.mode, CompareMode::Structure, "Structure");
+        });
+        if viewer.mode == CompareMode::Structure {
+            viewer.structure.update(&left.svg, &right.svg);
+            viewer.structure.show_changes(ui);
+            return;
+        }
+        for (render, document) in [
+            (&mut viewer.left_render, left),
+            (&mut viewer.right_render, right),
+        ] {
+            if let Err(Some(err)) = render.update(ui.ctx(), &document.svg, &self.usvg_options) {
+                error_manager.add_error(err);
+            }
+        }
+        match viewer.mode {
+            CompareMode::Heatmap => {
+                let mut hasher = DefaultHasher::new();
+                (&left.svg, &right.svg, viewer.channel_tolerance).hash(&mut hasher);
+                let key = hasher.finish();
+                if viewer.heatmap.as_ref().is_none_or(|h| h.key != key) {
+                    let (left, right) = (left.svg.clone(), right.svg.clone());
+                    let options = copy_options(&self.usvg_options);
+                    let channel_tolerance = viewer.channel_tolerance;
+                    let max_side = max_texture_side(ui.ctx());
+                    // replacing the heatmap cancels the running job
+                    viewer.heatmap = Some(Heatmap {
+                        key,
+                        job: Some(BackgroundJob::start(ui.ctx(), move |_| {
+                            Some(compute_heatmap(
+                                &left,
+                                &right,
+                                &options,
+                                channel_tolerance,
+                                max_side,
+                            ))
+                        })),
+                        result: None,
+                    });
+                }
+                if let Some(heatmap) = &mut viewer.heatmap {
+                    heatmap.poll(ui.ctx());
+                }
+                viewer.show_heatmap(ui);
+            }
+            CompareMode::Swipe => viewer.show_swipe(ui),
+            CompareMode::Structure => {}
+        }
+    }
+}
+
+/// Compare viewer panel
+#[derive(Debug)]
+pub struct CompareViewerPanel;
+
+impl BladvakPanel for CompareViewerPanel {
+    type App = GalagoApp;
+
+    fn name(&self) -> &'static str {
+        "SVG compare"
+    }
+
+    fn has_settings(&self) -> bool {
+        true
+    }
+
+    fn ui_settings(
+        &self,
+        app: &mut Self::App,
+        ui: &mut egui::Ui,
+        _error_manager: &mut ErrorManager,
+    ) {
+        ui.add(
+            egui::Slider::new(&mut app.compare_viewer.channel_tolerance, 0..=255)
+                .text("Channel tolerance"),
+        )
+        .on_hover_text("Channel difference ignored by the heatmap (anti-aliasing)");
+    }
+
+    fn has_ui(&self) -> bool {
+        true
+    }
+
+    fn ui(&self, app: &mut Self::App, ui: &mut egui::Ui, error_manager: &mut ErrorManager) {
+        app.show_compare(ui, error_manager);
+    }
+}
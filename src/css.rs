@@ -0,0 +1,436 @@
+//! Css
+//! Rules of the embedded `<style>` stylesheets: matching, cascade and inlining
+
+use simplecss::{AttributeOperator, PseudoClass, StyleSheet};
+use xmltree::{Element, XMLNode};
+
+use crate::node::{children_at, node_at, node_at_mut};
+use crate::style::{StyleDeclaration, is_presentation_attribute, parse_style, write_style};
+
+/// A rule of the stylesheets matching an element
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchedRule {
+    /// Selector of the rule
+    pub selector: String,
+    /// Specificity of the selector: ids, classes and attributes, types
+    pub specificity: [u8; 3],
+    /// Declarations of the rule
+    pub declarations: Vec<StyleDeclaration>,
+}
+
+/// Where the computed value of a property comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueSource {
+    /// Presentation attribute
+    Attribute,
+    /// Stylesheet rule with this selector
+    Rule(String),
+    /// `style` attribute
+    Style,
+}
+
+impl std::fmt::Display for ValueSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Attribute => write!(f, "attribute"),
+            Self::Rule(selector) => write!(f, "{selector}"),
+            Self::Style => write!(f, "style"),
+        }
+    }
+}
+
+/// Value of a property after the cascade
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComputedProperty {
+    /// Property name
+    pub name: String,
+    /// Winning value
+    pub value: String,
+    /// Origin of the winning value
+    pub source: ValueSource,
+    /// The winning declaration is `!important`
+    pub important: bool,
+}
+
+/// An element of the tree, with its path to find its parent and siblings
+#[derive(Clone)]
+struct TreeElement<'a> {
+    /// Root element of the tree
+    root: &'a Element,
+    /// Index path of the element
+    path: Vec<usize>,
+    /// The element itself
+    element: &'a Element,
+}
+
+impl<'a> TreeElement<'a> {
+    /// Element at `path`, `None` if it is not an element
+    fn new(root: &'a Element, path: Vec<usize>) -> Option<Self> {
+        let element = if path.is_empty() {
+            root
+        } else {
+            node_at(root, &path)?.as_element()?
+        };
+        Some(Self {
+            root,
+            path,
+            element,
+        })
+    }
+}
+
+impl simplecss::Element for TreeElement<'_> {
+    fn parent_element(&self) -> Option<Self> {
+        let (_, parent) = self.path.split_last()?;
+        Self::new(self.root, parent.to_vec())
+    }
+
+    fn prev_sibling_element(&self) -> Option<Self> {
+        let (index, parent) = self.path.split_last()?;
+        let siblings = children_at(self.root, parent)?;
+        let sibling = (0..*index)
+            .rev()
+            .find(|i| matches!(siblings.get(*i), Some(XMLNode::Element(_))))?;
+        let mut path = parent.to_vec();
+        path.push(sibling);
+        Self::new(self.root, path)
+    }
+
+    fn has_local_name(&self, name: &str) -> bool {
+        self.element.name == name
+    }
+
+    fn attribute_matches(&self, local_name: &str, operator: AttributeOperator<'_>) -> bool {
+        self.element
+            .attributes
+            .get(local_name)
+            .is_some_and(|value| operator.matches(value))
+    }
+
+    fn pseudo_class_matches(&self, class: PseudoClass<'_>) -> bool {
+        // dynamic pseudo-classes never match a static document
+        class == PseudoClass::FirstChild && self.prev_sibling_element().is_none()
+    }
+}
+
+/// Check if the element is a css `<style>` element
+fn is_css_style(element: &Element) -> bool {
+    element.name == "style"
+        && element
+            .attributes
+            .get("type")
+            .is_none_or(|kind| kind == "text/css")
+}
+
+/// Text of all the css `<style>` elements of the document
+#[must_use]
+pub fn stylesheet_text(root: &Element) -> String {
+    let mut text = String::new();
+    if is_css_style(root) {
+        for child in &root.children {
+            if let XMLNode::Text(content) | XMLNode::CData(content) = child {
+                text.push_str(content);
+                text.push('\n');
+            }
+        }
+    }
+    for child in &root.children {
+        if let XMLNode::Element(child) = child {
+            text.push_str(&stylesheet_text(child));
+        }
+    }
+    text
+}
+
+/// Rules of `stylesheet` matching the element at `path`, from the lowest to
+/// the highest priority
+#[must_use]
+pub fn matched_rules(root: &Element, stylesheet: &str, path: &[usize]) -> Vec<MatchedRule> {
+    let Some(element) = TreeElement::new(root, path.to_vec()) else {
+        return Vec::new();
+    };
+    // the rules are sorted by specificity, then by order in the stylesheet
+    StyleSheet::parse(stylesheet)
+        .rules
+        .iter()
+        .filter(|rule| rule.selector.matches(&element))
+        .map(|rule| MatchedRule {
+            selector: rule.selector.to_string(),
+            specificity: rule.selector.specificity(),
+            declarations: rule
+                .declarations
+                .iter()
+                .map(|declaration| StyleDeclaration {
+                    name: declaration.name.to_string(),
+                    value: declaration.value.to_string(),
+                    important: declaration.important,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Values of the properties set on the element at `path`, sorted by name
+///
+/// The cascade goes from the presentation attributes to the rules, then to the
+/// `style` attribute, `!important` declarations winning over normal ones.
+#[must_use]
+pub fn computed_properties(
+    root: &Element,
+    stylesheet: &str,
+    path: &[usize],
+) -> Vec<ComputedProperty> {
+    let Some(element) = TreeElement::new(root, path.to_vec()).map(|e| e.element) else {
+        return Vec::new();
+    };
+    let attributes = element
+        .attributes
+        .iter()
+        .filter(|(name, _)| is_presentation_attribute(name))
+        .map(|(name, value)| ComputedProperty {
+            name: name.clone(),
+            value: value.clone(),
+            source: ValueSource::Attribute,
+            important: false,
+        });
+    let rules = matched_rules(root, stylesheet, path)
+        .into_iter()
+        .flat_map(|rule| {
+            rule.declarations
+                .into_iter()
+                .map(move |declaration| ComputedProperty {
+                    name: declaration.name,
+                    value: declaration.value,
+                    source: ValueSource::Rule(rule.selector.clone()),
+                    important: declaration.important,
+                })
+        });
+    let style = element
+        .attributes
+        .get("style")
+        .map(|style| parse_style(style))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|declaration| ComputedProperty {
+            name: declaration.name,
+            value: declaration.value,
+            source: ValueSource::Style,
+            important: declaration.important,
+        });
+    let mut computed = cascade(attributes.chain(rules).chain(style));
+    computed.sort_by(|a, b| a.name.cmp(&b.name));
+    computed
+}
+
+/// Winning value of each property, `properties` going from the lowest to the
+/// highest priority, `!important` declarations winning over normal ones
+fn cascade(properties: impl IntoIterator<Item = ComputedProperty>) -> Vec<ComputedProperty> {
+    let mut computed: Vec<ComputedProperty> = Vec::new();
+    for property in properties {
+        match computed.iter_mut().find(|p| p.name == property.name) {
+            Some(current) if current.important && !property.important => {}
+            Some(current) => *current = property,
+            None => computed.push(property),
+        }
+    }
+    computed
+}
+
+/// Check if the stylesheet has selectors with pseudo-classes that never match
+/// a static document (`:hover`, `:nth-child()`...), their rules cannot be inlined
+#[must_use]
+pub fn has_unmatchable_selectors(stylesheet: &str) -> bool {
+    let mut depth = 0_usize;
+    let mut selectors = String::new();
+    for c in stylesheet.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => selectors.push(c),
+            _ => {}
+        }
+    }
+    selectors
+        .split(':')
+        .skip(1)
+        .any(|pseudo| !pseudo.starts_with("first-child"))
+}
+
+/// Paths of all the elements of the tree, the root being the empty path
+pub(crate) fn element_paths(element: &Element, path: &mut Vec<usize>, paths: &mut Vec<Vec<usize>>) {
+    paths.push(path.clone());
+    for (index, child) in element.children.iter().enumerate() {
+        if let XMLNode::Element(child) = child {
+            path.push(index);
+            element_paths(child, path, paths);
+            path.pop();
+        }
+    }
+}
+
+/// Remove the css `<style>` elements from the tree
+fn remove_css_styles(element: &mut Element) {
+    element
+        .children
+        .retain(|child| child.as_element().is_none_or(|e| !is_css_style(e)));
+    for child in &mut element.children {
+        if let XMLNode::Element(child) = child {
+            remove_css_styles(child);
+        }
+    }
+}
+
+/// Write the declarations of the matching rules on each element and remove
+/// the `<style>` elements, returns the number of changed elements
+///
+/// Presentation properties become attributes, the others go in the `style`
+/// attribute. Stylesheets with at-rules (`@media`, `@font-face`...) or with
+/// unmatchable selectors (`:hover`...) are kept.
+pub fn inline_css(root: &mut Element) -> usize {
+    let stylesheet = stylesheet_text(root);
+    if stylesheet.trim().is_empty() {
+        return 0;
+    }
+    let mut paths = Vec::new();
+    element_paths(root, &mut Vec::new(), &mut paths);
+    // match everything before editing, rules may select on attributes
+    let inlined = paths
+        .into_iter()
+        .map(|path| {
+            let mut computed = computed_properties(root, &stylesheet, &path);
+            computed.retain(|property| matches!(property.source, ValueSource::Rule(_)));
+            (path, computed)
+        })
+        .filter(|(_, computed)| !computed.is_empty())
+        .collect::<Vec<_>>();
+    let count = inlined.len();
+    for (path, computed) in inlined {
+        let element = if path.is_empty() {
+            &mut *root
+        } else {
+            match node_at_mut(root, &path) {
+                Some(XMLNode::Element(element)) => element,
+                _ => continue,
+            }
+        };
+        inline_rules(element, computed);
+    }
+    if !stylesheet.contains('@') && !has_unmatchable_selectors(&stylesheet) {
+        remove_css_styles(root);
+    }
+    count
+}
+
+/// Write the properties won by rules on the element
+fn inline_rules(element: &mut Element, computed: Vec<ComputedProperty>) {
+    let mut style = element
+        .attributes
+        .get("style")
+        .map(|style| parse_style(style))
+        .unwrap_or_default();
+    let mut added_style = Vec::new();
+    for property in computed {
+        // a losing declaration of the style attribute would win once inlined
+        style.retain(|d| d.name != property.name);
+        if is_presentation_attribute(&property.name) {
+            element.attributes.insert(property.name, property.value);
+        } else {
+            added_style.push(StyleDeclaration {
+                name: property.name,
+                value: property.value,
+                important: property.important,
+            });
+        }
+    }
+    added_style.append(&mut style);
+    if added_style.is_empty() {
+        element.attributes.shift_remove("style");
+    } else {
+        element
+            .attributes
+            .insert("style".to_string(), write_style(&added_style));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    const SVG: &str = r#"<svg><style><![CDATA[
+        rect { fill: red; stroke: black }
+        .a { fill: blue; mix-blend-mode: multiply }
+        #b { fill: green }
+        g > rect:first-child { stroke-width: 2 }
+    ]]></style><g><rect class="a" id="b" fill="yellow"/><rect class="a" style="fill:pink"/></g></svg>"#;
+
+    #[test]
+    fn test_matched_rules() {
+        let root = Element::parse(SVG.as_bytes()).unwrap();
+        let stylesheet = stylesheet_text(&root);
+        let rules = matched_rules(&root, &stylesheet, &[1, 0]);
+        let selectors = rules
+            .iter()
+            .map(|r| r.selector.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(selectors.len(), 4);
+        assert_eq!(rules[0].specificity, [0, 0, 1]);
+        assert_eq!(rules[3].specificity, [1, 0, 0]);
+        assert_eq!(matched_rules(&root, &stylesheet, &[1, 1]).len(), 2);
+
+        let computed = computed_properties(&root, &stylesheet, &[1, 0]);
+        let fill = computed.iter().find(|p| p.name == "fill").unwrap();
+        assert_eq!(fill.value, "green");
+        assert!(matches!(fill.source, ValueSource::Rule(_)));
+        let computed = computed_properties(&root, &stylesheet, &[1, 1]);
+        let fill = computed.iter().find(|p| p.name == "fill").unwrap();
+        assert_eq!(fill.value, "pink");
+        assert_eq!(fill.source, ValueSource::Style);
+    }
+
+    #[test]
+    fn test_inline_css() {
+        let mut root = Element::parse(SVG.as_bytes()).unwrap();
+        assert_eq!(inline_css(&mut root), 2);
+        assert!(root.get_child("style").is_none());
+        let g = root.get_child("g").unwrap();
+        let first = g.children[0].as_element().unwrap();
+        assert_eq!(first.attributes["fill"], "green");
+        assert_eq!(first.attributes["stroke"], "black");
+        assert_eq!(first.attributes["stroke-width"], "2");
+        assert_eq!(first.attributes["style"], "mix-blend-mode:multiply");
+        let second = g.children[1].as_element().unwrap();
+        // the style attribute wins over the rules
+        assert!(!second.attributes.contains_key("fill"));
+        assert_eq!(second.attributes["stroke"], "black");
+        assert_eq!(
+            second.attributes["style"],
+            "mix-blend-mode:multiply;fill:pink"
+        );
+    }
+
+    #[test]
+    fn test_inline_css_important() {
+        let svg = r#"<svg><style>rect { fill: red !important } #b { fill: green }</style><rect id="b" style="fill:blue"/></svg>"#;
+        let mut root = Element::parse(svg.as_bytes()).unwrap();
+        assert_eq!(inline_css(&mut root), 1);
+        let rect = root.get_child("rect").unwrap();
+        assert_eq!(rect.attributes["fill"], "red");
+        // the losing declaration of the style attribute is removed
+        assert!(!rect.attributes.contains_key("style"));
+    }
+
+    #[test]
+    fn test_inline_css_unmatchable() {
+        assert!(has_unmatchable_selectors("rect:hover { fill: red }"));
+        assert!(has_unmatchable_selectors("rect:nth-child(2) { fill: red }"));
+        assert!(!has_unmatchable_selectors(
+            "rect:first-child { fill: url(#a) }"
+        ));
+        let svg = "<svg><style>rect { fill: red } rect:hover { fill: blue }</style><rect/></svg>";
+        let mut root = Element::parse(svg.as_bytes()).unwrap();
+        assert_eq!(inline_css(&mut root), 1);
+        assert_eq!(root.get_child("rect").unwrap().attributes["fill"], "red");
+        assert!(root.get_child("style").is_some());
+    }
+}
@@ -0,0 +1,150 @@
+//! Css Viewer
+//! Panel showing the stylesheet rules applied to the selected element
+
+use bladvak::ErrorManager;
+use bladvak::app::BladvakPanel;
+use bladvak::eframe::egui::{self, Color32};
+use bladvak::egui_extras::{Column, TableBuilder};
+
+use crate::GalagoApp;
+use crate::css::{
+    computed_properties, has_unmatchable_selectors, inline_css, matched_rules, stylesheet_text,
+};
+
+impl GalagoApp {
+    /// Show the rules and computed values of the element selected in the tree viewer
+    pub fn show_css_rules(&mut self, ui: &mut egui::Ui, error_manager: &mut ErrorManager) {
+        let document_index = self.documents.get_current_index();
+        let Some(document) = self.documents.get_current_doc_mut() else {
+            return;
+        };
+        self.tree_viewer.sync_document(document_index, document);
+        let root = match document.tree.sync(&document.svg) {
+            Ok(root) => root,
+            Err(e) => {
+                ui.label(format!("Error: {e}"));
+                return;
+            }
+        };
+        let stylesheet = stylesheet_text(root);
+        if stylesheet.trim().is_empty() {
+            ui.label("No <style> element in the document");
+            return;
+        }
+        if ui
+            .button("Inline CSS")
+            .on_hover_text(
+                "Write the matching rules on each element and remove the <style> elements",
+            )
+            .clicked()
+        {
+            if inline_css(root) > 0 {
+                // removing the <style> elements shifts the paths
+                self.tree_viewer.selection.clear();
+                if let Err(err) = document.tree.write(&mut document.svg) {
+                    error_manager.add_error(err);
+                }
+            }
+            return;
+        }
+        if has_unmatchable_selectors(&stylesheet) {
+            ui.weak("Rules with dynamic pseudo-classes (:hover...) cannot be inlined, the <style> elements are kept");
+        }
+        ui.separator();
+        let [path] = self.tree_viewer.selection.as_slice() else {
+            ui.label("Select one element in the tree viewer");
+            return;
+        };
+        let rules = matched_rules(root, &stylesheet, path);
+        ui.heading("Rules");
+        if rules.is_empty() {
+            ui.label("No rule matches the selected element");
+        }
+        for rule in &rules {
+            let [ids, classes, types] = rule.specificity;
+            ui.horizontal(|ui| {
+                ui.monospace(&rule.selector);
+                ui.weak(format!("({ids},{classes},{types})"))
+                    .on_hover_text("Specificity: ids, classes and attributes, types");
+            });
+            for declaration in &rule.declarations {
+                let important = if declaration.important {
+                    " !important"
+                } else {
+                    ""
+                };
+                ui.monospace(format!(
+                    "    {}: {}{important}",
+                    declaration.name, declaration.value
+                ));
+            }
+        }
+        ui.separator();
+        ui.heading("Computed values");
+        let computed = computed_properties(root, &stylesheet, path);
+        TableBuilder::new(ui)
+            .id_salt("computed_values")
+            .column(Column::auto())
+            .column(Column::auto())
+            .column(Column::remainder())
+            .header(20.0, |mut header| {
+                for title in ["property", "value", "from"] {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+            })
+            .body(|mut body| {
+                for property in &computed {
+                    body.row(18.0, |mut row| {
+                        row.col(|ui| {
+                            ui.label(&property.name);
+                        });
+                        row.col(|ui| {
+                            ui.monospace(&property.value);
+                        });
+                        row.col(|ui| {
+                            let source = property.source.to_string();
+                            if property.important {
+                                ui.colored_label(Color32::ORANGE, format!("{source} !important"));
+                            } else {
+                                ui.label(source);
+                            }
+                        });
+                    });
+                }
+            });
+    }
+}
+
+/// Css rules panel
+#[derive(Debug)]
+pub struct CssViewerPanel;
+
+impl BladvakPanel for CssViewerPanel {
+    type App = GalagoApp;
+
+    fn name(&self) -> &'static str {
+        "CSS rules"
+    }
+
+    fn has_settings(&self) -> bool {
+        false
+    }
+
+    fn ui_settings(
+        &self,
+        _app: &mut Self::App,
+        _ui: &mut egui::Ui,
+        _error_manager: &mut ErrorManager,
+    ) {
+    }
+
+    fn has_ui(&self) -> bool {
+        true
+    }
+
+    fn ui(&self, app: &mut Self::App, ui: &mut egui::Ui, error_manager: &mut ErrorManager) {
+        app.show_css_rules(ui, error_manager);
+    }
+}
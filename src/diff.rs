@@ -0,0 +1,410 @@
+//! Diff
+//! Line and structural diff between two svg strings
+
+use xmltree::{Element, XMLNode};
+
+/// Maximum number of cells of the LCS table, bigger inputs only match their common ends
+const MAX_LCS_CELLS: usize = 16_000_000;
+
+/// A line of a diff
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    /// Line present in both texts
+    Same(&'a str),
+    /// Line only in the old text
+    Removed(&'a str),
+    /// Line only in the new text
+    Added(&'a str),
+}
+
+/// Diff two texts line by line
+///
+/// Common prefix and suffix are matched first, then the middle is diffed
+/// with a longest common subsequence.
+#[must_use]
+pub fn line_diff<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old_lines = old.lines().collect::<Vec<_>>();
+    let new_lines = new.lines().collect::<Vec<_>>();
+    let prefix = old_lines
+        .iter()
+        .zip(&new_lines)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old_lines[prefix..old_lines.len() - suffix];
+    let new_middle = &new_lines[prefix..new_lines.len() - suffix];
+
+    let mut diff = old_lines[..prefix]
+        .iter()
+        .map(|line| DiffLine::Same(line))
+        .collect::<Vec<_>>();
+    diff.extend(lcs_diff(old_middle, new_middle));
+    diff.extend(
+        old_lines[old_lines.len() - suffix..]
+            .iter()
+            .map(|line| DiffLine::Same(line)),
+    );
+    diff
+}
+
+/// Alignment step between two sequences
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Align {
+    /// Items at these indexes are equal
+    Both(usize, usize),
+    /// Item only in the old sequence
+    Old(usize),
+    /// Item only in the new sequence
+    New(usize),
+}
+
+/// Align two sequences with a longest common subsequence table
+///
+/// If the table would be bigger than [`MAX_LCS_CELLS`], only the common prefix
+/// and suffix are matched.
+pub(crate) fn lcs_align<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Align> {
+    if (old.len() + 1).saturating_mul(new.len() + 1) > MAX_LCS_CELLS {
+        return align_ends(old, new);
+    }
+    let width = new.len() + 1;
+    // table[i * width + j] = LCS length of old[i..] and new[j..]
+    let mut table = vec![0usize; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            table[i * width + j] = if old[i] == new[j] {
+                table[(i + 1) * width + j + 1] + 1
+            } else {
+                table[(i + 1) * width + j].max(table[i * width + j + 1])
+            };
+        }
+    }
+    let mut alignment = Vec::with_capacity(old.len() + new.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            alignment.push(Align::Both(i, j));
+            i += 1;
+            j += 1;
+        } else if table[(i + 1) * width + j] >= table[i * width + j + 1] {
+            alignment.push(Align::Old(i));
+            i += 1;
+        } else {
+            alignment.push(Align::New(j));
+            j += 1;
+        }
+    }
+    alignment.extend((i..old.len()).map(Align::Old));
+    alignment.extend((j..new.len()).map(Align::New));
+    alignment
+}
+
+/// Align the common prefix and suffix of two sequences, the middle is removed then added
+fn align_ends<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Align> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_end, new_end) = (old.len() - suffix, new.len() - suffix);
+    let mut alignment = Vec::with_capacity(old.len() + new.len());
+    alignment.extend((0..prefix).map(|i| Align::Both(i, i)));
+    alignment.extend((prefix..old_end).map(Align::Old));
+    alignment.extend((prefix..new_end).map(Align::New));
+    alignment.extend((0..suffix).map(|k| Align::Both(old_end + k, new_end + k)));
+    alignment
+}
+
+/// Diff two list of lines
+fn lcs_diff<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<DiffLine<'a>> {
+    lcs_align(old, new)
+        .into_iter()
+        .map(|step| match step {
+            Align::Both(i, _) => DiffLine::Same(old[i]),
+            Align::Old(i) => DiffLine::Removed(old[i]),
+            Align::New(j) => DiffLine::Added(new[j]),
+        })
+        .collect()
+}
+
+/// Count the removed and added lines of a diff
+#[must_use]
+pub fn diff_stats(diff: &[DiffLine<'_>]) -> (usize, usize) {
+    diff.iter()
+        .fold((0, 0), |(removed, added), line| match line {
+            DiffLine::Same(_) => (removed, added),
+            DiffLine::Removed(_) => (removed + 1, added),
+            DiffLine::Added(_) => (removed, added + 1),
+        })
+}
+
+/// Kind of a structural change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XmlChangeKind {
+    /// Element only in the new document
+    ElementAdded,
+    /// Element only in the old document
+    ElementRemoved,
+    /// Attribute only in the new element
+    AttributeAdded {
+        /// Attribute name
+        name: String,
+        /// New value
+        value: String,
+    },
+    /// Attribute only in the old element
+    AttributeRemoved {
+        /// Attribute name
+        name: String,
+        /// Old value
+        value: String,
+    },
+    /// Attribute with a different value
+    AttributeChanged {
+        /// Attribute name
+        name: String,
+        /// Old value
+        old: String,
+        /// New value
+        new: String,
+    },
+    /// Text content of the element changed
+    TextChanged {
+        /// Old text
+        old: String,
+        /// New text
+        new: String,
+    },
+}
+
+/// A structural change between two documents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlChange {
+    /// Path of the element, like `svg/g[1]/path[0]`
+    ///
+    /// The index counts the element children of the parent, in the new document
+    /// except for removed elements.
+    pub path: String,
+    /// What changed
+    pub kind: XmlChangeKind,
+}
+
+/// Key used to match the children of two elements
+fn element_key(element: &Element) -> (&str, Option<&str>) {
+    (
+        element.name.as_str(),
+        element.attributes.get("id").map(String::as_str),
+    )
+}
+
+/// Concatenated text content of an element
+fn element_text(element: &Element) -> String {
+    element
+        .children
+        .iter()
+        .filter_map(|node| match node {
+            XMLNode::Text(text) | XMLNode::CData(text) => Some(text.trim()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Structural diff between two parsed elements
+///
+/// Children are matched by name and `id` with a longest common subsequence,
+/// matched elements are compared recursively.
+#[must_use]
+pub fn xml_diff(old: &Element, new: &Element) -> Vec<XmlChange> {
+    let mut changes = Vec::new();
+    diff_elements(old, new, &new.name, &mut changes);
+    changes
+}
+
+/// Parse two svg strings and diff their structure
+/// # Errors
+/// Fails if one of the strings is not valid XML
+pub fn xml_diff_str(old: &str, new: &str) -> Result<Vec<XmlChange>, String> {
+    let old = Element::parse(old.as_bytes()).map_err(|e| format!("Old document: {e}"))?;
+    let new = Element::parse(new.as_bytes()).map_err(|e| format!("New document: {e}"))?;
+    Ok(xml_diff(&old, &new))
+}
+
+/// Diff two matched elements at `path`
+fn diff_elements(old: &Element, new: &Element, path: &str, changes: &mut Vec<XmlChange>) {
+    let change = |kind| XmlChange {
+        path: path.to_string(),
+        kind,
+    };
+    for (name, value) in &old.attributes {
+        match new.attributes.get(name) {
+            None => changes.push(change(XmlChangeKind::AttributeRemoved {
+                name: name.clone(),
+                value: value.clone(),
+            })),
+            Some(new_value) if new_value != value => {
+                changes.push(change(XmlChangeKind::AttributeChanged {
+                    name: name.clone(),
+                    old: value.clone(),
+                    new: new_value.clone(),
+                }));
+            }
+            Some(_) => {}
+        }
+    }
+    for (name, value) in &new.attributes {
+        if !old.attributes.contains_key(name) {
+            changes.push(change(XmlChangeKind::AttributeAdded {
+                name: name.clone(),
+                value: value.clone(),
+            }));
+        }
+    }
+    let (old_text, new_text) = (element_text(old), element_text(new));
+    if old_text != new_text {
+        changes.push(change(XmlChangeKind::TextChanged {
+            old: old_text,
+            new: new_text,
+        }));
+    }
+
+    let old_children = old
+        .children
+        .iter()
+        .filter_map(XMLNode::as_element)
+        .collect::<Vec<_>>();
+    let new_children = new
+        .children
+        .iter()
+        .filter_map(XMLNode::as_element)
+        .collect::<Vec<_>>();
+    let old_keys = old_children
+        .iter()
+        .map(|e| element_key(e))
+        .collect::<Vec<_>>();
+    let new_keys = new_children
+        .iter()
+        .map(|e| element_key(e))
+        .collect::<Vec<_>>();
+    let child_path = |element: &Element, index: usize| format!("{path}/{}[{index}]", element.name);
+    for step in lcs_align(&old_keys, &new_keys) {
+        match step {
+            Align::Both(i, j) => {
+                diff_elements(
+                    old_children[i],
+                    new_children[j],
+                    &child_path(new_children[j], j),
+                    changes,
+                );
+            }
+            Align::Old(i) => changes.push(XmlChange {
+                path: child_path(old_children[i], i),
+                kind: XmlChangeKind::ElementRemoved,
+            }),
+            Align::New(j) => changes.push(XmlChange {
+                path: child_path(new_children[j], j),
+                kind: XmlChangeKind::ElementAdded,
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for XmlChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = &self.path;
+        match &self.kind {
+            XmlChangeKind::ElementAdded => write!(f, "+ {path}"),
+            XmlChangeKind::ElementRemoved => write!(f, "- {path}"),
+            XmlChangeKind::AttributeAdded { name, value } => {
+                write!(f, "+ {path} @{name}=\"{value}\"")
+            }
+            XmlChangeKind::AttributeRemoved { name, value } => {
+                write!(f, "- {path} @{name}=\"{value}\"")
+            }
+            XmlChangeKind::AttributeChanged { name, old, new } => {
+                write!(f, "~ {path} @{name}: \"{old}\" -> \"{new}\"")
+            }
+            XmlChangeKind::TextChanged { old, new } => {
+                write!(f, "~ {path} text: \"{old}\" -> \"{new}\"")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn test_line_diff() {
+        let old = "<svg>\n<rect/>\n<circle/>\n</svg>";
+        let new = "<svg>\n<circle/>\n<path/>\n</svg>";
+        let diff = line_diff(old, new);
+        assert_eq!(
+            diff,
+            vec![
+                DiffLine::Same("<svg>"),
+                DiffLine::Removed("<rect/>"),
+                DiffLine::Same("<circle/>"),
+                DiffLine::Added("<path/>"),
+                DiffLine::Same("</svg>"),
+            ]
+        );
+        assert_eq!(diff_stats(&diff), (1, 1));
+        assert!(
+            line_diff(old, old)
+                .iter()
+                .all(|l| matches!(l, DiffLine::Same(_)))
+        );
+    }
+
+    #[test]
+    fn test_lcs_align_too_big() {
+        let old = (0..5000).collect::<Vec<_>>();
+        let mut new = (0..5000).collect::<Vec<_>>();
+        new[2] = 9999;
+        let alignment = lcs_align(&old, &new);
+        assert_eq!(alignment.len(), 5001);
+        assert_eq!(alignment[..2], [Align::Both(0, 0), Align::Both(1, 1)]);
+        assert_eq!(alignment[2..4], [Align::Old(2), Align::New(2)]);
+        assert_eq!(alignment[5000], Align::Both(4999, 4999));
+        // small inputs still get the full alignment
+        assert_eq!(
+            lcs_align(&[1, 2, 3], &[2, 3, 4]),
+            [
+                Align::Old(0),
+                Align::Both(1, 0),
+                Align::Both(2, 1),
+                Align::New(2)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_xml_diff() {
+        let old =
+            r#"<svg width="10"><rect id="a" fill="red"/><circle r="1"/><text>Hi</text></svg>"#;
+        let new = r#"<svg width="20" height="5"><rect id="a" fill="blue"/><path d="M0 0"/><text>Hello</text></svg>"#;
+        let changes = xml_diff_str(old, new).unwrap();
+        let lines = changes.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                r#"~ svg @width: "10" -> "20""#,
+                r#"+ svg @height="5""#,
+                r#"~ svg/rect[0] @fill: "red" -> "blue""#,
+                "- svg/circle[1]",
+                "+ svg/path[1]",
+                r#"~ svg/text[2] text: "Hi" -> "Hello""#,
+            ]
+        );
+        assert!(xml_diff_str(old, old).unwrap().is_empty());
+        assert!(xml_diff_str(old, "<svg").is_err());
+    }
+}
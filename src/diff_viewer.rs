@@ -0,0 +1,229 @@
+//! Diff Viewer
+//! Compare the saved svg with the current one
+
+use bladvak::ErrorManager;
+use bladvak::app::BladvakPanel;
+use bladvak::eframe::egui::{self, Color32};
+
+use crate::GalagoApp;
+use crate::diff::{DiffLine, XmlChange, XmlChangeKind, diff_stats, line_diff, xml_diff_str};
+
+/// Kind of diff shown in the panel
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffMode {
+    /// Line by line diff of the text
+    Text,
+    /// Added, removed and changed elements and attributes
+    Structure,
+}
+
+/// Diff computed once for a pair of svg strings
+#[derive(Debug)]
+pub(crate) struct DiffCache {
+    /// Compared strings
+    compared: Option<(String, String)>,
+    /// Prefix (` `, `-` or `+`) and text of each line
+    lines: Vec<(char, String)>,
+    /// Number of removed and added lines
+    stats: (usize, usize),
+    /// Structural changes, error if one of the svg is invalid
+    changes: Result<Vec<XmlChange>, String>,
+}
+
+impl Default for DiffCache {
+    fn default() -> Self {
+        Self {
+            compared: None,
+            lines: Vec::new(),
+            stats: (0, 0),
+            changes: Ok(Vec::new()),
+        }
+    }
+}
+
+impl DiffCache {
+    /// Recompute the diff if `old` or `new` changed
+    pub(crate) fn update(&mut self, old: &str, new: &str) {
+        if self
+            .compared
+            .as_ref()
+            .is_some_and(|(cached_old, cached_new)| cached_old == old && cached_new == new)
+        {
+            return;
+        }
+        let diff = line_diff(old, new);
+        self.stats = diff_stats(&diff);
+        self.lines = diff
+            .iter()
+            .map(|line| match line {
+                DiffLine::Same(text) => (' ', (*text).to_string()),
+                DiffLine::Removed(text) => ('-', (*text).to_string()),
+                DiffLine::Added(text) => ('+', (*text).to_string()),
+            })
+            .collect();
+        self.changes = xml_diff_str(old, new);
+        self.compared = Some((old.to_string(), new.to_string()));
+    }
+
+    /// Number of removed and added lines
+    pub(crate) fn stats(&self) -> (usize, usize) {
+        self.stats
+    }
+
+    /// Show the line diff, removed lines in red and added lines in green
+    pub(crate) fn show_lines(&self, ui: &mut egui::Ui, font_size: f32) {
+        let font = egui::FontId::monospace(font_size);
+        let row_height = ui.fonts_mut(|f| f.row_height(&font));
+        egui::ScrollArea::both()
+            .id_salt("line_diff")
+            .max_height(ui.ctx().viewport_rect().height() / 3.0)
+            .show_rows(ui, row_height, self.lines.len(), |ui, range| {
+                for (prefix, text) in &self.lines[range] {
+                    let color = match prefix {
+                        '-' => Color32::RED,
+                        '+' => Color32::GREEN,
+                        _ => ui.visuals().text_color(),
+                    };
+                    let text = egui::RichText::new(format!("{prefix} {text}"))
+                        .font(font.clone())
+                        .color(color);
+                    ui.add(egui::Label::new(text).extend());
+                }
+            });
+    }
+
+    /// Show the structural changes
+    pub(crate) fn show_changes(&self, ui: &mut egui::Ui) {
+        let changes = match &self.changes {
+            Ok(changes) if changes.is_empty() => {
+                ui.label("No structural change");
+                return;
+            }
+            Ok(changes) => changes,
+            Err(e) => {
+                ui.colored_label(Color32::RED, e);
+                return;
+            }
+        };
+        egui::ScrollArea::vertical()
+            .id_salt("structural_diff")
+            .max_height(ui.ctx().viewport_rect().height() / 3.0)
+            .show(ui, |ui| {
+                for change in changes {
+                    let color = match change.kind {
+                        XmlChangeKind::ElementAdded | XmlChangeKind::AttributeAdded { .. } => {
+                            Color32::GREEN
+                        }
+                        XmlChangeKind::ElementRemoved | XmlChangeKind::AttributeRemoved { .. } => {
+                            Color32::RED
+                        }
+                        XmlChangeKind::AttributeChanged { .. }
+                        | XmlChangeKind::TextChanged { .. } => Color32::YELLOW,
+                    };
+                    ui.colored_label(color, change.to_string());
+                }
+            });
+    }
+}
+
+/// `DiffViewer` Struct
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct DiffViewer {
+    /// Kind of diff shown
+    mode: DiffMode,
+    /// Draw the saved svg over the current one on the canvas
+    pub(crate) onion_skin: bool,
+    /// Opacity of the saved svg in the onion skin
+    pub(crate) onion_opacity: f32,
+    /// Font size of the line diff
+    font_size: f32,
+    /// Diff between the saved and current svg
+    #[serde(skip)]
+    cache: DiffCache,
+}
+
+impl Default for DiffViewer {
+    fn default() -> Self {
+        Self {
+            mode: DiffMode::Text,
+            onion_skin: false,
+            onion_opacity: 0.5,
+            font_size: 12.0,
+            cache: DiffCache::default(),
+        }
+    }
+}
+
+impl GalagoApp {
+    /// Show the diff between the saved and current svg
+    pub fn show_diff(&mut self, ui: &mut egui::Ui) {
+        let Some(document) = self.documents.get_current_doc_mut() else {
+            return;
+        };
+        let viewer = &mut self.diff_viewer;
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut viewer.onion_skin, "Onion skin")
+                .on_hover_text("Draw the saved svg over the current one");
+            ui.add_enabled(
+                viewer.onion_skin,
+                egui::Slider::new(&mut viewer.onion_opacity, 0.0..=1.0).text("Opacity"),
+            );
+        });
+        if document.saved_svg == document.svg {
+            ui.label("No change since the last save");
+            return;
+        }
+        viewer.cache.update(&document.saved_svg, &document.svg);
+        let (removed, added) = viewer.cache.stats();
+        ui.label(format!("Saved -> current: -{removed} +{added} lines"));
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut viewer.mode, DiffMode::Text, "Text");
+            ui.selectable_value(&mut viewer.mode, DiffMode::Structure, "Structure");
+        });
+        match viewer.mode {
+            DiffMode::Text => viewer.cache.show_lines(ui, viewer.font_size),
+            DiffMode::Structure => viewer.cache.show_changes(ui),
+        }
+        if ui
+            .button("Revert to saved")
+            .on_hover_text("Replace the current svg with the saved one")
+            .clicked()
+        {
+            document.svg.clone_from(&document.saved_svg);
+        }
+    }
+}
+
+/// Diff viewer panel
+#[derive(Debug)]
+pub struct DiffViewerPanel;
+
+impl BladvakPanel for DiffViewerPanel {
+    type App = GalagoApp;
+
+    fn name(&self) -> &'static str {
+        "SVG diff"
+    }
+
+    fn has_settings(&self) -> bool {
+        true
+    }
+
+    fn ui_settings(
+        &self,
+        app: &mut Self::App,
+        ui: &mut egui::Ui,
+        _error_manager: &mut ErrorManager,
+    ) {
+        ui.add(egui::Slider::new(&mut app.diff_viewer.font_size, 8.0..=32.0).text("Font Size"));
+    }
+
+    fn has_ui(&self) -> bool {
+        true
+    }
+
+    fn ui(&self, app: &mut Self::App, ui: &mut egui::Ui, _error_manager: &mut ErrorManager) {
+        app.show_diff(ui);
+    }
+}
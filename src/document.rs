@@ -2,10 +2,74 @@
 
 use bladvak::eframe::egui;
 use bladvak::utils::document::DocumentTrait;
+use std::collections::BTreeSet;
 use std::path::{Path, PathBuf};
+use xmltree::Element;
 
+use crate::patch::patch_svg;
+use crate::render::VisualDiff;
+use crate::render_job::BackgroundJob;
 use crate::svg_render::SvgRender;
 
+/// Parsed svg, synced with the text of the document
+#[derive(Debug, Default)]
+pub(crate) struct SvgTree {
+    /// Text the tree was parsed from or written to
+    source: String,
+    /// Root element, error if the text is invalid
+    root: Option<Result<Element, String>>,
+    /// Incremented each time the tree is parsed or written, the index paths of
+    /// its nodes may have changed
+    revision: u64,
+}
+
+impl SvgTree {
+    /// Root element of `svg`, parsed again only if `svg` changed since the last call
+    /// # Errors
+    /// Return the parse error if `svg` is invalid
+    pub(crate) fn sync(&mut self, svg: &str) -> Result<&mut Element, &str> {
+        if self.source != svg {
+            self.root = None;
+        }
+        let root = self.root.get_or_insert_with(|| {
+            self.revision += 1;
+            self.source = svg.to_string();
+            Element::parse(svg.as_bytes()).map_err(|e| e.to_string())
+        });
+        root.as_mut().map_err(|e| e.as_str())
+    }
+
+    /// Revision of the tree, changed when it is parsed again or written
+    pub(crate) fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Write the modified tree to `svg`, patching only the changed parts of the text
+    ///
+    /// The text is never rewritten as a whole, that would drop the attribute
+    /// prefixes and the formatting: if the patch fails, the edit is discarded.
+    /// # Errors
+    /// Return error if the tree cannot be patched into the text
+    pub(crate) fn write(&mut self, svg: &mut String) -> Result<(), String> {
+        let Some(Ok(root)) = &self.root else {
+            return Err("No valid svg to write".to_string());
+        };
+        self.revision += 1;
+        match patch_svg(&self.source, root) {
+            Ok(patched) => {
+                self.source.clone_from(&patched);
+                *svg = patched;
+                Ok(())
+            }
+            Err(e) => {
+                // parse the unchanged text again
+                self.root = None;
+                Err(format!("The edit was not applied: {e}"))
+            }
+        }
+    }
+}
+
 /// Document
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
 #[serde(default)]
@@ -19,12 +83,40 @@ pub(crate) struct Document {
     pub(crate) scene_rect: egui::Rect,
     /// `SvgRender`
     pub(crate) svg_render: SvgRender,
+    /// `SvgRender` of the saved svg, for the onion skin
+    #[serde(skip)]
+    pub(crate) saved_render: SvgRender,
     /// should reset the view
     pub(crate) should_reset_view: bool,
     /// Path to save the svg
     pub(crate) filename: PathBuf,
     /// Svg is valid
     pub(crate) svg_is_valid: bool,
+    /// SVG before the last optimization
+    #[serde(skip)]
+    pub(crate) pre_optimization_svg: Option<String>,
+    /// Revision of the tree after the last optimization, the check is dropped
+    /// once the svg is edited
+    #[serde(skip)]
+    pub(crate) optimized_revision: Option<u64>,
+    /// Sizes in bytes before and after the last optimization
+    #[serde(skip)]
+    pub(crate) optimization_sizes: Option<(usize, usize)>,
+    /// Result of the visual check against `pre_optimization_svg`
+    #[serde(skip)]
+    pub(crate) visual_check: Option<Result<VisualDiff, String>>,
+    /// Visual check running in the background
+    #[serde(skip)]
+    pub(crate) visual_check_job: Option<BackgroundJob<Result<VisualDiff, String>>>,
+    /// Names of the layers locked in the tree viewer
+    #[serde(skip)]
+    pub(crate) locked_layers: BTreeSet<String>,
+    /// Simplified svg waiting to be applied, with the svg it was computed from
+    #[serde(skip)]
+    pub(crate) simplify_preview: Option<(String, String)>,
+    /// Parsed svg, shared by the tree and layers viewers
+    #[serde(skip)]
+    pub(crate) tree: SvgTree,
 }
 
 impl Default for Document {
@@ -35,9 +127,42 @@ impl Default for Document {
             svg: String::new(),
             scene_rect: egui::Rect::NAN,
             svg_render: SvgRender::default(),
+            saved_render: SvgRender::default(),
             should_reset_view: false,
             filename: PathBuf::new(),
             svg_is_valid: true,
+            pre_optimization_svg: None,
+            optimized_revision: None,
+            optimization_sizes: None,
+            visual_check: None,
+            visual_check_job: None,
+            locked_layers: BTreeSet::new(),
+            simplify_preview: None,
+            tree: SvgTree::default(),
+        }
+    }
+}
+
+impl Document {
+    /// Replace the svg with an optimized version, keeping the previous one for the visual check
+    pub(crate) fn apply_optimization(&mut self, optimized: String) {
+        self.optimization_sizes = Some((self.svg.len(), optimized.len()));
+        self.pre_optimization_svg = Some(std::mem::replace(&mut self.svg, optimized));
+        // parse the optimized text now, any later change gets a new revision
+        let _ = self.tree.sync(&self.svg);
+        self.optimized_revision = Some(self.tree.revision());
+        self.visual_check = None;
+        self.visual_check_job = None;
+    }
+
+    /// Drop the sizes and the visual check if the svg changed since the last optimization
+    pub(crate) fn drop_stale_optimization(&mut self) {
+        if self.optimized_revision != Some(self.tree.revision()) {
+            self.optimized_revision = None;
+            self.optimization_sizes = None;
+            self.pre_optimization_svg = None;
+            self.visual_check = None;
+            self.visual_check_job = None;
         }
     }
 }
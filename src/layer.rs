@@ -0,0 +1,313 @@
+//! Layers
+//! Top-level groups of a svg, handled as layers
+
+use xmltree::{Element, XMLNode};
+
+use crate::attribute::rename_url_references;
+use crate::style::{parse_style_with_rest, write_style_with_rest};
+
+/// A layer of the document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layer {
+    /// Index of the layer in the children of the root element
+    pub index: usize,
+    /// Label, id or generated name of the layer
+    pub name: String,
+    /// Has a label or an id, a generated name changes with the index
+    pub is_named: bool,
+    /// Marked as a layer by Inkscape (`inkscape:groupmode="layer"`)
+    pub is_inkscape_layer: bool,
+    /// Hidden with `display` or `visibility`, as attributes or in the `style`
+    pub hidden: bool,
+}
+
+/// Check if the element is a layer, i.e. a group
+#[must_use]
+pub fn is_layer(element: &Element) -> bool {
+    element.name == "g"
+}
+
+/// Name of a layer: its Inkscape label, its id or `Layer {index}`
+#[must_use]
+pub fn layer_name(element: &Element, index: usize) -> String {
+    // xmltree drops the prefix of `inkscape:label`
+    element
+        .attributes
+        .get("label")
+        .or_else(|| element.attributes.get("id"))
+        .cloned()
+        .unwrap_or_else(|| format!("Layer {index}"))
+}
+
+/// Check if an element of the tree has the id `id`
+fn has_id(element: &Element, id: &str) -> bool {
+    element.attributes.get("id").map(String::as_str) == Some(id)
+        || element
+            .children
+            .iter()
+            .filter_map(XMLNode::as_element)
+            .any(|child| has_id(child, id))
+}
+
+/// Point the `href="#old"` and `url(#old)` references of the tree to `new`
+fn rename_references(element: &mut Element, old: &str, new: &str) {
+    for (key, value) in &mut element.attributes {
+        if key == "href" {
+            if value.strip_prefix('#') == Some(old) {
+                *value = format!("#{new}");
+            }
+        } else if let Some(renamed) = rename_url_references(value, |id| (id == old).then_some(new))
+        {
+            *value = renamed;
+        }
+    }
+    for child in &mut element.children {
+        if let XMLNode::Element(child) = child {
+            rename_references(child, old, new);
+        }
+    }
+}
+
+/// Rename the layer at `index` of the root children, updating its Inkscape
+/// label if it has one, its id and the references to it otherwise
+/// # Errors
+/// Fails if the name is empty, if it is an id already used or if there is no layer at `index`
+pub fn rename_layer(root: &mut Element, index: usize, name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("The layer name cannot be empty".to_string());
+    }
+    let Some(XMLNode::Element(element)) = root.children.get(index) else {
+        return Err(format!("No layer at index {index}"));
+    };
+    if element.attributes.contains_key("label") {
+        if let Some(XMLNode::Element(element)) = root.children.get_mut(index) {
+            element
+                .attributes
+                .insert("label".to_string(), name.to_string());
+        }
+        return Ok(());
+    }
+    let old = element.attributes.get("id").cloned();
+    if old.as_deref() == Some(name) {
+        return Ok(());
+    }
+    if name.contains(char::is_whitespace) {
+        return Err(format!("The id \"{name}\" cannot contain spaces"));
+    }
+    if has_id(root, name) {
+        return Err(format!("The id \"{name}\" is already used"));
+    }
+    if let Some(XMLNode::Element(element)) = root.children.get_mut(index) {
+        element
+            .attributes
+            .insert("id".to_string(), name.to_string());
+    }
+    if let Some(old) = old {
+        rename_references(root, &old, name);
+    }
+    Ok(())
+}
+
+/// Value of a property, from the `style` attribute or from the presentation
+/// attribute, the style winning
+fn property_value(element: &Element, name: &str) -> Option<String> {
+    let style = element
+        .attributes
+        .get("style")
+        .map(|style| parse_style_with_rest(style).0)
+        .unwrap_or_default();
+    style
+        .into_iter()
+        .rev()
+        .find(|d| d.name == name)
+        .map(|d| d.value)
+        .or_else(|| element.attributes.get(name).cloned())
+}
+
+/// Check if the element is hidden with `display:none` or `visibility:hidden`,
+/// as attributes or in its `style` like Inkscape does
+#[must_use]
+pub fn is_hidden(element: &Element) -> bool {
+    property_value(element, "display").as_deref() == Some("none")
+        || property_value(element, "visibility").as_deref() == Some("hidden")
+}
+
+/// Hide or show an element, editing the `display` of its `style` if it has one
+pub fn set_hidden(element: &mut Element, hidden: bool) {
+    let style = element.attributes.get("style").cloned().unwrap_or_default();
+    let (mut declarations, rest) = parse_style_with_rest(&style);
+    if hidden {
+        match declarations.iter_mut().find(|d| d.name == "display") {
+            Some(display) => display.value = "none".to_string(),
+            None => {
+                element
+                    .attributes
+                    .insert("display".to_string(), "none".to_string());
+            }
+        }
+    } else {
+        declarations.retain(|d| {
+            !(d.name == "display" && d.value == "none"
+                || d.name == "visibility" && d.value == "hidden")
+        });
+        if element.attributes.get("display").map(String::as_str) == Some("none") {
+            element.attributes.shift_remove("display");
+        }
+        if element.attributes.get("visibility").map(String::as_str) == Some("hidden") {
+            element.attributes.shift_remove("visibility");
+        }
+    }
+    if !style.is_empty() {
+        let style = write_style_with_rest(&declarations, rest);
+        if style.is_empty() {
+            element.attributes.shift_remove("style");
+        } else {
+            element.attributes.insert("style".to_string(), style);
+        }
+    }
+}
+
+/// Layers of the document, in document order (bottom first)
+#[must_use]
+pub fn layers(root: &Element) -> Vec<Layer> {
+    root.children
+        .iter()
+        .enumerate()
+        .filter_map(|(index, node)| match node {
+            XMLNode::Element(element) if is_layer(element) => Some(Layer {
+                index,
+                name: layer_name(element, index),
+                is_named: element.attributes.contains_key("label")
+                    || element.attributes.contains_key("id"),
+                is_inkscape_layer: element.attributes.get("groupmode").map(String::as_str)
+                    == Some("layer"),
+                hidden: is_hidden(element),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Move the node at `from` so that it ends at index `to`
+///
+/// Returns `false` if one of the indexes is out of bounds.
+pub fn move_node(nodes: &mut Vec<XMLNode>, from: usize, to: usize) -> bool {
+    if from >= nodes.len() || to >= nodes.len() {
+        return false;
+    }
+    let node = nodes.remove(from);
+    nodes.insert(to, node);
+    true
+}
+
+/// New index of the node at `index` after [`move_node`]
+#[must_use]
+pub fn moved_index(index: usize, from: usize, to: usize) -> usize {
+    if index == from {
+        to
+    } else if from < index && index <= to {
+        index - 1
+    } else if to <= index && index < from {
+        index + 1
+    } else {
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn test_layers() {
+        let mut root = Element::parse(
+            r#"<svg xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape"><g inkscape:groupmode="layer" inkscape:label="Background"/><rect/><g id="top" display="none"/><g/></svg>"#
+                .as_bytes(),
+        )
+        .unwrap();
+        let found = layers(&root);
+        let names = found.iter().map(|l| l.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Background", "top", "Layer 3"]);
+        assert_eq!(
+            found.iter().map(|l| l.index).collect::<Vec<_>>(),
+            vec![0, 2, 3]
+        );
+        assert!(found[0].is_inkscape_layer && !found[1].is_inkscape_layer);
+        assert!(found[1].hidden && !found[2].hidden);
+
+        assert!(found[0].is_named && found[1].is_named && !found[2].is_named);
+
+        if let XMLNode::Element(top) = &mut root.children[2] {
+            set_hidden(top, false);
+        }
+        rename_layer(&mut root, 2, "renamed").unwrap();
+        assert!(move_node(&mut root.children, 2, 0));
+        assert!(!move_node(&mut root.children, 4, 0));
+        let found = layers(&root);
+        assert_eq!(found[0].name, "renamed");
+        assert!(!found[0].hidden);
+        assert_eq!(found[1].name, "Background");
+    }
+
+    #[test]
+    fn test_rename_layer() {
+        let mut root = Element::parse(
+            r##"<svg><g id="a"><linearGradient id="grad"/></g><g id="b"/><rect fill="url(#a)" style="filter:url( '#a' )"/><use href="#a"/><use href="#ab"/></svg>"##
+                .as_bytes(),
+        )
+        .unwrap();
+        assert!(rename_layer(&mut root, 0, "").is_err());
+        assert!(rename_layer(&mut root, 0, "b").is_err());
+        assert!(rename_layer(&mut root, 0, "grad").is_err());
+        assert!(rename_layer(&mut root, 0, "new id").is_err());
+        assert!(rename_layer(&mut root, 5, "c").is_err());
+        rename_layer(&mut root, 0, "a").unwrap();
+        rename_layer(&mut root, 0, "c").unwrap();
+        let mut buf = Vec::new();
+        root.write(&mut buf).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            r##"<?xml version="1.0" encoding="UTF-8"?><svg><g id="c"><linearGradient id="grad" /></g><g id="b" /><rect fill="url(#c)" style="filter:url(#c)" /><use href="#c" /><use href="#ab" /></svg>"##
+        );
+    }
+
+    #[test]
+    fn test_moved_index() {
+        let mut nodes = (0..5)
+            .map(|i| XMLNode::Text(i.to_string()))
+            .collect::<Vec<_>>();
+        for (from, to) in [(1, 3), (3, 1), (2, 2), (0, 4)] {
+            let before = nodes.clone();
+            move_node(&mut nodes, from, to);
+            for (index, node) in before.iter().enumerate() {
+                assert_eq!(&nodes[moved_index(index, from, to)], node);
+            }
+        }
+    }
+
+    #[test]
+    fn test_inkscape_hidden_layer() {
+        let mut root = Element::parse(
+            r#"<svg xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape"><g inkscape:groupmode="layer" inkscape:label="Hidden" style="display:none;opacity:0.5"/><g inkscape:label="Shown" style="display:inline"/></svg>"#
+                .as_bytes(),
+        )
+        .unwrap();
+        let found = layers(&root);
+        assert!(found[0].hidden && !found[1].hidden);
+
+        let XMLNode::Element(hidden) = &mut root.children[0] else {
+            panic!("not an element");
+        };
+        set_hidden(hidden, false);
+        assert_eq!(hidden.attributes["style"], "opacity:0.5");
+        assert!(!is_hidden(hidden));
+        let XMLNode::Element(shown) = &mut root.children[1] else {
+            panic!("not an element");
+        };
+        set_hidden(shown, true);
+        assert_eq!(shown.attributes["style"], "display:none");
+        assert!(!shown.attributes.contains_key("display"));
+        assert!(is_hidden(shown));
+    }
+}
@@ -0,0 +1,164 @@
+//! Layers Viewer
+//! Panel listing the layers of the current document
+
+use bladvak::app::BladvakPanel;
+use bladvak::eframe::egui::{self, Stroke};
+use bladvak::{AppError, ErrorManager};
+use xmltree::XMLNode;
+
+use crate::GalagoApp;
+use crate::layer::{Layer, layers, move_node, rename_layer, set_hidden};
+
+/// Text field of the layer name, returns the new name once the edit is done
+fn edit_layer_name(ui: &mut egui::Ui, layer: &Layer) -> Option<String> {
+    // the name is kept aside while editing, not applied on every keystroke
+    let edit_id = egui::Id::new("layer_name").with(layer.index);
+    let mut name = ui
+        .data_mut(|data| data.get_temp::<String>(edit_id))
+        .unwrap_or_else(|| layer.name.clone());
+    let label = if layer.is_inkscape_layer {
+        "Rename the Inkscape label of the layer"
+    } else {
+        "Rename the id of the layer"
+    };
+    let response = ui.text_edit_singleline(&mut name).on_hover_text(label);
+    if response.lost_focus() {
+        ui.data_mut(|data| data.remove::<String>(edit_id));
+        return (name != layer.name).then_some(name);
+    }
+    if response.changed() {
+        ui.data_mut(|data| data.insert_temp(edit_id, name));
+    }
+    None
+}
+
+impl GalagoApp {
+    /// Show the layers of the current document, top of the z-order first
+    pub fn show_layers(&mut self, ui: &mut egui::Ui, error_manager: &mut ErrorManager) {
+        let Some(document) = self.documents.get_current_doc_mut() else {
+            return;
+        };
+        let root = match document.tree.sync(&document.svg) {
+            Ok(root) => root,
+            Err(e) => {
+                ui.label(format!("Error: {e}"));
+                return;
+            }
+        };
+        let found = layers(root);
+        if found.is_empty() {
+            ui.label("No layer, top-level groups are shown as layers");
+            return;
+        }
+        let mut modified = false;
+        let mut moved = None;
+        let mut renamed = None;
+        for layer in found.iter().rev() {
+            let Some(XMLNode::Element(element)) = root.children.get_mut(layer.index) else {
+                continue;
+            };
+            let row = ui.horizontal(|ui| {
+                ui.dnd_drag_source(
+                    egui::Id::new("layer").with(layer.index),
+                    layer.index,
+                    |ui| {
+                        ui.label("☰");
+                    },
+                )
+                .response
+                .on_hover_text("Drag to change the z-order");
+                let mut visible = !layer.hidden;
+                let text = if visible { "👁" } else { "—" };
+                if ui
+                    .toggle_value(&mut visible, text)
+                    .on_hover_text("Show or hide the layer")
+                    .changed()
+                {
+                    set_hidden(element, !visible);
+                    modified = true;
+                }
+                let mut locked = document.locked_layers.contains(&layer.name);
+                let text = if locked { "🔒" } else { "🔓" };
+                if ui
+                    .add_enabled_ui(layer.is_named, |ui| ui.toggle_value(&mut locked, text))
+                    .inner
+                    .on_hover_text("Lock the layer in the tree viewer")
+                    .on_disabled_hover_text("Name the layer to lock it")
+                    .changed()
+                {
+                    if locked {
+                        document.locked_layers.insert(layer.name.clone());
+                    } else {
+                        document.locked_layers.remove(&layer.name);
+                    }
+                }
+                if let Some(name) = edit_layer_name(ui, layer) {
+                    renamed = Some((layer.index, layer.name.clone(), name));
+                }
+            });
+            let response = row.response;
+            if response.dnd_hover_payload::<usize>().is_some() {
+                ui.painter().hline(
+                    response.rect.x_range(),
+                    response.rect.top(),
+                    Stroke::new(2.0, ui.visuals().selection.bg_fill),
+                );
+            }
+            if let Some(from) = response.dnd_release_payload::<usize>() {
+                moved = Some((*from, layer.index));
+            }
+        }
+        if let Some((index, old_name, name)) = renamed {
+            match rename_layer(root, index, &name) {
+                Ok(()) => {
+                    if document.locked_layers.remove(&old_name) {
+                        document.locked_layers.insert(name);
+                    }
+                    modified = true;
+                }
+                Err(e) => error_manager.add_error(AppError::new(e)),
+            }
+        }
+        if let Some((from, to)) = moved
+            && from != to
+            && move_node(&mut root.children, from, to)
+        {
+            modified = true;
+        }
+        if modified && let Err(err) = document.tree.write(&mut document.svg) {
+            error_manager.add_error(err);
+        }
+    }
+}
+
+/// Layers panel
+#[derive(Debug)]
+pub struct LayersViewerPanel;
+
+impl BladvakPanel for LayersViewerPanel {
+    type App = GalagoApp;
+
+    fn name(&self) -> &'static str {
+        "Layers"
+    }
+
+    fn has_settings(&self) -> bool {
+        false
+    }
+
+    fn ui_settings(
+        &self,
+        _app: &mut Self::App,
+        _ui: &mut egui::Ui,
+        _error_manager: &mut ErrorManager,
+    ) {
+    }
+
+    fn has_ui(&self) -> bool {
+        true
+    }
+
+    fn ui(&self, app: &mut Self::App, ui: &mut egui::Ui, error_manager: &mut ErrorManager) {
+        app.show_layers(ui, error_manager);
+    }
+}
@@ -1,4 +1,8 @@
 //! Galago
+//!
+//! Without the default `gui` feature, only the GUI-free modules are built:
+//! [`path`], [`attribute`], [`style`], [`css`], [`schema`], [`shape`], [`node`], [`layer`],
+//! [`lint`], [`optimize`], [`render`], [`diff`] and [`patch`].
 
 #![warn(clippy::all, rust_2018_idioms)]
 #![deny(
@@ -19,13 +23,51 @@
 )]
 #![allow(clippy::multiple_crate_versions)]
 
+pub mod attribute;
+pub mod css;
+pub mod diff;
+pub mod layer;
+pub mod lint;
+pub mod node;
+pub mod optimize;
+pub mod patch;
+pub mod path;
+pub mod render;
+pub mod schema;
+pub mod shape;
+pub mod style;
+
+pub use resvg;
+
+#[cfg(feature = "gui")]
 mod app;
+#[cfg(feature = "gui")]
 pub use app::GalagoApp;
+#[cfg(feature = "gui")]
+mod attribute_editor;
+#[cfg(feature = "gui")]
 mod central_panel;
+#[cfg(feature = "gui")]
+mod compare_viewer;
+#[cfg(feature = "gui")]
+mod css_viewer;
+#[cfg(feature = "gui")]
+mod diff_viewer;
+#[cfg(feature = "gui")]
 mod document;
-pub mod path;
+#[cfg(feature = "gui")]
+mod layers_viewer;
+#[cfg(feature = "gui")]
+mod lint_viewer;
+#[cfg(feature = "gui")]
+mod render_job;
+#[cfg(feature = "gui")]
 mod settings;
+#[cfg(feature = "gui")]
 mod string_viewer;
+#[cfg(feature = "gui")]
 mod svg_render;
+#[cfg(feature = "gui")]
 mod top_panel;
+#[cfg(feature = "gui")]
 mod tree_viewer;
@@ -0,0 +1,360 @@
+//! Lint
+//! Checks of a document: broken references, unknown names, invalid data and
+//! accessibility gaps, located in the tree and in the text
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use roxmltree::{Document, Node, NodeType};
+
+use crate::attribute::url_references;
+use crate::patch::source_children;
+use crate::path::SvgPath;
+use crate::schema::{SVG_NAMESPACE, element_schema};
+
+/// Namespace of the xlink attributes
+const XLINK_NAMESPACE: &str = "http://www.w3.org/1999/xlink";
+
+/// Severity of a diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The document is broken
+    Error,
+    /// The document works but something is likely wrong
+    Warning,
+    /// Suggestion
+    Info,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+            Self::Info => write!(f, "info"),
+        }
+    }
+}
+
+/// An issue found in the document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Severity of the issue
+    pub severity: Severity,
+    /// Description of the issue
+    pub message: String,
+    /// Index path of the element in the tree, empty for the root element
+    pub path: Vec<usize>,
+    /// Byte range of the element tag or attribute in the text
+    pub range: Range<usize>,
+    /// Line in the text, starting at 1
+    pub line: u32,
+    /// Column in the text, starting at 1
+    pub column: u32,
+}
+
+/// Diagnostics of a document, sorted by position
+struct Linter<'a, 'input> {
+    /// Parsed document
+    document: &'a Document<'input>,
+    /// Found diagnostics
+    diagnostics: Vec<Diagnostic>,
+    /// Elements by id, the first one wins
+    ids: HashMap<&'a str, Node<'a, 'input>>,
+    /// Referenced ids
+    references: HashSet<&'a str>,
+}
+
+impl<'a, 'input> Linter<'a, 'input> {
+    /// Add a diagnostic at `range`
+    fn report(&mut self, severity: Severity, message: String, path: &[usize], range: Range<usize>) {
+        let position = self.document.text_pos_at(range.start);
+        self.diagnostics.push(Diagnostic {
+            severity,
+            message,
+            path: path.to_vec(),
+            range,
+            line: position.row,
+            column: position.col,
+        });
+    }
+
+    /// Collect the ids and the references of the element and its descendants
+    fn collect(&mut self, node: Node<'a, 'input>, path: &mut Vec<usize>) {
+        for attribute in node.attributes() {
+            let value = attribute.value();
+            if attribute.name() == "id" && attribute.namespace().is_none() {
+                if let Some(first) = self.ids.get(value) {
+                    let line = self.document.text_pos_at(first.range().start).row;
+                    self.report(
+                        Severity::Error,
+                        format!("Duplicate id `{value}`, first defined line {line}"),
+                        path,
+                        attribute.range(),
+                    );
+                } else {
+                    self.ids.insert(value, node);
+                }
+            }
+            self.references
+                .extend(url_references(value).into_iter().map(|(_, id)| id));
+            if is_href(&attribute)
+                && let Some(id) = value.trim().strip_prefix('#')
+            {
+                self.references.insert(id);
+            }
+        }
+        for (index, child) in source_children(node).into_iter().enumerate() {
+            match child.node_type() {
+                NodeType::Element => {
+                    path.push(index);
+                    self.collect(child, path);
+                    path.pop();
+                }
+                // stylesheets and scripts can reference ids
+                NodeType::Text => {
+                    self.references.extend(
+                        url_references(child.text().unwrap_or_default())
+                            .into_iter()
+                            .map(|(_, id)| id),
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Check the element and its descendants
+    fn check(
+        &mut self,
+        node: Node<'a, 'input>,
+        parent: Option<Node<'a, 'input>>,
+        path: &mut Vec<usize>,
+    ) {
+        let is_svg = node
+            .tag_name()
+            .namespace()
+            .is_none_or(|ns| ns == SVG_NAMESPACE);
+        if is_svg {
+            self.check_element(node, parent, path);
+        }
+        for (index, child) in source_children(node).into_iter().enumerate() {
+            if child.is_element() {
+                path.push(index);
+                self.check(child, Some(node), path);
+                path.pop();
+            }
+        }
+    }
+
+    /// Check the name, the attributes and the content of an svg element
+    fn check_element(
+        &mut self,
+        node: Node<'a, 'input>,
+        parent: Option<Node<'a, 'input>>,
+        path: &[usize],
+    ) {
+        let name = node.tag_name().name();
+        let tag = tag_range(self.document.input_text(), node);
+        let Some(schema) = element_schema(name) else {
+            self.report(
+                Severity::Warning,
+                format!("Unknown element <{name}>"),
+                path,
+                tag,
+            );
+            return;
+        };
+        if let Some(parent) = parent
+            && parent
+                .tag_name()
+                .namespace()
+                .is_none_or(|ns| ns == SVG_NAMESPACE)
+            && let Some(parent_schema) = element_schema(parent.tag_name().name())
+            && !parent_schema.has_child(name)
+        {
+            self.report(
+                Severity::Warning,
+                format!("<{name}> is not allowed in <{}>", parent.tag_name().name()),
+                path,
+                tag.clone(),
+            );
+        }
+        for attribute in node.attributes() {
+            let is_checked = attribute
+                .namespace()
+                .is_none_or(|ns| ns == XLINK_NAMESPACE || ns == roxmltree::NS_XML_URI);
+            if is_checked && !schema.has_attribute(attribute.name()) {
+                self.report(
+                    Severity::Warning,
+                    format!("`{}` is not an attribute of <{name}>", attribute.name()),
+                    path,
+                    attribute.range(),
+                );
+            }
+            for (_, id) in url_references(attribute.value()) {
+                if !self.ids.contains_key(id) {
+                    self.report(
+                        Severity::Error,
+                        format!("Reference to the missing id `{id}`"),
+                        path,
+                        attribute.range(),
+                    );
+                }
+            }
+            if is_href(&attribute)
+                && let Some(id) = attribute.value().trim().strip_prefix('#')
+                && !self.ids.contains_key(id)
+            {
+                self.report(
+                    Severity::Error,
+                    format!("Link to the missing id `{id}`"),
+                    path,
+                    attribute.range(),
+                );
+            }
+        }
+        if name == "path"
+            && let Some(d) = node.attribute_node("d")
+            && let Err(e) = SvgPath::parse(d.value())
+        {
+            self.report(Severity::Error, e, path, d.range());
+        }
+        if name == "defs" {
+            self.check_defs(node, path);
+        }
+    }
+
+    /// Report the children of a `<defs>` that are never referenced
+    fn check_defs(&mut self, defs: Node<'a, 'input>, path: &[usize]) {
+        for (index, child) in source_children(defs).into_iter().enumerate() {
+            let name = child.tag_name().name();
+            // stylesheets and scripts apply without being referenced
+            if !child.is_element() || matches!(name, "style" | "script") {
+                continue;
+            }
+            let message = match child.attribute("id") {
+                Some(id) if self.references.contains(id) => continue,
+                Some(id) => format!("<{name} id=\"{id}\"> is never used"),
+                None => format!("<{name}> in <defs> has no id and cannot be used"),
+            };
+            let mut child_path = path.to_vec();
+            child_path.push(index);
+            let tag = tag_range(self.document.input_text(), child);
+            self.report(Severity::Warning, message, &child_path, tag);
+        }
+    }
+
+    /// Check the root element: `viewBox` and accessibility
+    fn check_root(&mut self, root: Node<'a, 'input>) {
+        if root.tag_name().name() != "svg" {
+            return;
+        }
+        let tag = tag_range(self.document.input_text(), root);
+        if !root.has_attribute("viewBox") {
+            self.report(
+                Severity::Warning,
+                "The root <svg> has no `viewBox`, it cannot be scaled".to_string(),
+                &[],
+                tag.clone(),
+            );
+        }
+        let has_title = root.children().any(|child| child.has_tag_name("title"))
+            || root.has_attribute("aria-label")
+            || root.has_attribute("aria-labelledby");
+        if !has_title {
+            self.report(
+                Severity::Info,
+                "The root <svg> has no <title>, screen readers cannot describe it".to_string(),
+                &[],
+                tag,
+            );
+        }
+    }
+}
+
+/// Check if the attribute is a `href` or `xlink:href`
+fn is_href(attribute: &roxmltree::Attribute<'_, '_>) -> bool {
+    attribute.name() == "href" && attribute.namespace().is_none_or(|ns| ns == XLINK_NAMESPACE)
+}
+
+/// Byte range of `<name` at the start of an element
+fn tag_range(source: &str, node: Node<'_, '_>) -> Range<usize> {
+    let start = node.range().start;
+    let length = source[start + 1..]
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .map_or(0, |length| length + 1);
+    start..start + length
+}
+
+/// Check the document, returns the diagnostics sorted by position
+/// # Errors
+/// Fails if the svg cannot be parsed
+pub fn lint_svg(source: &str) -> Result<Vec<Diagnostic>, String> {
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..roxmltree::ParsingOptions::default()
+    };
+    let document = Document::parse_with_options(source, options).map_err(|e| e.to_string())?;
+    let mut linter = Linter {
+        document: &document,
+        diagnostics: Vec::new(),
+        ids: HashMap::new(),
+        references: HashSet::new(),
+    };
+    let root = document.root_element();
+    linter.collect(root, &mut Vec::new());
+    linter.check_root(root);
+    linter.check(root, None, &mut Vec::new());
+    let mut diagnostics = linter.diagnostics;
+    diagnostics.sort_by_key(|d| (d.range.start, d.severity));
+    Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn test_lint_svg() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">
+    <defs>
+        <linearGradient id="used"/>
+        <radialGradient id="unused"/>
+    </defs>
+    <rect id="a" fill="url(#used)" stroke="url(#missing)"/>
+    <circle id="a" d="M0 0"/>
+    <path d="M0 0 L"/>
+    <use xlink:href="#nowhere"/>
+    <blink/>
+</svg>"##;
+        let diagnostics = lint_svg(svg).unwrap();
+        let messages = diagnostics
+            .iter()
+            .map(|d| (d.severity, d.message.as_str(), d.path.clone(), d.line))
+            .collect::<Vec<_>>();
+        assert_eq!(messages[0].0, Severity::Warning);
+        assert!(messages[0].1.contains("viewBox"));
+        assert_eq!(messages[1].0, Severity::Info);
+        assert!(messages[2].1.contains("id=\"unused\""));
+        assert_eq!(messages[2].2, [0, 1]);
+        assert_eq!(messages[2].3, 4);
+        assert!(messages[3].1.contains("`missing`"));
+        assert!(messages[4].1.contains("Duplicate id `a`"));
+        assert!(
+            messages[5]
+                .1
+                .contains("`d` is not an attribute of <circle>")
+        );
+        assert_eq!(messages[6].0, Severity::Error);
+        assert_eq!(messages[6].2, [3]);
+        assert!(messages[7].1.contains("`nowhere`"));
+        assert_eq!(messages[8].1, "Unknown element <blink>");
+        assert_eq!(messages.len(), 9);
+
+        let clean = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 1 1"><title>Dot</title><circle r="1"/></svg>"#;
+        assert!(lint_svg(clean).unwrap().is_empty());
+        assert!(lint_svg("<svg").is_err());
+    }
+}
@@ -0,0 +1,151 @@
+//! Lint Viewer
+//! Panel listing the diagnostics of the current document
+
+use bladvak::ErrorManager;
+use bladvak::app::BladvakPanel;
+use bladvak::eframe::egui::{self, Color32};
+
+use crate::GalagoApp;
+use crate::lint::{Diagnostic, Severity, lint_svg};
+
+/// `LintViewer` Struct
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct LintViewer {
+    /// Show the info diagnostics
+    show_info: bool,
+    /// Linted svg with its diagnostics
+    #[serde(skip)]
+    cache: Option<(String, Result<Vec<Diagnostic>, String>)>,
+}
+
+impl Default for LintViewer {
+    fn default() -> Self {
+        Self {
+            show_info: true,
+            cache: None,
+        }
+    }
+}
+
+/// Color of a severity
+fn severity_color(severity: Severity) -> Color32 {
+    match severity {
+        Severity::Error => Color32::RED,
+        Severity::Warning => Color32::YELLOW,
+        Severity::Info => Color32::LIGHT_BLUE,
+    }
+}
+
+impl GalagoApp {
+    /// Show the diagnostics, clicking one selects its node and its text
+    pub fn show_lint(&mut self, ui: &mut egui::Ui) {
+        let document_index = self.documents.get_current_index();
+        let Some(document) = self.documents.get_current_doc_mut() else {
+            return;
+        };
+        self.tree_viewer.sync_document(document_index, document);
+        let viewer = &mut self.lint_viewer;
+        if viewer
+            .cache
+            .as_ref()
+            .is_none_or(|(svg, _)| *svg != document.svg)
+        {
+            viewer.cache = Some((document.svg.clone(), lint_svg(&document.svg)));
+        }
+        let Some((_, result)) = &viewer.cache else {
+            return;
+        };
+        let diagnostics = match result {
+            Ok(diagnostics) => diagnostics,
+            Err(e) => {
+                ui.colored_label(Color32::RED, format!("Cannot parse the svg: {e}"));
+                return;
+            }
+        };
+        let count = |severity| {
+            diagnostics
+                .iter()
+                .filter(|d| d.severity == severity)
+                .count()
+        };
+        ui.horizontal(|ui| {
+            for (severity, label) in [
+                (Severity::Error, "errors"),
+                (Severity::Warning, "warnings"),
+                (Severity::Info, "infos"),
+            ] {
+                ui.colored_label(
+                    severity_color(severity),
+                    format!("{} {label}", count(severity)),
+                );
+            }
+            ui.checkbox(&mut viewer.show_info, "Show infos");
+        });
+        ui.separator();
+        if diagnostics.is_empty() {
+            ui.label("No issue found");
+            return;
+        }
+        let mut clicked = None;
+        egui::ScrollArea::vertical()
+            .id_salt("lint_viewer")
+            .show(ui, |ui| {
+                for diagnostic in diagnostics
+                    .iter()
+                    .filter(|d| viewer.show_info || d.severity != Severity::Info)
+                {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(
+                            severity_color(diagnostic.severity),
+                            diagnostic.severity.to_string(),
+                        );
+                        let location = format!("{}:{}", diagnostic.line, diagnostic.column);
+                        if ui
+                            .selectable_label(false, format!("{location} {}", diagnostic.message))
+                            .on_hover_text("Show in the tree and in the text")
+                            .clicked()
+                        {
+                            clicked = Some(diagnostic);
+                        }
+                    });
+                }
+            });
+        if let Some(diagnostic) = clicked {
+            self.tree_viewer.reveal(&diagnostic.path);
+            self.string_viewer.jump_to = Some(diagnostic.range.clone());
+        }
+    }
+}
+
+/// Lint panel
+#[derive(Debug)]
+pub struct LintViewerPanel;
+
+impl BladvakPanel for LintViewerPanel {
+    type App = GalagoApp;
+
+    fn name(&self) -> &'static str {
+        "Lint"
+    }
+
+    fn has_settings(&self) -> bool {
+        false
+    }
+
+    fn ui_settings(
+        &self,
+        _app: &mut Self::App,
+        _ui: &mut egui::Ui,
+        _error_manager: &mut ErrorManager,
+    ) {
+    }
+
+    fn has_ui(&self) -> bool {
+        true
+    }
+
+    fn ui(&self, app: &mut Self::App, ui: &mut egui::Ui, _error_manager: &mut ErrorManager) {
+        app.show_lint(ui);
+    }
+}
@@ -0,0 +1,594 @@
+//! Node operations
+//! Edit the xml tree, nodes are addressed by the path of child indexes from the root
+
+use std::collections::{HashMap, HashSet};
+
+use xmltree::{Element, XMLNode};
+
+use crate::attribute::rename_url_references;
+use crate::diff::{Align, lcs_align};
+use crate::optimize::INHERITED_ATTRIBUTES;
+
+/// Children of the element at `path`, the root children for an empty path
+#[must_use]
+pub fn children_at<'a>(root: &'a Element, path: &[usize]) -> Option<&'a Vec<XMLNode>> {
+    let mut element = root;
+    for index in path {
+        element = element.children.get(*index)?.as_element()?;
+    }
+    Some(&element.children)
+}
+
+/// Mutable children of the element at `path`, the root children for an empty path
+pub fn children_at_mut<'a>(root: &'a mut Element, path: &[usize]) -> Option<&'a mut Vec<XMLNode>> {
+    let mut element = root;
+    for index in path {
+        element = element.children.get_mut(*index)?.as_mut_element()?;
+    }
+    Some(&mut element.children)
+}
+
+/// Node at `path`
+#[must_use]
+pub fn node_at<'a>(root: &'a Element, path: &[usize]) -> Option<&'a XMLNode> {
+    let (index, parent) = path.split_last()?;
+    children_at(root, parent)?.get(*index)
+}
+
+/// Mutable node at `path`
+pub fn node_at_mut<'a>(root: &'a mut Element, path: &[usize]) -> Option<&'a mut XMLNode> {
+    let (index, parent) = path.split_last()?;
+    children_at_mut(root, parent)?.get_mut(*index)
+}
+
+/// Remove the node at `path`
+pub fn remove_node(root: &mut Element, path: &[usize]) -> Option<XMLNode> {
+    let (index, parent) = path.split_last()?;
+    let children = children_at_mut(root, parent)?;
+    (*index < children.len()).then(|| children.remove(*index))
+}
+
+/// Path of `path` once the node at `removed` is removed
+///
+/// Returns `None` if `path` is `removed` or inside it.
+#[must_use]
+pub fn path_after_removal(path: &[usize], removed: &[usize]) -> Option<Vec<usize>> {
+    if path.starts_with(removed) {
+        return None;
+    }
+    let mut path = path.to_vec();
+    if let Some((removed_index, removed_parent)) = removed.split_last()
+        && path.len() > removed_parent.len()
+        && path.starts_with(removed_parent)
+        && path[removed_parent.len()] > *removed_index
+    {
+        path[removed_parent.len()] -= 1;
+    }
+    Some(path)
+}
+
+/// Sort the paths in document order, removing duplicates and paths inside another one
+#[must_use]
+pub fn normalize_selection(paths: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut paths = paths.to_vec();
+    paths.sort();
+    paths.dedup();
+    let mut normalized: Vec<Vec<usize>> = Vec::with_capacity(paths.len());
+    for path in paths {
+        // an ancestor sorts right before its descendants
+        if !normalized.last().is_some_and(|last| path.starts_with(last)) {
+            normalized.push(path);
+        }
+    }
+    normalized
+}
+
+/// Remove the nodes at `paths`, returned in document order
+pub fn remove_nodes(root: &mut Element, paths: &[Vec<usize>]) -> Vec<XMLNode> {
+    let mut removed = normalize_selection(paths)
+        .iter()
+        .rev()
+        .filter_map(|path| remove_node(root, path))
+        .collect::<Vec<_>>();
+    removed.reverse();
+    removed
+}
+
+/// Move the node at `from` to the position `index` of the children of `parent`
+///
+/// `parent` and `index` are given before the move. Returns the new path of the node.
+/// # Errors
+/// Fails if a path is invalid or if the node is moved inside itself
+pub fn move_node_to(
+    root: &mut Element,
+    from: &[usize],
+    parent: &[usize],
+    index: usize,
+) -> Result<Vec<usize>, String> {
+    move_nodes_to(root, &[from.to_vec()], parent, index)?
+        .pop()
+        .ok_or_else(|| "Invalid node".to_string())
+}
+
+/// Move the nodes at `from` to the position `index` of the children of `parent`,
+/// keeping their document order
+///
+/// `parent` and `index` are given before the move. Returns the new paths of the nodes.
+/// # Errors
+/// Fails if a path is invalid or if a node is moved inside itself
+pub fn move_nodes_to(
+    root: &mut Element,
+    from: &[Vec<usize>],
+    parent: &[usize],
+    index: usize,
+) -> Result<Vec<Vec<usize>>, String> {
+    let from = normalize_selection(from);
+    if from.iter().any(|path| parent.starts_with(path)) {
+        return Err("Cannot move a node inside itself".to_string());
+    }
+    if children_at(root, parent).is_none_or(|children| index > children.len()) {
+        return Err("Invalid target".to_string());
+    }
+    if from.iter().any(|path| node_at(root, path).is_none()) {
+        return Err("Invalid node".to_string());
+    }
+    let (mut parent, mut index) = (parent.to_vec(), index);
+    let mut nodes = Vec::with_capacity(from.len());
+    // remove the last nodes first, the paths of the previous ones stay valid
+    for path in from.iter().rev() {
+        let (from_index, from_parent) = path.split_last().ok_or("Cannot move the root")?;
+        if from_parent == parent.as_slice() && *from_index < index {
+            index -= 1;
+        }
+        parent = path_after_removal(&parent, path).ok_or("Invalid target")?;
+        nodes.extend(remove_node(root, path));
+    }
+    let children = children_at_mut(root, &parent).ok_or("Invalid target")?;
+    let mut new_paths = Vec::with_capacity(nodes.len());
+    for (offset, node) in nodes.into_iter().rev().enumerate() {
+        children.insert(index + offset, node);
+        let mut new_path = parent.clone();
+        new_path.push(index + offset);
+        new_paths.push(new_path);
+    }
+    Ok(new_paths)
+}
+
+/// Collect the ids of the element and its descendants
+fn collect_ids<'a>(element: &'a Element, ids: &mut Vec<&'a str>) {
+    if let Some(id) = element.attributes.get("id") {
+        ids.push(id);
+    }
+    for child in element.children.iter().filter_map(XMLNode::as_element) {
+        collect_ids(child, ids);
+    }
+}
+
+/// Rename the ids of the element and its descendants, and the references to them
+fn rename_ids(element: &mut Element, renamed: &HashMap<String, String>) {
+    for (key, value) in &mut element.attributes {
+        if key == "id" {
+            if let Some(new_id) = renamed.get(value.as_str()) {
+                value.clone_from(new_id);
+            }
+        } else if key == "href" {
+            if let Some(new_id) = value.strip_prefix('#').and_then(|id| renamed.get(id)) {
+                *value = format!("#{new_id}");
+            }
+        } else if let Some(renamed_value) =
+            rename_url_references(value, |id| renamed.get(id).map(String::as_str))
+        {
+            *value = renamed_value;
+        }
+    }
+    for child in &mut element.children {
+        if let XMLNode::Element(child) = child {
+            rename_ids(child, renamed);
+        }
+    }
+}
+
+/// Path of `path` once `count` nodes are inserted at `inserted`
+#[must_use]
+pub fn path_after_insertion(path: &[usize], inserted: &[usize], count: usize) -> Vec<usize> {
+    let mut path = path.to_vec();
+    if let Some((inserted_index, inserted_parent)) = inserted.split_last()
+        && path.len() > inserted_parent.len()
+        && path.starts_with(inserted_parent)
+        && path[inserted_parent.len()] >= *inserted_index
+    {
+        path[inserted_parent.len()] += count;
+    }
+    path
+}
+
+/// Identity of a node to follow it between two versions of a tree
+fn node_identity(node: &XMLNode) -> (u8, Option<&str>, Option<&str>) {
+    match node {
+        XMLNode::Element(e) => (
+            0,
+            Some(e.name.as_str()),
+            e.attributes.get("id").map(String::as_str),
+        ),
+        XMLNode::Comment(_) => (1, None, None),
+        XMLNode::CData(_) | XMLNode::Text(_) => (2, None, None),
+        XMLNode::ProcessingInstruction(..) => (3, None, None),
+    }
+}
+
+/// Path in `new` of the node at `path` in `old`, the children of each level being
+/// matched by id, or aligned by kind and name; `None` if the node is not in `new` anymore
+#[must_use]
+pub fn remap_path(old: &Element, new: &Element, path: &[usize]) -> Option<Vec<usize>> {
+    let (mut old_element, mut new_element) = (old, new);
+    let mut remapped = Vec::with_capacity(path.len());
+    for (depth, index) in path.iter().enumerate() {
+        let old_keys = old_element.children.iter().map(node_identity);
+        let old_keys = old_keys.collect::<Vec<_>>();
+        let new_keys = new_element.children.iter().map(node_identity);
+        let new_keys = new_keys.collect::<Vec<_>>();
+        let key = old_keys.get(*index)?;
+        // ids are unique, a node with an id is followed even if it moved
+        let by_id = key
+            .2
+            .and_then(|_| new_keys.iter().position(|new_key| new_key == key));
+        let new_index = match by_id {
+            Some(new_index) => new_index,
+            None => lcs_align(&old_keys, &new_keys)
+                .into_iter()
+                .find_map(|step| match step {
+                    Align::Both(i, j) if i == *index => Some(j),
+                    _ => None,
+                })?,
+        };
+        remapped.push(new_index);
+        if depth + 1 < path.len() {
+            old_element = old_element.children.get(*index)?.as_element()?;
+            new_element = new_element.children.get(new_index)?.as_element()?;
+        }
+    }
+    Some(remapped)
+}
+
+/// Insert a copy of the node at `path` right after it
+///
+/// Ids of the copy get a `-copy` suffix, numbered if needed to stay unique, and
+/// the references inside the copy follow them. Returns the path of the copy.
+/// # Errors
+/// Fails if the path is invalid
+pub fn duplicate_node(root: &mut Element, path: &[usize]) -> Result<Vec<usize>, String> {
+    let (index, parent) = path.split_last().ok_or("Cannot duplicate the root")?;
+    let mut copy = node_at(root, path).ok_or("Invalid node")?.clone();
+    if let XMLNode::Element(element) = &mut copy {
+        let mut used = Vec::new();
+        collect_ids(root, &mut used);
+        let mut used = used.into_iter().map(String::from).collect::<HashSet<_>>();
+        let mut copied = Vec::new();
+        collect_ids(element, &mut copied);
+        let mut renamed = HashMap::new();
+        for id in copied {
+            let mut new_id = format!("{id}-copy");
+            let mut number = 2;
+            while used.contains(&new_id) {
+                new_id = format!("{id}-copy-{number}");
+                number += 1;
+            }
+            used.insert(new_id.clone());
+            renamed.insert(id.to_string(), new_id);
+        }
+        rename_ids(element, &renamed);
+    }
+    let children = children_at_mut(root, parent).ok_or("Invalid node")?;
+    children.insert(index + 1, copy);
+    let mut new_path = parent.to_vec();
+    new_path.push(index + 1);
+    Ok(new_path)
+}
+
+/// Wrap the children `indexes` of the element at `parent` in a new `g`
+///
+/// The group takes the place of the first node. Returns the path of the group.
+/// # Errors
+/// Fails if a path is invalid or if no index is given
+pub fn group_nodes(
+    root: &mut Element,
+    parent: &[usize],
+    indexes: &[usize],
+) -> Result<Vec<usize>, String> {
+    let children = children_at_mut(root, parent).ok_or("Invalid node")?;
+    let mut indexes = indexes.to_vec();
+    indexes.sort_unstable();
+    indexes.dedup();
+    let first = *indexes.first().ok_or("Nothing to group")?;
+    if indexes.iter().any(|index| *index >= children.len()) {
+        return Err("Invalid node".to_string());
+    }
+    let mut group = Element::new("g");
+    for index in indexes.iter().rev() {
+        group.children.insert(0, children.remove(*index));
+    }
+    children.insert(first, XMLNode::Element(group));
+    let mut group_path = parent.to_vec();
+    group_path.push(first);
+    Ok(group_path)
+}
+
+/// Check that the attribute `key` of a group can be moved to its `children`
+/// without changing the render
+fn is_movable_group_attribute(key: &str, children: &[&Element]) -> bool {
+    match key {
+        "transform" => true,
+        // a child setting them would not be affected by the group anymore
+        "display" => children
+            .iter()
+            .all(|child| !child.attributes.contains_key(key)),
+        // the children would be transparent one by one instead of together
+        "opacity" => matches!(children, [child] if !child.attributes.contains_key(key)),
+        _ => INHERITED_ATTRIBUTES.contains(&key),
+    }
+}
+
+/// Replace the group at `path` by its children
+///
+/// Attributes of the group are copied to the child elements that do not set them,
+/// transforms are combined. Returns the number of children moved out of the group.
+/// # Errors
+/// Fails if the path is not a group or if an attribute of the group cannot be moved
+/// to its children without changing the render
+pub fn ungroup_node(root: &mut Element, path: &[usize]) -> Result<usize, String> {
+    let (index, parent) = path.split_last().ok_or("Cannot ungroup the root")?;
+    let children = children_at_mut(root, parent).ok_or("Invalid node")?;
+    let Some(XMLNode::Element(group)) = children.get(*index) else {
+        return Err("Not an element".to_string());
+    };
+    if group.name != "g" {
+        return Err(format!("Cannot ungroup <{}>", group.name));
+    }
+    let group_children = group
+        .children
+        .iter()
+        .filter_map(XMLNode::as_element)
+        .collect::<Vec<_>>();
+    if let Some(key) = group
+        .attributes
+        .keys()
+        .find(|key| *key != "id" && !is_movable_group_attribute(key, &group_children))
+    {
+        return Err(format!(
+            "Cannot ungroup: the attribute {key} of the group cannot be moved to its children"
+        ));
+    }
+    let XMLNode::Element(mut group) = children.remove(*index) else {
+        return Err("Not an element".to_string());
+    };
+    group.attributes.shift_remove("id");
+    let count = group.children.len();
+    for (offset, mut node) in group.children.drain(..).enumerate() {
+        if let XMLNode::Element(child) = &mut node {
+            for (key, value) in &group.attributes {
+                match child.attributes.get_mut(key) {
+                    Some(child_transform) if key == "transform" => {
+                        *child_transform = format!("{value} {child_transform}");
+                    }
+                    Some(_) => {}
+                    None => {
+                        child.attributes.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        children.insert(index + offset, node);
+    }
+    Ok(count)
+}
+
+/// Swap the element at `path` with the next (`forward`) or previous sibling element
+///
+/// Returns the new path, unchanged if the element is already at the end.
+/// # Errors
+/// Fails if the path is invalid
+pub fn shift_node(root: &mut Element, path: &[usize], forward: bool) -> Result<Vec<usize>, String> {
+    let (index, parent) = path.split_last().ok_or("Cannot move the root")?;
+    let children = children_at_mut(root, parent).ok_or("Invalid node")?;
+    if *index >= children.len() {
+        return Err("Invalid node".to_string());
+    }
+    let is_element = |node: &XMLNode| matches!(node, XMLNode::Element(_));
+    let other = if forward {
+        children
+            .iter()
+            .enumerate()
+            .skip(index + 1)
+            .find(|(_, node)| is_element(node))
+            .map(|(other, _)| other)
+    } else {
+        children[..*index].iter().rposition(is_element)
+    };
+    let mut new_path = path.to_vec();
+    if let Some(other) = other {
+        children.swap(*index, other);
+        new_path[parent.len()] = other;
+    }
+    Ok(new_path)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    /// Parse a svg
+    fn parse(svg: &str) -> Element {
+        Element::parse(svg.as_bytes()).unwrap()
+    }
+
+    /// Names of the children of the element at `path`
+    fn names(root: &Element, path: &[usize]) -> Vec<String> {
+        children_at(root, path)
+            .unwrap()
+            .iter()
+            .filter_map(|node| node.as_element().map(|e| e.name.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_move_node() {
+        let mut root = parse("<svg><rect/><g><circle/></g><path/></svg>");
+        assert_eq!(move_node_to(&mut root, &[0], &[1], 1).unwrap(), vec![0, 1]);
+        assert_eq!(names(&root, &[]), vec!["g", "path"]);
+        assert_eq!(names(&root, &[0]), vec!["circle", "rect"]);
+        assert_eq!(move_node_to(&mut root, &[1], &[], 0).unwrap(), vec![0]);
+        assert_eq!(names(&root, &[]), vec!["path", "g"]);
+        assert_eq!(move_node_to(&mut root, &[1, 1], &[], 2).unwrap(), vec![2]);
+        assert_eq!(names(&root, &[]), vec!["path", "g", "rect"]);
+        assert!(move_node_to(&mut root, &[1], &[1], 0).is_err());
+        assert!(move_node_to(&mut root, &[0], &[5], 0).is_err());
+    }
+
+    #[test]
+    fn test_move_nodes() {
+        let mut root = parse("<svg><rect/><g><circle/></g><path/><line/></svg>");
+        let moved = move_nodes_to(&mut root, &[vec![3], vec![0], vec![1, 0]], &[1], 0).unwrap();
+        assert_eq!(moved, vec![vec![0, 0], vec![0, 1], vec![0, 2]]);
+        assert_eq!(names(&root, &[]), vec!["g", "path"]);
+        assert_eq!(names(&root, &[0]), vec!["rect", "circle", "line"]);
+        assert!(move_nodes_to(&mut root, &[vec![0], vec![1]], &[0], 0).is_err());
+
+        let mut root = parse("<svg><rect/><circle/><path/><line/></svg>");
+        let moved = move_nodes_to(&mut root, &[vec![0], vec![1]], &[], 3).unwrap();
+        assert_eq!(moved, vec![vec![1], vec![2]]);
+        assert_eq!(names(&root, &[]), vec!["path", "rect", "circle", "line"]);
+
+        let removed = remove_nodes(&mut root, &[vec![3], vec![0], vec![3]]);
+        assert_eq!(removed.len(), 2);
+        assert_eq!(names(&root, &[]), vec!["rect", "circle"]);
+    }
+
+    #[test]
+    fn test_normalize_selection() {
+        let paths = [vec![2], vec![0, 1], vec![0], vec![2], vec![1, 0]];
+        assert_eq!(
+            normalize_selection(&paths),
+            vec![vec![0], vec![1, 0], vec![2]]
+        );
+    }
+
+    #[test]
+    fn test_group_and_ungroup() {
+        let mut root =
+            parse(r#"<svg><rect/><circle transform="scale(2)"/><path fill="blue"/></svg>"#);
+        assert_eq!(group_nodes(&mut root, &[], &[2, 1]).unwrap(), vec![1]);
+        assert_eq!(names(&root, &[]), vec!["rect", "g"]);
+        assert_eq!(names(&root, &[1]), vec!["circle", "path"]);
+
+        if let Some(XMLNode::Element(group)) = node_at_mut(&mut root, &[1]) {
+            group.attributes.insert("fill".into(), "red".into());
+            group
+                .attributes
+                .insert("transform".into(), "translate(1)".into());
+        }
+        assert_eq!(ungroup_node(&mut root, &[1]).unwrap(), 2);
+        assert!(ungroup_node(&mut root, &[0]).is_err());
+        assert_eq!(names(&root, &[]), vec!["rect", "circle", "path"]);
+        let attr = |path: &[usize], key: &str| {
+            node_at(&root, path)
+                .and_then(XMLNode::as_element)
+                .and_then(|e| e.attributes.get(key).cloned())
+        };
+        assert_eq!(attr(&[1], "transform").unwrap(), "translate(1) scale(2)");
+        assert_eq!(attr(&[1], "fill").unwrap(), "red");
+        assert_eq!(attr(&[2], "fill").unwrap(), "blue");
+    }
+
+    #[test]
+    fn test_ungroup_unsafe_attributes() {
+        for group in [
+            r#"<g opacity="0.5"><rect/><circle/></g>"#,
+            r#"<g opacity="0.5"><rect opacity="0.5"/></g>"#,
+            r#"<g display="none"><rect display="inline"/></g>"#,
+            r#"<g filter="url(#f)"><rect/></g>"#,
+            r#"<g clip-path="url(#c)"><rect/></g>"#,
+            r#"<g class="a"><rect/></g>"#,
+        ] {
+            let mut root = parse(&format!("<svg>{group}</svg>"));
+            assert!(ungroup_node(&mut root, &[0]).is_err(), "{group}");
+            assert_eq!(names(&root, &[]), vec!["g"]);
+        }
+        let mut root = parse(r#"<svg><g id="g" opacity="0.5" display="none"><rect/></g></svg>"#);
+        assert_eq!(ungroup_node(&mut root, &[0]).unwrap(), 1);
+        let rect = node_at(&root, &[0]).and_then(XMLNode::as_element).unwrap();
+        assert_eq!(rect.attributes.get("opacity").unwrap(), "0.5");
+        assert_eq!(rect.attributes.get("display").unwrap(), "none");
+    }
+
+    #[test]
+    fn test_duplicate_shift_remove() {
+        let mut root = parse(r#"<svg><g id="a"><rect id="b"/></g><path/></svg>"#);
+        assert_eq!(duplicate_node(&mut root, &[0]).unwrap(), vec![1]);
+        let copy = node_at(&root, &[1]).and_then(XMLNode::as_element).unwrap();
+        assert_eq!(copy.attributes.get("id").unwrap(), "a-copy");
+        let inner = copy.children[0].as_element().unwrap();
+        assert_eq!(inner.attributes.get("id").unwrap(), "b-copy");
+        // a second copy does not reuse the ids of the first one
+        assert_eq!(duplicate_node(&mut root, &[0]).unwrap(), vec![1]);
+        let copy = node_at(&root, &[1]).and_then(XMLNode::as_element).unwrap();
+        assert_eq!(copy.attributes.get("id").unwrap(), "a-copy-2");
+        remove_node(&mut root, &[1]);
+
+        assert_eq!(shift_node(&mut root, &[2], false).unwrap(), vec![1]);
+        assert_eq!(names(&root, &[]), vec!["g", "path", "g"]);
+        assert_eq!(shift_node(&mut root, &[2], true).unwrap(), vec![2]);
+        assert!(remove_node(&mut root, &[1]).is_some());
+        assert!(remove_node(&mut root, &[5]).is_none());
+        assert_eq!(names(&root, &[]), vec!["g", "g"]);
+    }
+
+    #[test]
+    fn test_duplicate_references() {
+        let mut root = parse(
+            r##"<svg><g><linearGradient id="grad"/><rect fill="url(#grad)"/><use href="#grad"/><use href="#other"/></g></svg>"##,
+        );
+        duplicate_node(&mut root, &[0]).unwrap();
+        let attr = |path: &[usize], key: &str| {
+            node_at(&root, path)
+                .and_then(XMLNode::as_element)
+                .and_then(|e| e.attributes.get(key).cloned())
+        };
+        assert_eq!(attr(&[0, 1], "fill").unwrap(), "url(#grad)");
+        assert_eq!(attr(&[1, 0], "id").unwrap(), "grad-copy");
+        assert_eq!(attr(&[1, 1], "fill").unwrap(), "url(#grad-copy)");
+        assert_eq!(attr(&[1, 2], "href").unwrap(), "#grad-copy");
+        assert_eq!(attr(&[1, 3], "href").unwrap(), "#other");
+    }
+
+    #[test]
+    fn test_path_after_removal() {
+        assert_eq!(path_after_removal(&[2, 1], &[0]), Some(vec![1, 1]));
+        assert_eq!(path_after_removal(&[0, 1], &[0, 0]), Some(vec![0, 0]));
+        assert_eq!(path_after_removal(&[0, 1], &[0]), None);
+        assert_eq!(path_after_removal(&[1], &[2]), Some(vec![1]));
+    }
+
+    #[test]
+    fn test_path_after_insertion() {
+        assert_eq!(path_after_insertion(&[2, 1], &[1], 2), vec![4, 1]);
+        assert_eq!(path_after_insertion(&[0, 1], &[0, 2], 1), vec![0, 1]);
+        assert_eq!(path_after_insertion(&[1], &[1, 0], 1), vec![1]);
+    }
+
+    #[test]
+    fn test_remap_path() {
+        let old = Element::parse(
+            r#"<svg><g id="a"><rect/><circle/></g><g id="b"><path/></g></svg>"#.as_bytes(),
+        )
+        .unwrap();
+        // a node inserted before, the groups swapped and the rect removed
+        let new = Element::parse(
+            r#"<svg><title/><g id="b"><path/></g><g id="a"><circle/></g></svg>"#.as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(remap_path(&old, &new, &[0, 1]), Some(vec![2, 0]));
+        assert_eq!(remap_path(&old, &new, &[1, 0]), Some(vec![1, 0]));
+        assert_eq!(remap_path(&old, &new, &[0, 0]), None);
+    }
+}
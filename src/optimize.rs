@@ -0,0 +1,1075 @@
+//! Optimize
+//! Svg optimizations that do not need a GUI
+
+use resvg::usvg;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use xmltree::{Element, EmitterConfig, XMLNode};
+
+use crate::attribute::{rename_url_references, url_references};
+use crate::path::{SvgPath, round_to};
+use crate::style::parse_style_with_rest;
+
+/// Simplify the svg by round-tripping it through usvg
+///
+/// The output is a normalized svg: groups, ids and editor data may be lost.
+/// # Errors
+/// Fails if usvg cannot parse the svg
+pub fn simplify(
+    svg: &str,
+    options: &usvg::Options<'_>,
+    write_options: &usvg::WriteOptions,
+) -> Result<String, usvg::Error> {
+    let tree = usvg::Tree::from_str(svg, options)?;
+    Ok(tree.to_string(write_options))
+}
+
+/// Indentation of the usvg output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IndentStyle {
+    /// Everything on one line
+    None,
+    /// Indent with the given number of spaces
+    Spaces(u8),
+    /// Indent with tabs
+    Tabs,
+}
+
+impl From<IndentStyle> for usvg::Indent {
+    fn from(indent: IndentStyle) -> Self {
+        match indent {
+            IndentStyle::None => usvg::Indent::None,
+            IndentStyle::Spaces(n) => usvg::Indent::Spaces(n),
+            IndentStyle::Tabs => usvg::Indent::Tabs,
+        }
+    }
+}
+
+/// Options of [`simplify`], a serializable version of [`usvg::WriteOptions`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct SimplifyOptions {
+    /// Indentation of the elements
+    pub indent: IndentStyle,
+    /// Indentation of the attributes, `None` keeps them on the element line
+    pub attributes_indent: IndentStyle,
+    /// Use `'` instead of `"` around attribute values
+    pub use_single_quote: bool,
+    /// Prefix added to each id, empty for none
+    pub id_prefix: String,
+    /// Number of decimals of the coordinates
+    pub coordinates_precision: u8,
+    /// Number of decimals of the transforms
+    pub transforms_precision: u8,
+    /// Keep `text` elements instead of converting them to paths
+    pub preserve_text: bool,
+}
+
+impl Default for SimplifyOptions {
+    fn default() -> Self {
+        let write_options = usvg::WriteOptions::default();
+        Self {
+            indent: IndentStyle::Spaces(4),
+            attributes_indent: IndentStyle::None,
+            use_single_quote: write_options.use_single_quote,
+            id_prefix: String::new(),
+            coordinates_precision: write_options.coordinates_precision,
+            transforms_precision: write_options.transforms_precision,
+            preserve_text: write_options.preserve_text,
+        }
+    }
+}
+
+impl SimplifyOptions {
+    /// Convert to the usvg write options
+    #[must_use]
+    pub fn write_options(&self) -> usvg::WriteOptions {
+        usvg::WriteOptions {
+            id_prefix: (!self.id_prefix.is_empty()).then(|| self.id_prefix.clone()),
+            preserve_text: self.preserve_text,
+            coordinates_precision: self.coordinates_precision,
+            transforms_precision: self.transforms_precision,
+            use_single_quote: self.use_single_quote,
+            indent: self.indent.into(),
+            attributes_indent: self.attributes_indent.into(),
+        }
+    }
+}
+
+/// Namespaces of the elements written by svg editors
+const EDITOR_NAMESPACES: &[&str] = &[
+    "http://www.inkscape.org/namespaces/inkscape",
+    "http://sodipodi.sourceforge.net/DTD/sodipodi-0.dtd",
+    "http://www.bohemiancoding.com/sketch/ns",
+    "http://ns.adobe.com/AdobeIllustrator/10.0/",
+    "http://ns.adobe.com/Graphs/1.0/",
+    "http://ns.adobe.com/SaveForWeb/1.0/",
+];
+
+/// Namespaces only used inside `<metadata>`
+const METADATA_NAMESPACES: &[&str] = &[
+    "http://www.w3.org/1999/02/22-rdf-syntax-ns#",
+    "http://creativecommons.org/ns#",
+    "http://purl.org/dc/elements/1.1/",
+];
+
+/// Attributes written by svg editors
+///
+/// The xml parser drops the attribute prefixes (`inkscape:label` is read as `label`),
+/// so editor attributes are matched by their local name.
+pub(crate) const EDITOR_ATTRIBUTES: &[&str] = &[
+    "label",
+    "groupmode",
+    "nodetypes",
+    "docname",
+    "connector-curvature",
+    "collect",
+    "export-filename",
+    "export-xdpi",
+    "export-ydpi",
+    "transform-center-x",
+    "transform-center-y",
+    "insensitive",
+    "original-d",
+    "path-effect",
+];
+
+/// Presentation attributes inherited by the children
+pub(crate) const INHERITED_ATTRIBUTES: &[&str] = &[
+    "clip-rule",
+    "color",
+    "display",
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "font-family",
+    "font-size",
+    "font-style",
+    "font-weight",
+    "stroke",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-miterlimit",
+    "stroke-opacity",
+    "stroke-width",
+    "visibility",
+];
+
+/// Default values of presentation attributes
+const DEFAULT_VALUES: &[(&str, &[&str])] = &[
+    ("opacity", &["1"]),
+    ("fill", &["black", "#000", "#000000"]),
+    ("fill-opacity", &["1"]),
+    ("fill-rule", &["nonzero"]),
+    ("clip-rule", &["nonzero"]),
+    ("stroke", &["none"]),
+    ("stroke-width", &["1"]),
+    ("stroke-opacity", &["1"]),
+    ("stroke-linecap", &["butt"]),
+    ("stroke-linejoin", &["miter"]),
+    ("stroke-miterlimit", &["4"]),
+    ("stroke-dasharray", &["none"]),
+    ("stroke-dashoffset", &["0"]),
+    ("visibility", &["visible"]),
+    ("display", &["inline"]),
+];
+
+/// Attributes holding a color
+const COLOR_ATTRIBUTES: &[&str] = &[
+    "fill",
+    "stroke",
+    "stop-color",
+    "flood-color",
+    "lighting-color",
+    "color",
+];
+
+/// Color keywords shorter than their hex notation
+const SHORT_COLOR_NAMES: &[(&str, [u8; 3])] = &[
+    ("red", [255, 0, 0]),
+    ("tan", [210, 180, 140]),
+    ("gold", [255, 215, 0]),
+    ("gray", [128, 128, 128]),
+    ("navy", [0, 0, 128]),
+    ("peru", [205, 133, 63]),
+    ("pink", [255, 192, 203]),
+    ("plum", [221, 160, 221]),
+    ("snow", [255, 250, 250]),
+    ("teal", [0, 128, 128]),
+    ("azure", [240, 255, 255]),
+    ("beige", [245, 245, 220]),
+    ("brown", [165, 42, 42]),
+    ("coral", [255, 127, 80]),
+    ("green", [0, 128, 0]),
+    ("ivory", [255, 255, 240]),
+    ("khaki", [240, 230, 140]),
+    ("linen", [250, 240, 230]),
+    ("olive", [128, 128, 0]),
+    ("wheat", [245, 222, 179]),
+    ("bisque", [255, 228, 196]),
+    ("indigo", [75, 0, 130]),
+    ("maroon", [128, 0, 0]),
+    ("orange", [255, 165, 0]),
+    ("orchid", [218, 112, 214]),
+    ("purple", [128, 0, 128]),
+    ("salmon", [250, 128, 114]),
+    ("sienna", [160, 82, 45]),
+    ("silver", [192, 192, 192]),
+    ("tomato", [255, 99, 71]),
+    ("violet", [238, 130, 238]),
+];
+
+/// Attributes holding a single number
+const NUMBER_ATTRIBUTES: &[&str] = &[
+    "x",
+    "y",
+    "width",
+    "height",
+    "cx",
+    "cy",
+    "r",
+    "rx",
+    "ry",
+    "x1",
+    "y1",
+    "x2",
+    "y2",
+    "stroke-width",
+    "opacity",
+    "fill-opacity",
+    "stroke-opacity",
+];
+
+/// Attributes holding a list of numbers
+const NUMBER_LIST_ATTRIBUTES: &[&str] = &["points", "viewBox"];
+
+/// Elements that can be used from elsewhere in the document (their rendering depends on the user)
+const REFERENCEABLE_ELEMENTS: &[&str] =
+    &["defs", "symbol", "marker", "pattern", "clipPath", "mask"];
+
+/// Elements that make ids unsafe to rename (they can reference ids in text)
+const ID_UNSAFE_ELEMENTS: &[&str] = &[
+    "style",
+    "script",
+    "animate",
+    "animateMotion",
+    "animateTransform",
+    "set",
+];
+
+/// An optimization pass over the svg tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Pass {
+    /// Remove editor metadata, editor namespaces and comments
+    RemoveMetadata,
+    /// Remove presentation attributes set to their default value
+    RemoveDefaults,
+    /// Write colors in their shortest form
+    MinifyColors,
+    /// Round numbers in path data and geometry attributes
+    RoundNumbers,
+    /// Remove unused ids and shorten the used ones
+    ShortenIds,
+    /// Replace groups by their children when the result is identical
+    CollapseGroups,
+    /// Remove empty groups and definitions
+    RemoveEmptyGroups,
+    /// Merge consecutive paths with the same attributes
+    MergePaths,
+}
+
+impl Pass {
+    /// All the passes, in execution order
+    pub const ALL: [Pass; 8] = [
+        Pass::RemoveMetadata,
+        Pass::RemoveDefaults,
+        Pass::MinifyColors,
+        Pass::RoundNumbers,
+        Pass::ShortenIds,
+        Pass::CollapseGroups,
+        Pass::RemoveEmptyGroups,
+        Pass::MergePaths,
+    ];
+
+    /// Name of the pass
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Pass::RemoveMetadata => "Remove editor metadata",
+            Pass::RemoveDefaults => "Remove default values",
+            Pass::MinifyColors => "Minify colors",
+            Pass::RoundNumbers => "Round numbers",
+            Pass::ShortenIds => "Shorten ids",
+            Pass::CollapseGroups => "Collapse groups",
+            Pass::RemoveEmptyGroups => "Remove empty groups",
+            Pass::MergePaths => "Merge paths",
+        }
+    }
+
+    /// Short description of the pass
+    #[must_use]
+    pub fn description(self) -> &'static str {
+        match self {
+            Pass::RemoveMetadata => {
+                "Remove <metadata>, Inkscape/Sodipodi/Illustrator data and comments"
+            }
+            Pass::RemoveDefaults => "Remove presentation attributes set to their default value",
+            Pass::MinifyColors => "Write colors in their shortest form (#ff0000 -> red)",
+            Pass::RoundNumbers => "Round numbers in path data and geometry attributes",
+            Pass::ShortenIds => "Remove unused ids and shorten the used ones",
+            Pass::CollapseGroups => "Replace useless groups by their children",
+            Pass::RemoveEmptyGroups => "Remove empty <g> and <defs>",
+            Pass::MergePaths => "Merge consecutive non-overlapping paths with the same attributes",
+        }
+    }
+
+    /// Run the pass on the svg root element
+    fn run(self, root: &mut Element, options: &OptimizeOptions) {
+        match self {
+            Pass::RemoveMetadata => {
+                remove_metadata(root);
+                remove_unused_namespaces(root);
+            }
+            Pass::RemoveDefaults => {
+                let has_stylesheet = has_element(root, &["style"]);
+                remove_defaults(root, &HashSet::new(), has_stylesheet);
+            }
+            Pass::MinifyColors => minify_colors(root),
+            Pass::RoundNumbers => round_numbers(root, options.precision),
+            Pass::ShortenIds => shorten_ids(root),
+            Pass::CollapseGroups => collapse_groups(root),
+            Pass::RemoveEmptyGroups => remove_empty_groups(root),
+            Pass::MergePaths => merge_paths(root),
+        }
+    }
+}
+
+/// Options of the optimizer
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct OptimizeOptions {
+    /// Enabled passes
+    pub passes: Vec<Pass>,
+    /// Number of decimals kept by [`Pass::RoundNumbers`]
+    pub precision: u64,
+    /// Indent the output
+    pub indent: bool,
+}
+
+impl Default for OptimizeOptions {
+    fn default() -> Self {
+        Self {
+            passes: Pass::ALL.to_vec(),
+            precision: 3,
+            indent: false,
+        }
+    }
+}
+
+impl OptimizeOptions {
+    /// Check if a pass is enabled
+    #[must_use]
+    pub fn is_enabled(&self, pass: Pass) -> bool {
+        self.passes.contains(&pass)
+    }
+
+    /// Enable or disable a pass
+    pub fn set_enabled(&mut self, pass: Pass, enabled: bool) {
+        self.passes.retain(|p| *p != pass);
+        if enabled {
+            self.passes.push(pass);
+        }
+    }
+}
+
+/// Result of the optimizer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptimizeReport {
+    /// Optimized svg
+    pub svg: String,
+    /// Size in bytes before the optimization
+    pub before_size: usize,
+    /// Size in bytes after the optimization
+    pub after_size: usize,
+}
+
+impl OptimizeReport {
+    /// Ratio of bytes saved, negative if the output is bigger
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn saved_ratio(&self) -> f64 {
+        if self.before_size == 0 {
+            return 0.0;
+        }
+        1.0 - self.after_size as f64 / self.before_size as f64
+    }
+}
+
+/// Run the enabled passes on the svg
+///
+/// Unlike [`simplify`], the structure of the document is kept.
+/// # Errors
+/// Fails if the svg is not valid xml
+pub fn optimize(svg: &str, options: &OptimizeOptions) -> Result<OptimizeReport, String> {
+    let mut root = Element::parse(svg.as_bytes()).map_err(|e| e.to_string())?;
+    for pass in Pass::ALL {
+        if options.is_enabled(pass) {
+            pass.run(&mut root, options);
+        }
+    }
+    let mut buf = Vec::new();
+    let writer_config = EmitterConfig {
+        perform_indent: options.indent,
+        write_document_declaration: false,
+        ..EmitterConfig::new()
+    };
+    root.write_with_config(&mut buf, writer_config)
+        .map_err(|e| e.to_string())?;
+    let optimized = String::from_utf8(buf).map_err(|e| e.to_string())?;
+    Ok(OptimizeReport {
+        before_size: svg.len(),
+        after_size: optimized.len(),
+        svg: optimized,
+    })
+}
+
+/// Child elements of an element
+fn child_elements_mut(element: &mut Element) -> impl Iterator<Item = &mut Element> {
+    element
+        .children
+        .iter_mut()
+        .filter_map(XMLNode::as_mut_element)
+}
+
+/// Call `f` on the element and all its descendants
+fn walk_mut(element: &mut Element, f: &mut impl FnMut(&mut Element)) {
+    f(element);
+    for child in child_elements_mut(element) {
+        walk_mut(child, f);
+    }
+}
+
+/// Check if the element or one of its descendants has one of the names
+fn has_element(element: &Element, names: &[&str]) -> bool {
+    names.contains(&element.name.as_str())
+        || element
+            .children
+            .iter()
+            .filter_map(XMLNode::as_element)
+            .any(|child| has_element(child, names))
+}
+
+/// Remove editor elements, attributes and comments
+fn remove_metadata(element: &mut Element) {
+    element.children.retain(|node| match node {
+        XMLNode::Element(e) => {
+            e.name != "metadata"
+                && !e
+                    .namespace
+                    .as_deref()
+                    .is_some_and(|ns| EDITOR_NAMESPACES.contains(&ns))
+        }
+        XMLNode::Comment(_) => false,
+        _ => true,
+    });
+    element
+        .attributes
+        .retain(|key, _| !EDITOR_ATTRIBUTES.contains(&key.as_str()));
+    for child in child_elements_mut(element) {
+        remove_metadata(child);
+    }
+}
+
+/// Remove the editor and metadata namespace declarations that are not used anymore
+fn remove_unused_namespaces(root: &mut Element) {
+    let mut used = HashSet::new();
+    walk_mut(root, &mut |e| {
+        if let Some(ns) = &e.namespace {
+            used.insert(ns.clone());
+        }
+    });
+    walk_mut(root, &mut |e| {
+        if let Some(namespaces) = &mut e.namespaces {
+            namespaces.0.retain(|_, uri| {
+                used.contains(uri)
+                    || !(EDITOR_NAMESPACES.contains(&uri.as_str())
+                        || METADATA_NAMESPACES.contains(&uri.as_str()))
+            });
+        }
+    });
+}
+
+/// Remove presentation attributes set to their default value
+///
+/// Inherited attributes are only removed when no ancestor sets them,
+/// and never in elements that can be used from elsewhere.
+fn remove_defaults(
+    element: &mut Element,
+    set_by_ancestors: &HashSet<String>,
+    unsafe_inherit: bool,
+) {
+    let unsafe_inherit = unsafe_inherit
+        || element.attributes.contains_key("id")
+        || REFERENCEABLE_ELEMENTS.contains(&element.name.as_str());
+    element.attributes.retain(|key, value| {
+        let is_default = DEFAULT_VALUES
+            .iter()
+            .any(|(name, defaults)| name == key && defaults.contains(&value.trim()));
+        if !is_default {
+            return true;
+        }
+        let inherited = INHERITED_ATTRIBUTES.contains(&key.as_str());
+        inherited && (unsafe_inherit || set_by_ancestors.contains(key))
+    });
+    let mut set = set_by_ancestors.clone();
+    set.extend(element.attributes.keys().cloned());
+    if let Some(style) = element.attributes.get("style") {
+        set.extend(
+            style
+                .split(';')
+                .filter_map(|declaration| declaration.split_once(':'))
+                .map(|(property, _)| property.trim().to_string()),
+        );
+    }
+    for child in child_elements_mut(element) {
+        remove_defaults(child, &set, unsafe_inherit);
+    }
+}
+
+/// Shortest notation of a color, `None` if it cannot be minified
+fn minify_color(value: &str) -> Option<String> {
+    let color = svgtypes::Color::from_str(value.trim()).ok()?;
+    if color.alpha != 255 {
+        return None;
+    }
+    let rgb = [color.red, color.green, color.blue];
+    let mut shortest = format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2]);
+    if rgb.iter().all(|c| c >> 4 == c & 0xf) {
+        shortest = format!("#{:x}{:x}{:x}", rgb[0] & 0xf, rgb[1] & 0xf, rgb[2] & 0xf);
+    }
+    if let Some((name, _)) = SHORT_COLOR_NAMES
+        .iter()
+        .find(|(name, named)| *named == rgb && name.len() < shortest.len())
+    {
+        shortest = (*name).to_string();
+    }
+    (shortest.len() < value.len()).then_some(shortest)
+}
+
+/// Write colors in their shortest form
+fn minify_colors(root: &mut Element) {
+    walk_mut(root, &mut |e| {
+        for (key, value) in &mut e.attributes {
+            if COLOR_ATTRIBUTES.contains(&key.as_str())
+                && let Some(minified) = minify_color(value)
+            {
+                *value = minified;
+            }
+        }
+    });
+}
+
+/// Round a single number, `None` if the value is not a plain number
+fn round_number(value: &str, precision: u64) -> Option<String> {
+    let number = value.trim().parse::<f64>().ok()?;
+    Some(round_to(number, precision).to_string())
+}
+
+/// Round numbers in path data and geometry attributes
+fn round_numbers(root: &mut Element, precision: u64) {
+    walk_mut(root, &mut |e| {
+        for (key, value) in &mut e.attributes {
+            let rounded = if key == "d" {
+                SvgPath::parse(value).ok().map(|mut path| {
+                    path.round(precision);
+                    path.to_string()
+                })
+            } else if NUMBER_ATTRIBUTES.contains(&key.as_str()) {
+                round_number(value, precision)
+            } else if NUMBER_LIST_ATTRIBUTES.contains(&key.as_str()) {
+                value
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|part| !part.is_empty())
+                    .map(|part| round_number(part, precision))
+                    .collect::<Option<Vec<_>>>()
+                    .map(|numbers| numbers.join(" "))
+            } else {
+                None
+            };
+            if let Some(rounded) = rounded
+                && rounded.len() < value.len()
+            {
+                *value = rounded;
+            }
+        }
+    });
+}
+
+/// Short id for an index: `a`, `b`, ..., `z`, `aa`, `ab`, ...
+fn short_id(mut index: usize) -> String {
+    let mut id = Vec::new();
+    loop {
+        // index % 26 is always a valid ascii letter offset
+        #[allow(clippy::cast_possible_truncation)]
+        id.push(b'a' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    id.reverse();
+    String::from_utf8_lossy(&id).into_owned()
+}
+
+/// Remove unused ids and shorten the used ones
+///
+/// Skipped if the document has stylesheets, scripts or animations that may reference ids.
+fn shorten_ids(root: &mut Element) {
+    if has_element(root, ID_UNSAFE_ELEMENTS) {
+        return;
+    }
+    let mut ids = Vec::new();
+    let mut references = HashSet::new();
+    walk_mut(root, &mut |e| {
+        for (key, value) in &e.attributes {
+            if key == "id" {
+                ids.push(value.clone());
+            } else if key == "href" {
+                if let Some(id) = value.strip_prefix('#') {
+                    references.insert(id.to_string());
+                }
+            } else {
+                references.extend(
+                    url_references(value)
+                        .into_iter()
+                        .map(|(_, id)| id.to_string()),
+                );
+            }
+        }
+    });
+    let mut renamed = HashMap::new();
+    let mut index = 0;
+    for id in ids.iter().filter(|id| references.contains(*id)) {
+        if renamed.contains_key(id) {
+            continue;
+        }
+        // do not take the name of a broken reference
+        let mut new_id = short_id(index);
+        while references.contains(&new_id) && !ids.contains(&new_id) {
+            index += 1;
+            new_id = short_id(index);
+        }
+        index += 1;
+        renamed.insert(id.clone(), new_id);
+    }
+    walk_mut(root, &mut |e| {
+        e.attributes
+            .retain(|key, value| match renamed.get(value.as_str()) {
+                Some(new_id) if key == "id" => {
+                    *value = new_id.clone();
+                    true
+                }
+                _ => key != "id",
+            });
+        for (key, value) in &mut e.attributes {
+            if key == "href" {
+                if let Some(new_id) = value.strip_prefix('#').and_then(|id| renamed.get(id)) {
+                    *value = format!("#{new_id}");
+                }
+            } else if let Some(renamed_value) =
+                rename_url_references(value, |id| renamed.get(id).map(String::as_str))
+            {
+                // all the references are renamed at once, a new id can be an old one
+                *value = renamed_value;
+            }
+        }
+    });
+}
+
+/// Attributes that cannot be moved from a group to its child
+const UNMOVABLE_CHILD_ATTRIBUTES: &[&str] = &["clip-path", "mask", "filter"];
+
+/// Move the attributes of a group to its only child, returns false if it is not possible
+fn move_group_attributes(
+    group_attributes: &xmltree::AttributeMap<String, String>,
+    child: &mut Element,
+) -> bool {
+    if child.attributes.contains_key("id")
+        || UNMOVABLE_CHILD_ATTRIBUTES
+            .iter()
+            .any(|attr| child.attributes.contains_key(*attr))
+    {
+        return false;
+    }
+    let movable = group_attributes.iter().all(|(key, _)| {
+        key == "transform"
+            || ((INHERITED_ATTRIBUTES.contains(&key.as_str()) || key == "opacity")
+                && !child.attributes.contains_key(key))
+    });
+    if !movable {
+        return false;
+    }
+    for (key, value) in group_attributes {
+        if key == "transform"
+            && let Some(child_transform) = child.attributes.get_mut("transform")
+        {
+            *child_transform = format!("{value} {child_transform}");
+        } else {
+            child.attributes.insert(key.clone(), value.clone());
+        }
+    }
+    true
+}
+
+/// Replace groups by their children when the result is identical
+///
+/// The groups of a `<switch>` are kept, it renders only its first matching child.
+fn collapse_groups(element: &mut Element) {
+    for child in child_elements_mut(element) {
+        collapse_groups(child);
+    }
+    if element.name == "switch" {
+        return;
+    }
+    let children = std::mem::take(&mut element.children);
+    for node in children {
+        match node {
+            XMLNode::Element(mut group) if group.name == "g" => {
+                let only_child = match group.children.as_mut_slice() {
+                    [XMLNode::Element(child)] => Some(child),
+                    _ => None,
+                };
+                if group.attributes.is_empty() {
+                    element.children.append(&mut group.children);
+                } else if let Some(child) = only_child
+                    && move_group_attributes(&group.attributes, child)
+                {
+                    element.children.append(&mut group.children);
+                } else {
+                    element.children.push(XMLNode::Element(group));
+                }
+            }
+            node => element.children.push(node),
+        }
+    }
+}
+
+/// Remove empty groups and definitions
+fn remove_empty_groups(element: &mut Element) {
+    for child in child_elements_mut(element) {
+        remove_empty_groups(child);
+    }
+    element.children.retain(|node| match node {
+        XMLNode::Element(e) if e.name == "g" || e.name == "defs" => {
+            !e.children.is_empty() || e.attributes.contains_key("id")
+        }
+        _ => true,
+    });
+}
+
+/// Box of a path element, including a margin for its stroke
+fn path_element_box(element: &Element) -> Option<(f64, f64, f64, f64)> {
+    let (min_x, min_y, max_x, max_y) = SvgPath::parse(element.attributes.get("d")?)
+        .ok()?
+        .control_box()?;
+    let has_stroke = element
+        .attributes
+        .get("stroke")
+        .is_some_and(|stroke| stroke.trim() != "none");
+    let margin = if has_stroke {
+        let width = element
+            .attributes
+            .get("stroke-width")
+            .map_or(Some(1.0), |w| w.trim().parse::<f64>().ok())?;
+        // miter joins can go further than half the stroke width
+        2.0 * width.abs() + 1.0
+    } else {
+        0.0
+    };
+    Some((
+        min_x - margin,
+        min_y - margin,
+        max_x + margin,
+        max_y + margin,
+    ))
+}
+
+/// Properties referencing an element sized by the box of the path
+const BOX_REFERENCE_PROPERTIES: &[&str] = &[
+    "fill",
+    "stroke",
+    "clip-path",
+    "mask",
+    "filter",
+    "marker",
+    "marker-start",
+    "marker-mid",
+    "marker-end",
+];
+
+/// Check if a paint, clip, mask, filter or marker of the element is a `url()`,
+/// which can use the bounding box of the element
+fn has_box_reference(element: &Element) -> bool {
+    let style = element
+        .attributes
+        .get("style")
+        .map(|style| parse_style_with_rest(style).0)
+        .unwrap_or_default();
+    element
+        .attributes
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .chain(style.iter().map(|d| (d.name.as_str(), d.value.as_str())))
+        .any(|(name, value)| {
+            BOX_REFERENCE_PROPERTIES.contains(&name) && !url_references(value).is_empty()
+        })
+}
+
+/// Check if a path element can be merged with another one
+fn is_mergeable_path(element: &Element) -> bool {
+    element.name == "path"
+        && element.children.is_empty()
+        && element.attributes.contains_key("d")
+        && !element
+            .attributes
+            .keys()
+            .any(|key| key == "id" || key.starts_with("marker") || key == "style")
+        && !has_box_reference(element)
+}
+
+/// Merge consecutive paths with the same attributes
+///
+/// Paths are only merged when their boxes do not overlap, so fill rules and opacity are kept.
+fn merge_paths(element: &mut Element) {
+    for child in child_elements_mut(element) {
+        merge_paths(child);
+    }
+    let children = std::mem::take(&mut element.children);
+    let mut merged_box: Option<(f64, f64, f64, f64)> = None;
+    for node in children {
+        let XMLNode::Element(path) = node else {
+            merged_box = None;
+            element.children.push(node);
+            continue;
+        };
+        let path_box = is_mergeable_path(&path)
+            .then(|| path_element_box(&path))
+            .flatten();
+        let previous = match element.children.last_mut() {
+            Some(XMLNode::Element(previous)) if merged_box.is_some() => Some(previous),
+            _ => None,
+        };
+        if let (Some(previous), Some(current_box), Some(previous_box)) =
+            (previous, path_box, merged_box)
+        {
+            let same_attributes = previous.attributes.len() == path.attributes.len()
+                && previous
+                    .attributes
+                    .iter()
+                    .all(|(key, value)| key == "d" || path.attributes.get(key) == Some(value));
+            let overlap = current_box.0 <= previous_box.2
+                && previous_box.0 <= current_box.2
+                && current_box.1 <= previous_box.3
+                && previous_box.1 <= current_box.3;
+            if same_attributes
+                && !overlap
+                && let Some(d) = path.attributes.get("d")
+                && let Ok(mut path_data) = SvgPath::parse(d)
+            {
+                // the first `m` of a path is absolute
+                if let Some(svgtypes::PathSegment::MoveTo { abs, .. }) =
+                    path_data.items.first_mut().map(|item| &mut item.inner)
+                {
+                    *abs = true;
+                }
+                if let Some(previous_d) = previous.attributes.get_mut("d") {
+                    previous_d.push_str(&path_data.to_string());
+                }
+                merged_box = Some((
+                    previous_box.0.min(current_box.0),
+                    previous_box.1.min(current_box.1),
+                    previous_box.2.max(current_box.2),
+                    previous_box.3.max(current_box.3),
+                ));
+                continue;
+            }
+        }
+        merged_box = path_box;
+        element.children.push(XMLNode::Element(path));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::render::{VISUAL_CHANNEL_TOLERANCE, VISUAL_MAX_RATIO, compare_svgs};
+
+    /// Run a single pass
+    fn run_pass(svg: &str, pass: Pass) -> String {
+        let options = OptimizeOptions {
+            passes: vec![pass],
+            ..OptimizeOptions::default()
+        };
+        optimize(svg, &options).unwrap().svg
+    }
+
+    #[test]
+    fn test_simplify_options() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect id="r" x="0.123456" y="0" width="5" height="5"/></svg>"#;
+        let options = SimplifyOptions {
+            indent: IndentStyle::None,
+            id_prefix: "p-".to_string(),
+            coordinates_precision: 2,
+            use_single_quote: true,
+            ..SimplifyOptions::default()
+        };
+        let simplified =
+            simplify(svg, &usvg::Options::default(), &options.write_options()).unwrap();
+        assert!(!simplified.contains('\n'));
+        assert!(simplified.contains("id='p-r'"));
+        assert!(simplified.contains("0.12"));
+        assert!(!simplified.contains("0.123"));
+    }
+
+    #[test]
+    fn test_remove_metadata() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" xmlns:sodipodi="http://sodipodi.sourceforge.net/DTD/sodipodi-0.dtd"><!-- comment --><metadata>data</metadata><sodipodi:namedview/><g inkscape:label="Layer 1" inkscape:groupmode="layer"><path d="M0 0L1 1"/></g></svg>"#;
+        assert_eq!(
+            run_pass(svg, Pass::RemoveMetadata),
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><g><path d="M0 0L1 1" /></g></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_remove_defaults() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><path d="M0 0" stroke="none" opacity="1" fill="red"/><g stroke="red"><path d="M0 0" stroke="none" opacity="1"/></g></svg>"#;
+        assert_eq!(
+            run_pass(svg, Pass::RemoveDefaults),
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><path d="M0 0" fill="red" /><g stroke="red"><path d="M0 0" stroke="none" /></g></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_minify_colors() {
+        assert_eq!(minify_color("#ff0000").as_deref(), Some("red"));
+        assert_eq!(minify_color("#FFFFFF").as_deref(), Some("#fff"));
+        assert_eq!(minify_color("rgb(0, 0, 255)").as_deref(), Some("#00f"));
+        assert_eq!(minify_color("#123456"), None);
+        assert_eq!(minify_color("none"), None);
+        assert_eq!(minify_color("url(#a)"), None);
+    }
+
+    #[test]
+    fn test_round_numbers() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10.12345 10"><rect x="1.23456" y="2" width="3.00001" height="4px"/><path d="M0.1234 0L1.98765 1"/></svg>"#;
+        assert_eq!(
+            run_pass(svg, Pass::RoundNumbers),
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10.123 10"><rect x="1.235" y="2" width="3" height="4px" /><path d="M0.123 0L1.988 1" /></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_round_relative_path() {
+        let d = format!("M0 0{}", "l0.4996 0".repeat(200));
+        let svg = format!(r#"<svg xmlns="http://www.w3.org/2000/svg"><path d="{d}"/></svg>"#);
+        let rounded = run_pass(&svg, Pass::RoundNumbers);
+        let root = Element::parse(rounded.as_bytes()).unwrap();
+        let mut path = SvgPath::parse(&root.get_child("path").unwrap().attributes["d"]).unwrap();
+        path.absolute();
+        let Some(svgtypes::PathSegment::LineTo { x, .. }) = path.items.last().map(|i| i.inner)
+        else {
+            panic!("{rounded}");
+        };
+        // the rounding error does not add up
+        assert!((x - 99.92).abs() < 0.001, "{x}");
+    }
+
+    #[test]
+    fn test_shorten_ids() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg"><defs><linearGradient id="gradient-long-name"/></defs><rect id="unused" fill="url(#gradient-long-name)"/><use href="#gradient-long-name"/></svg>"##;
+        assert_eq!(
+            run_pass(svg, Pass::ShortenIds),
+            r##"<svg xmlns="http://www.w3.org/2000/svg"><defs><linearGradient id="a" /></defs><rect fill="url(#a)" /><use href="#a" /></svg>"##
+        );
+        // the ids swap their names, every reference follows its element
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><linearGradient id="b"/><linearGradient id="a"/><rect fill="url(#b)"/><rect fill="url( #a )"/></svg>"#;
+        assert_eq!(
+            run_pass(svg, Pass::ShortenIds),
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><linearGradient id="a" /><linearGradient id="b" /><rect fill="url(#a)" /><rect fill="url(#b)" /></svg>"#
+        );
+        assert_eq!(short_id(25), "z");
+        assert_eq!(short_id(26), "aa");
+        assert_eq!(short_id(27), "ab");
+    }
+
+    #[test]
+    fn test_collapse_and_remove_empty_groups() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><g><g fill="red"><path d="M0 0"/></g><g/></g><g id="keep"/></svg>"#;
+        let options = OptimizeOptions {
+            passes: vec![Pass::CollapseGroups, Pass::RemoveEmptyGroups],
+            ..OptimizeOptions::default()
+        };
+        assert_eq!(
+            optimize(svg, &options).unwrap().svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><path d="M0 0" fill="red" /><g id="keep" /></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_collapse_switch_groups() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100"><switch><g><rect width="50" height="50" fill="red"/><rect x="50" width="50" height="50" fill="blue"/></g></switch></svg>"#;
+        let collapsed = run_pass(svg, Pass::CollapseGroups);
+        assert!(collapsed.contains("<switch><g>"), "{collapsed}");
+        let diff = compare_svgs(
+            svg,
+            &collapsed,
+            &usvg::Options::default(),
+            1,
+            u32::MAX,
+            VISUAL_CHANNEL_TOLERANCE,
+        )
+        .unwrap();
+        assert!(diff.is_equal(VISUAL_MAX_RATIO), "{diff:?}");
+    }
+
+    #[test]
+    fn test_merge_paths_with_references() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100"><linearGradient id="g"><stop offset="0" stop-color="red"/><stop offset="1" stop-color="blue"/></linearGradient><path d="M0 0H40V40H0Z" fill="url(#g)"/><path d="M60 60H100V100H60Z" fill="url(#g)"/></svg>"#;
+        let merged = run_pass(svg, Pass::MergePaths);
+        let diff = compare_svgs(
+            svg,
+            &merged,
+            &usvg::Options::default(),
+            1,
+            u32::MAX,
+            VISUAL_CHANNEL_TOLERANCE,
+        )
+        .unwrap();
+        assert!(diff.is_equal(VISUAL_MAX_RATIO), "{diff:?}");
+    }
+
+    #[test]
+    fn test_merge_paths() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><path d="M0 0L1 1" fill="red"/><path d="m5 5l1 1" fill="red"/><path d="M0 0L6 6" fill="red"/><path d="M10 10L11 11" fill="blue"/></svg>"#;
+        assert_eq!(
+            run_pass(svg, Pass::MergePaths),
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><path d="M0 0L1 1M5 5l1 1" fill="red" /><path d="M0 0L6 6" fill="red" /><path d="M10 10L11 11" fill="blue" /></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_optimize_report() {
+        let svg = include_str!("../assets/galago.svg");
+        let report = optimize(svg, &OptimizeOptions::default()).unwrap();
+        assert_eq!(report.before_size, svg.len());
+        assert!(report.after_size < report.before_size);
+        assert!(report.saved_ratio() > 0.0);
+    }
+}
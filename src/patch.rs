@@ -0,0 +1,394 @@
+//! Patch
+//! Write an edited tree back into its source text, keeping the formatting of
+//! the unchanged parts
+
+use std::ops::Range;
+
+use xmltree::{Element, EmitterConfig, XMLNode};
+
+use crate::diff::{Align, lcs_align};
+
+/// Replacement of a byte range of the source
+type Edit = (Range<usize>, String);
+
+/// Write `root` into `source`, only rewriting the bytes of what changed
+///
+/// Attributes, texts and comments are patched in place, added and removed
+/// nodes are inserted or removed with the indentation of their siblings.
+/// # Errors
+/// Fails if `source` cannot be parsed or if the patched text does not match `root`
+pub fn patch_svg(source: &str, root: &Element) -> Result<String, String> {
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..roxmltree::ParsingOptions::default()
+    };
+    let document =
+        roxmltree::Document::parse_with_options(source, options).map_err(|e| e.to_string())?;
+    let old = Element::parse(source.as_bytes()).map_err(|e| e.to_string())?;
+    let mut edits = Vec::new();
+    patch_element(source, &old, root, document.root_element(), &mut edits)?;
+    // edits do not overlap, apply them from the end to keep the ranges valid
+    edits.sort_by_key(|(range, _)| range.start);
+    let mut patched = source.to_string();
+    for (range, text) in edits.into_iter().rev() {
+        patched.replace_range(range, &text);
+    }
+    let check = Element::parse(patched.as_bytes()).map_err(|e| e.to_string())?;
+    if same_content(&check, root) && check.namespaces == root.namespaces {
+        Ok(patched)
+    } else {
+        Err("The patched svg does not match the tree".to_string())
+    }
+}
+
+/// Check if two elements have the same names, attributes and children,
+/// ignoring the namespace declarations
+fn same_content(a: &Element, b: &Element) -> bool {
+    a.name == b.name
+        && a.prefix == b.prefix
+        && a.attributes == b.attributes
+        && a.children.len() == b.children.len()
+        && a.children.iter().zip(&b.children).all(|pair| match pair {
+            (XMLNode::Element(a), XMLNode::Element(b)) => same_content(a, b),
+            (a, b) => a == b,
+        })
+}
+
+/// Key used to match the children of the old and new elements, matched
+/// elements are patched so their ids are not part of the key
+fn node_key(node: &XMLNode) -> (u8, Option<&str>) {
+    match node {
+        XMLNode::Element(e) => (0, Some(e.name.as_str())),
+        XMLNode::Comment(_) => (1, None),
+        XMLNode::CData(_) | XMLNode::Text(_) => (2, None),
+        XMLNode::ProcessingInstruction(..) => (3, None),
+    }
+}
+
+/// Drop the namespace declarations, they are already in scope in the source
+fn strip_namespaces(element: &mut Element) {
+    element.namespaces = None;
+    for child in &mut element.children {
+        if let XMLNode::Element(child) = child {
+            strip_namespaces(child);
+        }
+    }
+}
+
+/// Children of `node` as parsed by `xmltree`, which drops whitespace-only texts
+pub(crate) fn source_children<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+) -> Vec<roxmltree::Node<'a, 'input>> {
+    node.children()
+        .filter(|child| !child.is_text() || !child.text().unwrap_or_default().trim().is_empty())
+        .collect()
+}
+
+/// Check if a source node is of the same kind as the parsed one
+fn same_kind(source: roxmltree::Node<'_, '_>, node: &XMLNode) -> bool {
+    match node {
+        XMLNode::Element(_) => source.is_element(),
+        XMLNode::Comment(_) => source.is_comment(),
+        XMLNode::CData(_) | XMLNode::Text(_) => source.is_text(),
+        XMLNode::ProcessingInstruction(..) => source.is_pi(),
+    }
+}
+
+/// Whitespace between the start of the line and `pos`, with its line break,
+/// empty if there is something else before `pos` on the line
+fn line_indent(source: &str, pos: usize) -> &str {
+    let line_start = source[..pos].rfind('\n').unwrap_or(0);
+    let indent = &source[line_start..pos];
+    if indent.trim().is_empty() { indent } else { "" }
+}
+
+/// Escape `value` for an attribute delimited by `quote`
+fn escape_attribute(value: &str, quote: char) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '"' if quote == '"' => escaped.push_str("&quot;"),
+            '\'' if quote == '\'' => escaped.push_str("&apos;"),
+            // parsers normalize the whitespace characters of attributes to spaces
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => escaped.push_str("&#13;"),
+            '\t' => escaped.push_str("&#9;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape the text of a node
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Write an element, continuation lines are indented with `indent`
+fn write_element(element: &Element, indent: &str) -> Result<String, String> {
+    let mut buf = Vec::new();
+    let config = EmitterConfig {
+        perform_indent: true,
+        write_document_declaration: false,
+        ..EmitterConfig::new()
+    };
+    element
+        .write_with_config(&mut buf, config)
+        .map_err(|e| e.to_string())?;
+    let written = String::from_utf8(buf).map_err(|e| e.to_string())?;
+    let indent = indent.trim_start_matches(['\r', '\n']);
+    Ok(written.replace('\n', &format!("\n{indent}")))
+}
+
+/// Write a node, continuation lines are indented with `indent`
+fn write_node(node: &XMLNode, indent: &str) -> Result<String, String> {
+    Ok(match node {
+        XMLNode::Element(element) => {
+            let mut element = element.clone();
+            strip_namespaces(&mut element);
+            write_element(&element, indent)?
+        }
+        XMLNode::Comment(comment) => format!("<!--{comment}-->"),
+        XMLNode::CData(data) => format!("<![CDATA[{data}]]>"),
+        XMLNode::Text(text) => escape_text(text),
+        XMLNode::ProcessingInstruction(name, Some(data)) => format!("<?{name} {data}?>"),
+        XMLNode::ProcessingInstruction(name, None) => format!("<?{name}?>"),
+    })
+}
+
+/// Patch the start tag of `node` from the attributes of `old` to those of `new`
+fn patch_attributes(
+    source: &str,
+    old: &Element,
+    new: &Element,
+    node: roxmltree::Node<'_, '_>,
+    edits: &mut Vec<Edit>,
+) {
+    for attribute in node.attributes() {
+        let Some(old_value) = old.attributes.get(attribute.name()) else {
+            continue;
+        };
+        match new.attributes.get(attribute.name()) {
+            None => {
+                let range = attribute.range();
+                let start = source[..range.start].trim_end().len();
+                edits.push((start..range.end, String::new()));
+            }
+            Some(value) if value != old_value => {
+                let range = attribute.range_value();
+                let quote = source[range.end..].chars().next().unwrap_or('"');
+                edits.push((range, escape_attribute(value, quote)));
+            }
+            Some(_) => {}
+        }
+    }
+    // after the last attribute, or after the tag name
+    let end = node.attributes().next_back().map_or_else(
+        || {
+            let start = node.range().start + 1;
+            let name_len = source[start..]
+                .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+                .unwrap_or(0);
+            start + name_len
+        },
+        |attribute| attribute.range().end,
+    );
+    for (name, value) in &new.attributes {
+        if !old.attributes.contains_key(name) {
+            let text = format!(" {name}=\"{}\"", escape_attribute(value, '"'));
+            edits.push((end..end, text));
+        }
+    }
+}
+
+/// Patch the source of `node`, parsed as `old`, to match `new`
+fn patch_element(
+    source: &str,
+    old: &Element,
+    new: &Element,
+    node: roxmltree::Node<'_, '_>,
+    edits: &mut Vec<Edit>,
+) -> Result<(), String> {
+    if same_content(old, new) {
+        return Ok(());
+    }
+    let children = source_children(node);
+    let range = node.range();
+    let is_self_closing = source[..range.end].ends_with("/>");
+    let can_patch = old.name == new.name
+        && old.prefix == new.prefix
+        && children.len() == old.children.len()
+        && children
+            .iter()
+            .zip(&old.children)
+            .all(|(child, old_child)| same_kind(*child, old_child))
+        && (!is_self_closing || new.children.is_empty());
+    if !can_patch {
+        let indent = line_indent(source, range.start);
+        let mut element = new.clone();
+        strip_namespaces(&mut element);
+        // the root declares the namespaces of the document
+        if node.parent_element().is_none() {
+            element.namespaces.clone_from(&new.namespaces);
+        }
+        edits.push((range, write_element(&element, indent)?));
+        return Ok(());
+    }
+    patch_attributes(source, old, new, node, edits);
+
+    let old_keys = old.children.iter().map(node_key).collect::<Vec<_>>();
+    let new_keys = new.children.iter().map(node_key).collect::<Vec<_>>();
+    // inserted nodes go after the last visited old child
+    let mut previous: Option<roxmltree::Node<'_, '_>> = None;
+    for step in lcs_align(&old_keys, &new_keys) {
+        match step {
+            Align::Both(i, j) => {
+                let child = children[i];
+                match (&old.children[i], &new.children[j]) {
+                    (XMLNode::Element(old_child), XMLNode::Element(new_child)) => {
+                        patch_element(source, old_child, new_child, child, edits)?;
+                    }
+                    (old_child, new_child) if old_child != new_child => {
+                        let indent = line_indent(source, child.range().start);
+                        edits.push((child.range(), write_node(new_child, indent)?));
+                    }
+                    _ => {}
+                }
+                previous = Some(child);
+            }
+            Align::Old(i) => {
+                let child = children[i];
+                let range = child.range();
+                let start = range.start - line_indent(source, range.start).len();
+                edits.push((start..range.end, String::new()));
+                previous = Some(child);
+            }
+            Align::New(j) => {
+                let inserted = &new.children[j];
+                let edit = if let Some(previous) = previous {
+                    let indent = line_indent(source, previous.range().start);
+                    let end = previous.range().end;
+                    (
+                        end..end,
+                        format!("{indent}{}", write_node(inserted, indent)?),
+                    )
+                } else if let Some(first) = children.first() {
+                    let indent = line_indent(source, first.range().start);
+                    let start = first.range().start;
+                    (
+                        start..start,
+                        format!("{}{indent}", write_node(inserted, indent)?),
+                    )
+                } else {
+                    // empty element with an end tag
+                    let end_tag = source[..range.end].rfind("</").unwrap_or(range.end);
+                    (end_tag..end_tag, write_node(inserted, "")?)
+                };
+                edits.push(edit);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    const SOURCE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- hand formatted -->
+<svg xmlns="http://www.w3.org/2000/svg"   viewBox='0 0 10 10'>
+    <g id="a"  fill="red">
+        <rect width="1" height="1"/>
+    </g>
+      <circle r="2"/>
+</svg>
+"#;
+
+    /// Parse, edit and patch the source
+    fn edited(edit: impl FnOnce(&mut Element)) -> String {
+        let mut root = Element::parse(SOURCE.as_bytes()).unwrap();
+        edit(&mut root);
+        patch_svg(SOURCE, &root).unwrap()
+    }
+
+    #[test]
+    fn test_patch_attributes() {
+        assert_eq!(edited(|_| {}), SOURCE);
+        let patched = edited(|root| {
+            let g = root.get_mut_child("g").unwrap();
+            g.attributes.insert("fill".to_string(), "a&b".to_string());
+            g.attributes.shift_remove("id");
+            g.attributes
+                .insert("stroke".to_string(), "blue".to_string());
+            root.attributes
+                .insert("viewBox".to_string(), "0 0 20 20".to_string());
+        });
+        let expected = SOURCE
+            .replace("viewBox='0 0 10 10'", "viewBox='0 0 20 20'")
+            .replace(
+                r#"<g id="a"  fill="red">"#,
+                r#"<g  fill="a&amp;b" stroke="blue">"#,
+            );
+        assert_eq!(patched, expected);
+    }
+
+    #[test]
+    fn test_patch_children() {
+        let patched = edited(|root| {
+            root.children
+                .retain(|node| node.as_element().is_none_or(|e| e.name != "circle"));
+            let g = root.get_mut_child("g").unwrap();
+            g.children.push(XMLNode::Element(Element::new("path")));
+            g.children.push(XMLNode::Comment(" note ".to_string()));
+        });
+        let expected = SOURCE.replace("\n      <circle r=\"2\"/>", "").replace(
+            "<rect width=\"1\" height=\"1\"/>",
+            "<rect width=\"1\" height=\"1\"/>\n        <path />\n        <!-- note -->",
+        );
+        assert_eq!(patched, expected);
+    }
+
+    #[test]
+    fn test_patch_text_nodes() {
+        let source = "<svg>\n  <style><![CDATA[rect { fill: red; }]]></style>\n  <text>a &amp; b</text>\n  <?pi data?>\n</svg>";
+        let mut root = Element::parse(source.as_bytes()).unwrap();
+        root.children[0].as_mut_element().unwrap().children[0] =
+            XMLNode::CData("rect { fill: blue; }".to_string());
+        root.children[1].as_mut_element().unwrap().children[0] = XMLNode::Text("a < b".to_string());
+        root.children[2] = XMLNode::ProcessingInstruction("pi".to_string(), None);
+        root.children.push(XMLNode::Comment("end".to_string()));
+        let patched = patch_svg(source, &root).unwrap();
+        assert_eq!(
+            patched,
+            "<svg>\n  <style><![CDATA[rect { fill: blue; }]]></style>\n  <text>a &lt; b</text>\n  <?pi?>\n  <!--end-->\n</svg>"
+        );
+    }
+
+    #[test]
+    fn test_patch_root_namespaces() {
+        let source = r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="10" height="10"/>"#;
+        let mut root = Element::parse(source.as_bytes()).unwrap();
+        root.children.push(XMLNode::Element(Element::new("rect")));
+        let patched = patch_svg(source, &root).unwrap();
+        assert!(patched.contains(r#"xmlns="http://www.w3.org/2000/svg""#));
+        assert!(patched.contains(r#"xmlns:xlink="http://www.w3.org/1999/xlink""#));
+        assert!(patched.contains("<rect"), "{patched}");
+    }
+
+    #[test]
+    fn test_patch_attribute_whitespace() {
+        let source = r#"<svg><text x="1"/></svg>"#;
+        let mut root = Element::parse(source.as_bytes()).unwrap();
+        let text = root.get_mut_child("text").unwrap();
+        text.attributes
+            .insert("x".to_string(), "1\n2\t3\r".to_string());
+        let patched = patch_svg(source, &root).unwrap();
+        assert_eq!(patched, r#"<svg><text x="1&#10;2&#9;3&#13;"/></svg>"#);
+    }
+}
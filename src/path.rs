@@ -2,17 +2,18 @@
 //! Good reading <https://razrfalcon.github.io/notes-on-svg-parsing/path-data.html>
 
 use std::fmt::{Display, Formatter, Write};
+use std::str::FromStr;
 use svgtypes::{PathParser, PathSegment};
 
 /// Represents a single SVG path segment.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct SvgItem {
     /// Represents a single SVG path segment.
     pub inner: PathSegment,
 }
 
 /// Round a value
-fn round_to(value: f64, decimals: u64) -> f64 {
+pub(crate) fn round_to(value: f64, decimals: u64) -> f64 {
     let decimals = i32::try_from(decimals).unwrap_or(1);
     let factor = 10f64.powi(decimals);
     (value * factor).round() / factor
@@ -120,7 +121,7 @@ impl Display for SvgItem {
 }
 
 /// Represents an SVG path, which is a collection of SVG path items.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct SvgPath {
     /// A vector of SVG path items.
     pub items: Vec<SvgItem>,
@@ -558,20 +559,46 @@ impl SvgPath {
     }
 
     /// Rounds all coordinates and values in the path to the given number of decimal places.
+    ///
+    /// Relative segments are rounded from the rounded pen position, so the
+    /// rounding errors do not add up along the path.
     pub fn round(&mut self, decimal: u64) {
+        // exact and rounded pen positions, and the starts of the subpath
+        let mut exact = (0.0, 0.0);
+        let mut rounded = (0.0, 0.0);
+        let mut exact_start = exact;
+        let mut rounded_start = rounded;
         for item in &mut self.items {
+            let is_abs = item.inner.is_abs();
+            let (exact_origin, rounded_origin) = if is_abs {
+                ((0.0, 0.0), (0.0, 0.0))
+            } else {
+                (exact, rounded)
+            };
+            // round a point of the segment, returns its exact and rounded positions
+            let round_point = |x: &mut f64, y: &mut f64| {
+                let target = (exact_origin.0 + *x, exact_origin.1 + *y);
+                *x = round_to(target.0 - rounded_origin.0, decimal);
+                *y = round_to(target.1 - rounded_origin.1, decimal);
+                (target, (rounded_origin.0 + *x, rounded_origin.1 + *y))
+            };
             match &mut item.inner {
-                PathSegment::MoveTo { x, y, .. }
-                | PathSegment::LineTo { x, y, .. }
-                | PathSegment::SmoothQuadratic { x, y, .. } => {
-                    *x = round_to(*x, decimal);
-                    *y = round_to(*y, decimal);
+                PathSegment::MoveTo { x, y, .. } => {
+                    (exact, rounded) = round_point(x, y);
+                    (exact_start, rounded_start) = (exact, rounded);
+                }
+                PathSegment::LineTo { x, y, .. } | PathSegment::SmoothQuadratic { x, y, .. } => {
+                    (exact, rounded) = round_point(x, y);
                 }
                 PathSegment::HorizontalLineTo { x, .. } => {
-                    *x = round_to(*x, decimal);
+                    let mut y = 0.0;
+                    let (target, point) = round_point(x, &mut y);
+                    (exact.0, rounded.0) = (target.0, point.0);
                 }
                 PathSegment::VerticalLineTo { y, .. } => {
-                    *y = round_to(*y, decimal);
+                    let mut x = 0.0;
+                    let (target, point) = round_point(&mut x, y);
+                    (exact.1, rounded.1) = (target.1, point.1);
                 }
                 PathSegment::CurveTo {
                     x1,
@@ -582,24 +609,17 @@ impl SvgPath {
                     y,
                     ..
                 } => {
-                    *x1 = round_to(*x1, decimal);
-                    *y1 = round_to(*y1, decimal);
-                    *x2 = round_to(*x2, decimal);
-                    *y2 = round_to(*y2, decimal);
-                    *x = round_to(*x, decimal);
-                    *y = round_to(*y, decimal);
+                    round_point(x1, y1);
+                    round_point(x2, y2);
+                    (exact, rounded) = round_point(x, y);
                 }
                 PathSegment::SmoothCurveTo { x2, y2, x, y, .. } => {
-                    *x2 = round_to(*x2, decimal);
-                    *y2 = round_to(*y2, decimal);
-                    *x = round_to(*x, decimal);
-                    *y = round_to(*y, decimal);
+                    round_point(x2, y2);
+                    (exact, rounded) = round_point(x, y);
                 }
                 PathSegment::Quadratic { x1, y1, x, y, .. } => {
-                    *x1 = round_to(*x1, decimal);
-                    *y1 = round_to(*y1, decimal);
-                    *x = round_to(*x, decimal);
-                    *y = round_to(*y, decimal);
+                    round_point(x1, y1);
+                    (exact, rounded) = round_point(x, y);
                 }
                 PathSegment::EllipticalArc {
                     rx,
@@ -612,10 +632,11 @@ impl SvgPath {
                     *rx = round_to(*rx, decimal);
                     *ry = round_to(*ry, decimal);
                     *x_axis_rotation = round_to(*x_axis_rotation, decimal);
-                    *x = round_to(*x, decimal);
-                    *y = round_to(*y, decimal);
+                    (exact, rounded) = round_point(x, y);
+                }
+                PathSegment::ClosePath { .. } => {
+                    (exact, rounded) = (exact_start, rounded_start);
                 }
-                PathSegment::ClosePath { .. } => {}
             }
         }
     }
@@ -917,18 +938,33 @@ impl SvgPath {
         pts.push(cur);
 
         let mut forward_ctrl2: Vec<Option<(f64, f64)>> = vec![None];
+        // control point of quadratic segments, `T` ones are resolved from the previous segment
+        let mut forward_quad_ctrl: Vec<Option<(f64, f64)>> = vec![None];
 
         for item in contour {
+            let mut quad_ctrl = None;
             match item.inner {
                 PathSegment::MoveTo { x, y, .. } => {
                     start = (x, y);
                     cur = start;
                     forward_ctrl2.push(None);
                 }
-                PathSegment::LineTo { x, y, .. }
-                | PathSegment::Quadratic { x, y, .. }
-                | PathSegment::SmoothQuadratic { x, y, .. }
-                | PathSegment::EllipticalArc { x, y, .. } => {
+                PathSegment::LineTo { x, y, .. } | PathSegment::EllipticalArc { x, y, .. } => {
+                    cur = (x, y);
+                    forward_ctrl2.push(None);
+                }
+                PathSegment::Quadratic { x1, y1, x, y, .. } => {
+                    quad_ctrl = Some((x1, y1));
+                    cur = (x, y);
+                    forward_ctrl2.push(None);
+                }
+                PathSegment::SmoothQuadratic { x, y, .. } => {
+                    let ctrl = forward_quad_ctrl
+                        .last()
+                        .copied()
+                        .flatten()
+                        .map_or(cur, |prev| (2.0 * cur.0 - prev.0, 2.0 * cur.1 - prev.1));
+                    quad_ctrl = Some(ctrl);
                     cur = (x, y);
                     forward_ctrl2.push(None);
                 }
@@ -951,6 +987,7 @@ impl SvgPath {
                     forward_ctrl2.push(None);
                 }
             }
+            forward_quad_ctrl.push(quad_ctrl);
 
             pts.push(cur);
         }
@@ -1038,7 +1075,8 @@ impl SvgPath {
                     });
                 }
 
-                PathSegment::Quadratic { x1, y1, .. } => {
+                PathSegment::Quadratic { .. } | PathSegment::SmoothQuadratic { .. } => {
+                    let (x1, y1) = forward_quad_ctrl[i + 1].unwrap_or(seg_start);
                     out.push(SvgItem {
                         inner: PathSegment::Quadratic {
                             abs: true,
@@ -1101,6 +1139,370 @@ impl Display for SvgPath {
     }
 }
 
+impl SvgItem {
+    /// Returns the numeric values of the segment, in path data order (flags excluded)
+    fn values(&self) -> Vec<f64> {
+        match self.inner {
+            PathSegment::MoveTo { x, y, .. }
+            | PathSegment::LineTo { x, y, .. }
+            | PathSegment::SmoothQuadratic { x, y, .. } => vec![x, y],
+            PathSegment::HorizontalLineTo { x, .. } => vec![x],
+            PathSegment::VerticalLineTo { y, .. } => vec![y],
+            PathSegment::CurveTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+                ..
+            } => vec![x1, y1, x2, y2, x, y],
+            PathSegment::SmoothCurveTo { x2, y2, x, y, .. } => vec![x2, y2, x, y],
+            PathSegment::Quadratic { x1, y1, x, y, .. } => vec![x1, y1, x, y],
+            PathSegment::EllipticalArc {
+                rx,
+                ry,
+                x_axis_rotation,
+                x,
+                y,
+                ..
+            } => vec![rx, ry, x_axis_rotation, x, y],
+            PathSegment::ClosePath { .. } => vec![],
+        }
+    }
+
+    /// Compare two segments, allowing numeric values to differ by at most `tolerance`
+    #[must_use]
+    pub fn approx_eq(&self, other: &SvgItem, tolerance: f64) -> bool {
+        let flags = |item: &SvgItem| match item.inner {
+            PathSegment::EllipticalArc {
+                large_arc, sweep, ..
+            } => Some((large_arc, sweep)),
+            _ => None,
+        };
+        self.get_letter() == other.get_letter()
+            && flags(self) == flags(other)
+            && self
+                .values()
+                .iter()
+                .zip(other.values())
+                .all(|(a, b)| (a - b).abs() <= tolerance)
+    }
+}
+
+impl From<PathSegment> for SvgItem {
+    fn from(inner: PathSegment) -> Self {
+        SvgItem { inner }
+    }
+}
+
+impl FromStr for SvgItem {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // path data must start with a move, parse the segment after a dummy one
+        let mut path = SvgPath::parse(&format!("M0 0 {s}"))?;
+        match path.items.len() {
+            2 => Ok(path.items.remove(1)),
+            len => Err(format!(
+                "Expected one SVG path segment, found {} in {s}",
+                len - 1
+            )),
+        }
+    }
+}
+
+impl SvgPath {
+    /// Creates an empty path, to be used as a builder
+    ///
+    /// ```
+    /// use galago::path::SvgPath;
+    ///
+    /// let path = SvgPath::new().move_to(0.0, 0.0).line_to(10.0, 0.0).close();
+    /// assert_eq!(path.to_string(), "M0 0L10 0Z");
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends an absolute `M` segment
+    #[must_use]
+    pub fn move_to(mut self, x: f64, y: f64) -> Self {
+        self.push(PathSegment::MoveTo { abs: true, x, y });
+        self
+    }
+
+    /// Appends an absolute `L` segment
+    #[must_use]
+    pub fn line_to(mut self, x: f64, y: f64) -> Self {
+        self.push(PathSegment::LineTo { abs: true, x, y });
+        self
+    }
+
+    /// Appends an absolute `C` segment
+    #[must_use]
+    pub fn cubic_to(mut self, x1: f64, y1: f64, x2: f64, y2: f64, x: f64, y: f64) -> Self {
+        self.push(PathSegment::CurveTo {
+            abs: true,
+            x1,
+            y1,
+            x2,
+            y2,
+            x,
+            y,
+        });
+        self
+    }
+
+    /// Appends an absolute `A` segment
+    #[must_use]
+    #[allow(clippy::too_many_arguments)]
+    pub fn arc_to(
+        mut self,
+        rx: f64,
+        ry: f64,
+        x_axis_rotation: f64,
+        large_arc: bool,
+        sweep: bool,
+        x: f64,
+        y: f64,
+    ) -> Self {
+        self.push(PathSegment::EllipticalArc {
+            abs: true,
+            rx,
+            ry,
+            x_axis_rotation,
+            large_arc,
+            sweep,
+            x,
+            y,
+        });
+        self
+    }
+
+    /// Appends a `Z` segment
+    #[must_use]
+    pub fn close(mut self) -> Self {
+        self.push(PathSegment::ClosePath { abs: true });
+        self
+    }
+
+    /// Appends a segment
+    pub fn push(&mut self, segment: impl Into<SvgItem>) {
+        self.items.push(segment.into());
+    }
+
+    /// Number of segments
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns true if the path has no segment
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Iterate over the segments
+    pub fn iter(&self) -> std::slice::Iter<'_, SvgItem> {
+        self.items.iter()
+    }
+
+    /// Iterate mutably over the segments
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, SvgItem> {
+        self.items.iter_mut()
+    }
+
+    /// Box containing all the points and control points of the path
+    ///
+    /// The box always contains the path but may be larger than its exact bounds.
+    /// Returns `(min_x, min_y, max_x, max_y)`, or `None` for an empty path.
+    #[must_use]
+    pub fn control_box(&self) -> Option<(f64, f64, f64, f64)> {
+        let mut path = self.clone();
+        path.absolute();
+        let mut bbox: Option<(f64, f64, f64, f64)> = None;
+        let mut add = |x: f64, y: f64, margin: f64| {
+            let (min_x, min_y, max_x, max_y) = bbox.get_or_insert((x, y, x, y));
+            *min_x = min_x.min(x - margin);
+            *min_y = min_y.min(y - margin);
+            *max_x = max_x.max(x + margin);
+            *max_y = max_y.max(y + margin);
+        };
+        let mut cur = (0.0, 0.0);
+        let mut start = (0.0, 0.0);
+        // last control points, used by the smooth segments
+        let mut cubic_ctrl: Option<(f64, f64)> = None;
+        let mut quad_ctrl: Option<(f64, f64)> = None;
+        let reflect = |ctrl: Option<(f64, f64)>, cur: (f64, f64)| {
+            ctrl.map_or(cur, |(x, y)| (2.0 * cur.0 - x, 2.0 * cur.1 - y))
+        };
+        for item in &path {
+            let (mut next_cubic_ctrl, mut next_quad_ctrl) = (None, None);
+            match item.inner {
+                PathSegment::MoveTo { x, y, .. } => {
+                    add(x, y, 0.0);
+                    cur = (x, y);
+                    start = cur;
+                }
+                PathSegment::LineTo { x, y, .. } => {
+                    add(x, y, 0.0);
+                    cur = (x, y);
+                }
+                PathSegment::HorizontalLineTo { x, .. } => {
+                    cur.0 = x;
+                    add(cur.0, cur.1, 0.0);
+                }
+                PathSegment::VerticalLineTo { y, .. } => {
+                    cur.1 = y;
+                    add(cur.0, cur.1, 0.0);
+                }
+                PathSegment::CurveTo {
+                    x1,
+                    y1,
+                    x2,
+                    y2,
+                    x,
+                    y,
+                    ..
+                } => {
+                    add(x1, y1, 0.0);
+                    add(x2, y2, 0.0);
+                    add(x, y, 0.0);
+                    next_cubic_ctrl = Some((x2, y2));
+                    cur = (x, y);
+                }
+                PathSegment::SmoothCurveTo { x2, y2, x, y, .. } => {
+                    let (x1, y1) = reflect(cubic_ctrl, cur);
+                    add(x1, y1, 0.0);
+                    add(x2, y2, 0.0);
+                    add(x, y, 0.0);
+                    next_cubic_ctrl = Some((x2, y2));
+                    cur = (x, y);
+                }
+                PathSegment::Quadratic { x1, y1, x, y, .. } => {
+                    add(x1, y1, 0.0);
+                    add(x, y, 0.0);
+                    next_quad_ctrl = Some((x1, y1));
+                    cur = (x, y);
+                }
+                PathSegment::SmoothQuadratic { x, y, .. } => {
+                    let (x1, y1) = reflect(quad_ctrl, cur);
+                    add(x1, y1, 0.0);
+                    add(x, y, 0.0);
+                    next_quad_ctrl = Some((x1, y1));
+                    cur = (x, y);
+                }
+                PathSegment::EllipticalArc { rx, ry, x, y, .. } => {
+                    // the arc stays in a circle around its end points
+                    let chord = (x - cur.0).hypot(y - cur.1);
+                    let margin = 2.0 * rx.abs().max(ry.abs()).max(chord);
+                    add(cur.0, cur.1, margin);
+                    add(x, y, margin);
+                    cur = (x, y);
+                }
+                PathSegment::ClosePath { .. } => cur = start,
+            }
+            cubic_ctrl = next_cubic_ctrl;
+            quad_ctrl = next_quad_ctrl;
+        }
+        bbox
+    }
+
+    /// Compare two paths segment by segment, allowing numeric values to differ by at most `tolerance`
+    #[must_use]
+    pub fn approx_eq(&self, other: &SvgPath, tolerance: f64) -> bool {
+        self.items.len() == other.items.len()
+            && self
+                .items
+                .iter()
+                .zip(&other.items)
+                .all(|(a, b)| a.approx_eq(b, tolerance))
+    }
+}
+
+impl FromStr for SvgPath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SvgPath::parse(s)
+    }
+}
+
+impl IntoIterator for SvgPath {
+    type Item = SvgItem;
+    type IntoIter = std::vec::IntoIter<SvgItem>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a SvgPath {
+    type Item = &'a SvgItem;
+    type IntoIter = std::slice::Iter<'a, SvgItem>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut SvgPath {
+    type Item = &'a mut SvgItem;
+    type IntoIter = std::slice::IterMut<'a, SvgItem>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter_mut()
+    }
+}
+
+impl<T: Into<SvgItem>> FromIterator<T> for SvgPath {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        SvgPath {
+            items: iter.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl<T: Into<SvgItem>> Extend<T> for SvgPath {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.items.extend(iter.into_iter().map(Into::into));
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for SvgItem {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SvgItem {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Serialized as path data (`"M0 0L10 0Z"`)
+#[cfg(feature = "serde")]
+impl serde::Serialize for SvgPath {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SvgPath {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 /// Convert a polyline string to an SVG path data string
 /// # Errors
 /// Fails if fails to write to `path_data`
@@ -1184,6 +1586,473 @@ pub fn ellipse_to_path(cx: &str, cy: &str, rx: &str, ry: &str) -> Result<String,
     Ok(formatted)
 }
 
+/// Tolerance used when comparing coordinates during primitive detection
+const PRIMITIVE_EPSILON: f64 = 1e-6;
+
+/// Decimals kept in the attributes of a detected primitive
+const PRIMITIVE_DECIMALS: u64 = 6;
+
+/// Compare two floats with the primitive tolerance
+fn approx_eq(a: f64, b: f64) -> bool {
+    (a - b).abs() <= PRIMITIVE_EPSILON * a.abs().max(b.abs()).max(1.0)
+}
+
+/// A basic SVG shape that can be used in place of a `path`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Primitive {
+    /// `<rect>`, with optional rounded corners
+    Rect {
+        /// Left edge
+        x: f64,
+        /// Top edge
+        y: f64,
+        /// Width
+        width: f64,
+        /// Height
+        height: f64,
+        /// Corner radius (x, y), if rounded
+        radius: Option<(f64, f64)>,
+    },
+    /// `<circle>`
+    Circle {
+        /// Center x
+        cx: f64,
+        /// Center y
+        cy: f64,
+        /// Radius
+        r: f64,
+    },
+    /// `<ellipse>`
+    Ellipse {
+        /// Center x
+        cx: f64,
+        /// Center y
+        cy: f64,
+        /// Radius x
+        rx: f64,
+        /// Radius y
+        ry: f64,
+    },
+    /// `<line>`
+    Line {
+        /// Start x
+        x1: f64,
+        /// Start y
+        y1: f64,
+        /// End x
+        x2: f64,
+        /// End y
+        y2: f64,
+    },
+    /// `<polyline>`
+    Polyline {
+        /// Points of the polyline
+        points: Vec<(f64, f64)>,
+    },
+    /// `<polygon>`
+    Polygon {
+        /// Points of the polygon
+        points: Vec<(f64, f64)>,
+    },
+}
+
+/// Edge of a single contour, in absolute coordinates
+#[derive(Debug, Clone, Copy)]
+enum ContourEdge {
+    /// Straight line to a point
+    Line((f64, f64)),
+    /// Elliptical arc to a point
+    Arc {
+        /// Radius x
+        rx: f64,
+        /// Radius y
+        ry: f64,
+        /// Rotation of the x axis
+        x_axis_rotation: f64,
+        /// Large arc flag
+        large_arc: bool,
+        /// Sweep flag
+        sweep: bool,
+        /// End point
+        to: (f64, f64),
+    },
+}
+
+impl Primitive {
+    /// Name of the SVG element
+    #[must_use]
+    pub fn element_name(&self) -> &'static str {
+        match self {
+            Primitive::Rect { .. } => "rect",
+            Primitive::Circle { .. } => "circle",
+            Primitive::Ellipse { .. } => "ellipse",
+            Primitive::Line { .. } => "line",
+            Primitive::Polyline { .. } => "polyline",
+            Primitive::Polygon { .. } => "polygon",
+        }
+    }
+
+    /// Attributes of the SVG element describing the primitive
+    #[must_use]
+    pub fn attributes(&self) -> Vec<(&'static str, String)> {
+        /// Format a number without float noise
+        fn number(value: f64) -> String {
+            (round_to(value, PRIMITIVE_DECIMALS) + 0.0).to_string()
+        }
+        /// Format a list of points
+        fn points_attribute(points: &[(f64, f64)]) -> String {
+            points
+                .iter()
+                .map(|(x, y)| format!("{},{}", number(*x), number(*y)))
+                .collect::<Vec<_>>()
+                .join(" ")
+        }
+        match self {
+            Primitive::Rect {
+                x,
+                y,
+                width,
+                height,
+                radius,
+            } => {
+                let mut attrs = vec![
+                    ("x", number(*x)),
+                    ("y", number(*y)),
+                    ("width", number(*width)),
+                    ("height", number(*height)),
+                ];
+                if let Some((rx, ry)) = radius {
+                    attrs.push(("rx", number(*rx)));
+                    if !approx_eq(*rx, *ry) {
+                        attrs.push(("ry", number(*ry)));
+                    }
+                }
+                attrs
+            }
+            Primitive::Circle { cx, cy, r } => {
+                vec![("cx", number(*cx)), ("cy", number(*cy)), ("r", number(*r))]
+            }
+            Primitive::Ellipse { cx, cy, rx, ry } => vec![
+                ("cx", number(*cx)),
+                ("cy", number(*cy)),
+                ("rx", number(*rx)),
+                ("ry", number(*ry)),
+            ],
+            Primitive::Line { x1, y1, x2, y2 } => vec![
+                ("x1", number(*x1)),
+                ("y1", number(*y1)),
+                ("x2", number(*x2)),
+                ("y2", number(*y2)),
+            ],
+            Primitive::Polyline { points } | Primitive::Polygon { points } => {
+                vec![("points", points_attribute(points))]
+            }
+        }
+    }
+}
+
+impl SvgPath {
+    /// Detect if the path is exactly a basic shape (rect, circle, line, ...)
+    ///
+    /// This is the inverse of [`rect_to_path`], [`circle_to_path`], [`ellipse_to_path`],
+    /// [`line_to_path`], [`polyline_to_path`] and [`polygon_to_path`].
+    #[must_use]
+    pub fn to_primitive(&self) -> Option<Primitive> {
+        let (start, edges, closed) = self.single_contour()?;
+        if edges.is_empty() {
+            return None;
+        }
+        if edges.iter().all(|e| matches!(e, ContourEdge::Line(_))) {
+            return Self::lines_to_primitive(start, &edges, closed);
+        }
+        Self::ellipse_to_primitive(start, &edges)
+            .or_else(|| Self::rounded_rect_to_primitive(start, &edges, closed))
+    }
+
+    /// Flatten the path into a single contour made of lines and arcs
+    ///
+    /// Returns the start point, the edges and whether the contour is closed.
+    fn single_contour(&self) -> Option<((f64, f64), Vec<ContourEdge>, bool)> {
+        let mut path = self.clone();
+        path.absolute();
+        let mut items = path.items.iter().peekable();
+        let mut start = None;
+        // consecutive MoveTo only keep the last one
+        while let Some(SvgItem {
+            inner: PathSegment::MoveTo { x, y, .. },
+        }) = items.peek()
+        {
+            start = Some((*x, *y));
+            items.next();
+        }
+        let start = start?;
+        let mut cur = start;
+        let mut edges = Vec::new();
+        let mut closed = false;
+        for item in items {
+            if closed {
+                return None;
+            }
+            let edge = match item.inner {
+                PathSegment::LineTo { x, y, .. } => ContourEdge::Line((x, y)),
+                PathSegment::HorizontalLineTo { x, .. } => ContourEdge::Line((x, cur.1)),
+                PathSegment::VerticalLineTo { y, .. } => ContourEdge::Line((cur.0, y)),
+                PathSegment::EllipticalArc {
+                    rx,
+                    ry,
+                    x_axis_rotation,
+                    large_arc,
+                    sweep,
+                    x,
+                    y,
+                    ..
+                } => ContourEdge::Arc {
+                    rx,
+                    ry,
+                    x_axis_rotation,
+                    large_arc,
+                    sweep,
+                    to: (x, y),
+                },
+                PathSegment::ClosePath { .. } => {
+                    closed = true;
+                    continue;
+                }
+                _ => return None,
+            };
+            cur = match edge {
+                ContourEdge::Line(to) | ContourEdge::Arc { to, .. } => to,
+            };
+            edges.push(edge);
+        }
+        Some((start, edges, closed))
+    }
+
+    /// Detect a line, a polyline, a polygon or a rect from straight edges
+    fn lines_to_primitive(
+        start: (f64, f64),
+        edges: &[ContourEdge],
+        closed: bool,
+    ) -> Option<Primitive> {
+        let mut points = vec![start];
+        points.extend(edges.iter().filter_map(|e| match e {
+            ContourEdge::Line(to) => Some(*to),
+            ContourEdge::Arc { .. } => None,
+        }));
+        let same_point = |a: (f64, f64), b: (f64, f64)| approx_eq(a.0, b.0) && approx_eq(a.1, b.1);
+        if !closed {
+            if points.len() == 2 {
+                return Some(Primitive::Line {
+                    x1: points[0].0,
+                    y1: points[0].1,
+                    x2: points[1].0,
+                    y2: points[1].1,
+                });
+            }
+            return Some(Primitive::Polyline { points });
+        }
+        if points.len() > 1 && same_point(points[0], points[points.len() - 1]) {
+            points.pop();
+        }
+        if points.len() < 3 {
+            return None;
+        }
+        if points.len() == 4 {
+            // edges must alternate between horizontal and vertical
+            let is_axis_edge = |i: usize, horizontal: bool| {
+                let a = points[i];
+                let b = points[(i + 1) % 4];
+                if horizontal {
+                    approx_eq(a.1, b.1) && !approx_eq(a.0, b.0)
+                } else {
+                    approx_eq(a.0, b.0) && !approx_eq(a.1, b.1)
+                }
+            };
+            let is_rect = [true, false]
+                .into_iter()
+                .any(|first| (0..4).all(|i| is_axis_edge(i, (i % 2 == 0) == first)));
+            if is_rect {
+                let min_x = points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+                let min_y = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+                let max_x = points.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+                let max_y = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+                return Some(Primitive::Rect {
+                    x: min_x,
+                    y: min_y,
+                    width: max_x - min_x,
+                    height: max_y - min_y,
+                    radius: None,
+                });
+            }
+        }
+        Some(Primitive::Polygon { points })
+    }
+
+    /// Detect a circle or an ellipse made of 2 half arcs or 4 quarter arcs
+    fn ellipse_to_primitive(start: (f64, f64), edges: &[ContourEdge]) -> Option<Primitive> {
+        let mut points = vec![start];
+        let mut radius = None;
+        let mut direction = None;
+        for edge in edges {
+            let ContourEdge::Arc {
+                rx,
+                ry,
+                x_axis_rotation,
+                large_arc,
+                sweep,
+                to,
+            } = *edge
+            else {
+                return None;
+            };
+            if rx <= 0.0 || ry <= 0.0 {
+                return None;
+            }
+            if !approx_eq(rx, ry) && !approx_eq(x_axis_rotation.rem_euclid(180.0), 0.0) {
+                return None;
+            }
+            if edges.len() == 4 && large_arc {
+                return None;
+            }
+            match radius {
+                None => radius = Some((rx, ry)),
+                Some((r_x, r_y)) if approx_eq(r_x, rx) && approx_eq(r_y, ry) => {}
+                Some(_) => return None,
+            }
+            match direction {
+                None => direction = Some(sweep),
+                Some(s) if s == sweep => {}
+                Some(_) => return None,
+            }
+            points.push(to);
+        }
+        let (rx, ry) = radius?;
+        let half = match edges.len() {
+            2 => 1,
+            4 => 2,
+            _ => return None,
+        };
+        let last = points[points.len() - 1];
+        if !approx_eq(last.0, start.0) || !approx_eq(last.1, start.1) {
+            return None;
+        }
+        let opposite = points[half];
+        let cx = f64::midpoint(start.0, opposite.0);
+        let cy = f64::midpoint(start.1, opposite.1);
+        let on_ellipse = points.iter().all(|(x, y)| {
+            let nx = (x - cx) / rx;
+            let ny = (y - cy) / ry;
+            approx_eq(nx * nx + ny * ny, 1.0)
+        });
+        if !on_ellipse {
+            return None;
+        }
+        if half == 2 {
+            // quarter arcs must go through the ellipse extremities
+            let on_axis = points
+                .iter()
+                .all(|(x, y)| approx_eq(*x, cx) || approx_eq(*y, cy));
+            if !on_axis {
+                return None;
+            }
+        }
+        if approx_eq(rx, ry) {
+            Some(Primitive::Circle { cx, cy, r: rx })
+        } else {
+            Some(Primitive::Ellipse { cx, cy, rx, ry })
+        }
+    }
+
+    /// Detect a rect with rounded corners: 4 quarter arcs joined by straight edges
+    fn rounded_rect_to_primitive(
+        start: (f64, f64),
+        edges: &[ContourEdge],
+        closed: bool,
+    ) -> Option<Primitive> {
+        let mut points = vec![start];
+        let mut cur = start;
+        let mut radius = None;
+        let mut direction = None;
+        let mut arcs = 0;
+        for edge in edges {
+            match *edge {
+                ContourEdge::Line(to) => {
+                    if !approx_eq(to.0, cur.0) && !approx_eq(to.1, cur.1) {
+                        return None;
+                    }
+                    cur = to;
+                }
+                ContourEdge::Arc {
+                    rx,
+                    ry,
+                    x_axis_rotation,
+                    large_arc,
+                    sweep,
+                    to,
+                } => {
+                    if large_arc
+                        || rx <= 0.0
+                        || ry <= 0.0
+                        || !approx_eq(x_axis_rotation.rem_euclid(90.0), 0.0)
+                    {
+                        return None;
+                    }
+                    match radius {
+                        None => radius = Some((rx, ry)),
+                        Some((r_x, r_y)) if approx_eq(r_x, rx) && approx_eq(r_y, ry) => {}
+                        Some(_) => return None,
+                    }
+                    match direction {
+                        None => direction = Some(sweep),
+                        Some(s) if s == sweep => {}
+                        Some(_) => return None,
+                    }
+                    if !approx_eq((to.0 - cur.0).abs(), rx) || !approx_eq((to.1 - cur.1).abs(), ry)
+                    {
+                        return None;
+                    }
+                    arcs += 1;
+                    cur = to;
+                }
+            }
+            points.push(cur);
+        }
+        let (rx, ry) = radius?;
+        if arcs != 4 {
+            return None;
+        }
+        let ends_at_start = approx_eq(cur.0, start.0) && approx_eq(cur.1, start.1);
+        // a closed contour may end with an implicit straight edge
+        let closing_edge = closed && (approx_eq(cur.0, start.0) || approx_eq(cur.1, start.1));
+        if !ends_at_start && !closing_edge {
+            return None;
+        }
+        let min_x = points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+        let min_y = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+        let max_x = points.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+        let max_y = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+        let on_border = points.iter().all(|(x, y)| {
+            let on_horizontal = (approx_eq(*y, min_y) || approx_eq(*y, max_y))
+                && *x >= min_x + rx - PRIMITIVE_EPSILON
+                && *x <= max_x - rx + PRIMITIVE_EPSILON;
+            let on_vertical = (approx_eq(*x, min_x) || approx_eq(*x, max_x))
+                && *y >= min_y + ry - PRIMITIVE_EPSILON
+                && *y <= max_y - ry + PRIMITIVE_EPSILON;
+            on_horizontal || on_vertical
+        });
+        if !on_border {
+            return None;
+        }
+        Some(Primitive::Rect {
+            x: min_x,
+            y: min_y,
+            width: max_x - min_x,
+            height: max_y - min_y,
+            radius: Some((rx, ry)),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::unwrap_used)]
@@ -1315,4 +2184,432 @@ mod tests {
             "M116 202C109 199 102 191 100 184C94 163 116 145 136 155C141 158 147 165 149 171C152 181 149 190 141 197C134 204 125 205 116 202ZM133 185C137 185 140 183 140 179C140 175 138 172 133 172C128 172 126 175 126 179C126 183 128 185 133 185Z"
         );
     }
+
+    #[test]
+    fn test_path_reversed_quadratic() {
+        // the control point of `T` is the reflection of the previous one
+        let parsed = SvgPath::parse("M0 0Q10 10 20 0T40 0").unwrap();
+        assert_eq!(parsed.reversed().to_string(), "M40 0Q30 -10 20 0Q10 10 0 0");
+    }
+
+    #[test]
+    fn test_path_to_primitive() {
+        let to_primitive = |d: &str| SvgPath::parse(d).unwrap().to_primitive();
+        assert_eq!(
+            to_primitive(&rect_to_path("10", "20", "30", "40")),
+            Some(Primitive::Rect {
+                x: 10.0,
+                y: 20.0,
+                width: 30.0,
+                height: 40.0,
+                radius: None
+            })
+        );
+        assert_eq!(
+            to_primitive(&circle_to_path("50", "50", "50").unwrap()),
+            Some(Primitive::Circle {
+                cx: 50.0,
+                cy: 50.0,
+                r: 50.0
+            })
+        );
+        assert_eq!(
+            to_primitive(&ellipse_to_path("100", "50", "100", "50").unwrap()),
+            Some(Primitive::Ellipse {
+                cx: 100.0,
+                cy: 50.0,
+                rx: 100.0,
+                ry: 50.0
+            })
+        );
+        assert_eq!(
+            to_primitive(&line_to_path("0", "0", "80", "20")),
+            Some(Primitive::Line {
+                x1: 0.0,
+                y1: 0.0,
+                x2: 80.0,
+                y2: 20.0
+            })
+        );
+        assert_eq!(
+            to_primitive(&polyline_to_path("0 100 50 25 50 75 100 0").unwrap()),
+            Some(Primitive::Polyline {
+                points: vec![(0.0, 100.0), (50.0, 25.0), (50.0, 75.0), (100.0, 0.0)]
+            })
+        );
+        assert_eq!(
+            to_primitive(&polygon_to_path("0 100 50 25 50 75").unwrap()),
+            Some(Primitive::Polygon {
+                points: vec![(0.0, 100.0), (50.0, 25.0), (50.0, 75.0)]
+            })
+        );
+        assert_eq!(
+            to_primitive("M2 0H8A2 1 0 0 1 10 1V5A2 1 0 0 1 8 6H2A2 1 0 0 1 0 5V1A2 1 0 0 1 2 0Z"),
+            Some(Primitive::Rect {
+                x: 0.0,
+                y: 0.0,
+                width: 10.0,
+                height: 6.0,
+                radius: Some((2.0, 1.0))
+            })
+        );
+        assert_eq!(to_primitive("M0 0C1 1 2 2 3 3"), None);
+        assert_eq!(to_primitive("M0 0L1 1ZM2 2L3 3Z"), None);
+        assert_eq!(to_primitive("M0 0A5 5 0 0 1 10 0A5 5 0 0 1 0 10"), None);
+        // open contours are not closed shapes
+        assert_eq!(to_primitive("M0 5A5 5 0 0 1 10 5A5 5 0 0 1 0 5.5"), None);
+        assert_eq!(
+            to_primitive("M2 0H8A2 1 0 0 1 10 1V5A2 1 0 0 1 8 6H2A2 1 0 0 1 0 5V1A2 1 0 0 1 2 0"),
+            Some(Primitive::Rect {
+                x: 0.0,
+                y: 0.0,
+                width: 10.0,
+                height: 6.0,
+                radius: Some((2.0, 1.0))
+            })
+        );
+        assert_eq!(
+            to_primitive(
+                "M2 0H8A2 1 0 0 1 10 1V5A2 1 0 0 1 8 6H2A2 1 0 0 1 0 5V1A2 1 0 0 1 2 0L3 0"
+            ),
+            None
+        );
+        assert_eq!(
+            to_primitive("M2 0H8A2 1 0 0 1 10 1V5A2 1 0 0 1 8 6H2A2 1 0 0 1 0 5V2A2 1 0 0 1 2 1"),
+            None
+        );
+        assert_eq!(
+            to_primitive("M2 0H8A2 1 0 0 1 10 1V5A2 1 0 0 1 8 6H2A2 1 0 0 1 0 5V2A2 1 0 0 1 2 1Z"),
+            None
+        );
+        assert!(
+            to_primitive("M3 0H8A2 1 0 0 1 10 1V5A2 1 0 0 1 8 6H2A2 1 0 0 1 0 5V1A2 1 0 0 1 2 0Z")
+                .is_some()
+        );
+        assert_eq!(
+            to_primitive("M3 0H8A2 1 0 0 1 10 1V5A2 1 0 0 1 8 6H2A2 1 0 0 1 0 5V1A2 1 0 0 1 2 0"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_primitive_attributes() {
+        let rect = Primitive::Rect {
+            x: 1.0,
+            y: 2.0,
+            width: 3.0,
+            height: 4.5,
+            radius: Some((1.0, 1.0)),
+        };
+        assert_eq!(rect.element_name(), "rect");
+        assert_eq!(
+            rect.attributes(),
+            vec![
+                ("x", "1".to_string()),
+                ("y", "2".to_string()),
+                ("width", "3".to_string()),
+                ("height", "4.5".to_string()),
+                ("rx", "1".to_string()),
+            ]
+        );
+        let polygon = Primitive::Polygon {
+            points: vec![(0.0, 1.0), (2.0, 3.0), (4.0, 5.0)],
+        };
+        assert_eq!(
+            polygon.attributes(),
+            vec![("points", "0,1 2,3 4,5".to_string())]
+        );
+        let circle = Primitive::Circle {
+            cx: 0.1 + 0.2,
+            cy: -1e-9,
+            r: 10.0 / 3.0,
+        };
+        assert_eq!(
+            circle.attributes(),
+            vec![
+                ("cx", "0.3".to_string()),
+                ("cy", "0".to_string()),
+                ("r", "3.333333".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_path_builder() {
+        let built = SvgPath::new()
+            .move_to(0.0, 0.0)
+            .line_to(10.0, 0.0)
+            .cubic_to(10.0, 5.0, 5.0, 10.0, 0.0, 10.0)
+            .arc_to(5.0, 5.0, 0.0, false, true, 0.0, 0.0)
+            .close();
+        assert_eq!(built.to_string(), "M0 0L10 0C10 5 5 10 0 10A5 5 0 0 1 0 0Z");
+        let parsed: SvgPath = "M0 0L10 0C10 5 5 10 0 10A5 5 0 0 1 0 0Z".parse().unwrap();
+        assert_eq!(built, parsed);
+        assert_eq!(built.len(), 5);
+
+        let letters = built.iter().map(SvgItem::get_letter).collect::<String>();
+        assert_eq!(letters, "MLCAZ");
+
+        let mut extended: SvgPath = built.clone().into_iter().take(2).collect();
+        extended.extend(["L0 10".parse::<SvgItem>().unwrap()]);
+        extended.extend([PathSegment::ClosePath { abs: true }]);
+        assert_eq!(extended.to_string(), "M0 0L10 0L0 10Z");
+
+        assert!("M0 0L1 1".parse::<SvgItem>().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_round_trip() {
+        use serde::Deserialize;
+        use serde::de::IntoDeserializer;
+        use serde::de::value::{Error, StrDeserializer};
+
+        // serialized with `Display`, deserialized with `FromStr`
+        for segment in [
+            "M1 2",
+            "m1 2",
+            "L0 10",
+            "l-1 2",
+            "H5",
+            "v5",
+            "C1 2 3 4 5 6",
+            "s1 2 3 4",
+            "Q1 2 3 4",
+            "t1 2",
+            "A5 5 0 0 1 10 10",
+            "Z",
+        ] {
+            let item: SvgItem = segment.parse().unwrap();
+            let serialized = item.to_string();
+            let deserializer: StrDeserializer<'_, Error> = serialized.as_str().into_deserializer();
+            assert_eq!(SvgItem::deserialize(deserializer).unwrap(), item);
+        }
+        let path = SvgPath::parse("M0 0L10 0Q5 5 0 10Z").unwrap();
+        let serialized = path.to_string();
+        let deserializer: StrDeserializer<'_, Error> = serialized.as_str().into_deserializer();
+        assert_eq!(SvgPath::deserialize(deserializer).unwrap(), path);
+    }
+
+    #[test]
+    fn test_path_approx_eq() {
+        let a = SvgPath::parse("M0 0L10 10A5 5 0 0 1 20 20Z").unwrap();
+        let b = SvgPath::parse("M0.0001 0L10 9.9999A5 5 0 0 1 20 20Z").unwrap();
+        assert_ne!(a, b);
+        assert!(a.approx_eq(&b, 0.001));
+        assert!(!a.approx_eq(&b, 0.000_01));
+        let flipped = SvgPath::parse("M0 0L10 10A5 5 0 0 0 20 20Z").unwrap();
+        assert!(!a.approx_eq(&flipped, 0.001));
+        let relative = SvgPath::parse("m0 0l10 10a5 5 0 0 1 10 10z").unwrap();
+        assert!(!a.approx_eq(&relative, 0.001));
+    }
+}
+
+#[cfg(test)]
+mod property_tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    /// Tolerance for float comparison after coordinate conversions
+    const TOLERANCE: f64 = 1e-6;
+
+    /// Integer or float coordinate
+    fn coord() -> impl Strategy<Value = f64> {
+        prop_oneof![(-100i32..100).prop_map(f64::from), -1000.0..1000.0f64]
+    }
+
+    /// Any segment but `M` and `Z`
+    fn drawing_segment() -> impl Strategy<Value = PathSegment> {
+        prop_oneof![
+            (any::<bool>(), coord(), coord()).prop_map(|(abs, x, y)| PathSegment::LineTo {
+                abs,
+                x,
+                y
+            }),
+            (any::<bool>(), coord()).prop_map(|(abs, x)| PathSegment::HorizontalLineTo { abs, x }),
+            (any::<bool>(), coord()).prop_map(|(abs, y)| PathSegment::VerticalLineTo { abs, y }),
+            (
+                any::<bool>(),
+                (coord(), coord()),
+                (coord(), coord()),
+                (coord(), coord())
+            )
+                .prop_map(|(abs, (x1, y1), (x2, y2), (x, y))| PathSegment::CurveTo {
+                    abs,
+                    x1,
+                    y1,
+                    x2,
+                    y2,
+                    x,
+                    y
+                }),
+            (any::<bool>(), (coord(), coord()), (coord(), coord())).prop_map(
+                |(abs, (x2, y2), (x, y))| PathSegment::SmoothCurveTo { abs, x2, y2, x, y }
+            ),
+            (any::<bool>(), (coord(), coord()), (coord(), coord()))
+                .prop_map(|(abs, (x1, y1), (x, y))| PathSegment::Quadratic { abs, x1, y1, x, y }),
+            (any::<bool>(), coord(), coord())
+                .prop_map(|(abs, x, y)| { PathSegment::SmoothQuadratic { abs, x, y } }),
+            (
+                any::<bool>(),
+                (0.1..100.0f64, 0.1..100.0f64, 0.0..360.0f64),
+                (any::<bool>(), any::<bool>()),
+                (coord(), coord())
+            )
+                .prop_map(
+                    |(abs, (rx, ry, x_axis_rotation), (large_arc, sweep), (x, y))| {
+                        PathSegment::EllipticalArc {
+                            abs,
+                            rx,
+                            ry,
+                            x_axis_rotation,
+                            large_arc,
+                            sweep,
+                            x,
+                            y,
+                        }
+                    }
+                ),
+        ]
+    }
+
+    /// A sub path: `M`, some drawing segments and an optional `Z`
+    fn contour() -> impl Strategy<Value = Vec<PathSegment>> {
+        (
+            any::<bool>(),
+            coord(),
+            coord(),
+            vec(drawing_segment(), 1..8),
+            any::<bool>(),
+        )
+            .prop_map(|(abs, x, y, segments, closed)| {
+                let mut contour = vec![PathSegment::MoveTo { abs, x, y }];
+                contour.extend(segments);
+                if closed {
+                    contour.push(PathSegment::ClosePath { abs });
+                }
+                contour
+            })
+    }
+
+    /// A path made of several sub paths
+    fn path() -> impl Strategy<Value = SvgPath> {
+        vec(contour(), 1..4).prop_map(|contours| contours.into_iter().flatten().collect())
+    }
+
+    /// Absolute path using only `M`, `L`, `C`, `Q`, `A` and `Z`
+    fn normalized(path: &SvgPath) -> SvgPath {
+        let mut path = path.clone();
+        path.absolute();
+        let mut cur = (0.0, 0.0);
+        let mut start = (0.0, 0.0);
+        let mut prev_cubic_ctrl = None;
+        let mut prev_quad_ctrl = None;
+        let reflect = |ctrl: Option<(f64, f64)>, cur: (f64, f64)| {
+            ctrl.map_or(cur, |(x, y): (f64, f64)| (2.0 * cur.0 - x, 2.0 * cur.1 - y))
+        };
+        let mut out = SvgPath::new();
+        for item in path {
+            let (mut cubic_ctrl, mut quad_ctrl) = (None, None);
+            let segment = match item.inner {
+                PathSegment::HorizontalLineTo { x, .. } => PathSegment::LineTo {
+                    abs: true,
+                    x,
+                    y: cur.1,
+                },
+                PathSegment::VerticalLineTo { y, .. } => PathSegment::LineTo {
+                    abs: true,
+                    x: cur.0,
+                    y,
+                },
+                PathSegment::SmoothCurveTo { x2, y2, x, y, .. } => {
+                    let (x1, y1) = reflect(prev_cubic_ctrl, cur);
+                    PathSegment::CurveTo {
+                        abs: true,
+                        x1,
+                        y1,
+                        x2,
+                        y2,
+                        x,
+                        y,
+                    }
+                }
+                PathSegment::SmoothQuadratic { x, y, .. } => {
+                    let (x1, y1) = reflect(prev_quad_ctrl, cur);
+                    PathSegment::Quadratic {
+                        abs: true,
+                        x1,
+                        y1,
+                        x,
+                        y,
+                    }
+                }
+                segment => segment,
+            };
+            match segment {
+                PathSegment::MoveTo { x, y, .. } => {
+                    cur = (x, y);
+                    start = cur;
+                }
+                PathSegment::CurveTo { x2, y2, x, y, .. } => {
+                    cubic_ctrl = Some((x2, y2));
+                    cur = (x, y);
+                }
+                PathSegment::Quadratic { x1, y1, x, y, .. } => {
+                    quad_ctrl = Some((x1, y1));
+                    cur = (x, y);
+                }
+                PathSegment::LineTo { x, y, .. } | PathSegment::EllipticalArc { x, y, .. } => {
+                    cur = (x, y);
+                }
+                PathSegment::ClosePath { .. } => cur = start,
+                _ => {}
+            }
+            prev_cubic_ctrl = cubic_ctrl;
+            prev_quad_ctrl = quad_ctrl;
+            out.push(segment);
+        }
+        out
+    }
+
+    proptest! {
+        #[test]
+        fn absolute_relative_round_trip(path in path()) {
+            let mut absolute = path.clone();
+            absolute.absolute();
+            let mut round_trip = absolute.clone();
+            round_trip.relative();
+            round_trip.absolute();
+            prop_assert!(absolute.approx_eq(&round_trip, TOLERANCE), "{absolute} != {round_trip}");
+        }
+
+        #[test]
+        fn double_reverse_is_identity(path in path()) {
+            let expected = normalized(&path);
+            let reversed = path.reversed().reversed();
+            prop_assert!(expected.approx_eq(&reversed, TOLERANCE), "{expected} != {reversed}");
+        }
+
+        #[test]
+        fn reverse_keeps_segment_count(path in path()) {
+            let reversed = normalized(&path.reversed());
+            prop_assert_eq!(reversed.len(), normalized(&path).len());
+        }
+
+        #[test]
+        fn translate_inverse_restores(path in path(), dx in coord(), dy in coord()) {
+            let mut translated = path.clone();
+            translated.translate(dx, dy);
+            translated.translate(-dx, -dy);
+            prop_assert!(path.approx_eq(&translated, TOLERANCE), "{path} != {translated}");
+        }
+
+        #[test]
+        fn to_string_reparses(path in path()) {
+            let reparsed = SvgPath::parse(&path.to_string()).unwrap();
+            prop_assert_eq!(path, reparsed);
+        }
+    }
 }
@@ -0,0 +1,794 @@
+//! Render
+//! Rasterize svg without any GUI
+
+use std::ops::Range;
+
+use resvg::tiny_skia::{Pixmap, PixmapPaint, Transform};
+use resvg::usvg;
+use xmltree::{Element, EmitterConfig, XMLNode};
+
+use crate::node::node_at_mut;
+
+/// Pick a scaler so that small svg are rendered with enough pixels
+#[must_use]
+#[allow(clippy::cast_sign_loss)]
+#[allow(clippy::cast_possible_truncation)]
+pub fn auto_scaler(size: usvg::Size) -> u32 {
+    let size = size.width().max(size.height()) as u32;
+    if size < 500 {
+        6
+    } else if size < 1000 {
+        4
+    } else if size < 2000 {
+        2
+    } else {
+        1
+    }
+}
+
+/// Most pixels of a render of the whole svg, 128 MB of pixmap
+pub const MAX_RENDER_PIXELS: u64 = 32 * 1024 * 1024;
+
+/// Scale to render a svg of `size` with `scaler`, reduced if needed so that
+/// the pixmap has at most `max_side` pixels per side and [`MAX_RENDER_PIXELS`]
+/// pixels in total
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn fit_scale(size: usvg::Size, scaler: u32, max_side: u32) -> f32 {
+    let by_side = max_side as f32 / size.width().max(size.height());
+    let by_pixels = (MAX_RENDER_PIXELS as f32 / (size.width() * size.height())).sqrt();
+    (scaler as f32).min(by_side).min(by_pixels)
+}
+
+/// Empty pixmap for the tree scaled by `scale`, each side being at least one pixel
+/// # Errors
+/// Fails if the scale is not positive or if the pixmap is too big
+#[allow(clippy::cast_sign_loss)]
+#[allow(clippy::cast_possible_truncation)]
+fn tree_pixmap(tree: &usvg::Tree, scale: f32) -> Result<Pixmap, String> {
+    if !(scale.is_finite() && scale > 0.0) {
+        return Err(format!(
+            "The render is too small: the svg cannot be drawn at the scale {scale}"
+        ));
+    }
+    // a very thin svg keeps a row or a column of pixels
+    let (w, h) = (
+        ((tree.size().width() * scale) as u32).max(1),
+        ((tree.size().height() * scale) as u32).max(1),
+    );
+    Pixmap::new(w, h)
+        .ok_or_else(|| format!("The svg is too big to render: {w}x{h} pixels, lower the scaler"))
+}
+
+/// Scale to render two svg trees alike with `scaler`, reduced so that both
+/// fit in `max_side` pixels per side
+#[must_use]
+pub fn common_scale(a: &usvg::Tree, b: &usvg::Tree, scaler: u32, max_side: u32) -> f32 {
+    fit_scale(a.size(), scaler, max_side).min(fit_scale(b.size(), scaler, max_side))
+}
+
+/// Render a parsed svg tree, scaled by `scaler`
+/// # Errors
+/// Fails if the pixmap cannot be created
+#[allow(clippy::cast_precision_loss)]
+pub fn render_tree(tree: &usvg::Tree, scaler: u32) -> Result<Pixmap, String> {
+    render_tree_at(tree, scaler as f32)
+}
+
+/// Render a parsed svg tree, scaled by `scale`
+/// # Errors
+/// Fails if the pixmap cannot be created
+pub fn render_tree_at(tree: &usvg::Tree, scale: f32) -> Result<Pixmap, String> {
+    let mut pixmap = tree_pixmap(tree, scale)?;
+    resvg::render(
+        tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    Ok(pixmap)
+}
+
+/// Rasterization of a parsed tree node by node, to follow its progress and
+/// stop it early
+pub struct TreeRenderer {
+    /// Parsed svg
+    tree: usvg::Tree,
+    /// Target of the render
+    pixmap: Pixmap,
+    /// Scale of the render
+    scale: f32,
+    /// Index paths of the nodes to render in order, the groups without
+    /// isolation are split into their children
+    nodes: Vec<Vec<usize>>,
+    /// Number of rendered nodes
+    rendered: usize,
+}
+
+impl std::fmt::Debug for TreeRenderer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TreeRenderer")
+            .field("scale", &self.scale)
+            .field("nodes", &self.nodes.len())
+            .field("rendered", &self.rendered)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TreeRenderer {
+    /// Prepare the render of `tree` scaled by `scale`
+    /// # Errors
+    /// Fails if the pixmap cannot be created
+    pub fn new(tree: usvg::Tree, scale: f32) -> Result<Self, String> {
+        let pixmap = tree_pixmap(&tree, scale)?;
+        let mut nodes = Vec::new();
+        render_steps(tree.root(), &mut Vec::new(), &mut nodes);
+        Ok(Self {
+            tree,
+            pixmap,
+            scale,
+            nodes,
+            rendered: 0,
+        })
+    }
+
+    /// Scale of the render
+    #[must_use]
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Fraction of the rendered nodes, between 0 and 1
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn progress(&self) -> f32 {
+        if self.nodes.is_empty() {
+            1.0
+        } else {
+            self.rendered as f32 / self.nodes.len() as f32
+        }
+    }
+
+    /// Check if all the nodes are rendered
+    #[must_use]
+    pub fn is_done(&self) -> bool {
+        self.rendered == self.nodes.len()
+    }
+
+    /// Render the next node, returns `false` once everything is rendered
+    #[allow(clippy::cast_precision_loss)]
+    pub fn step(&mut self) -> bool {
+        let Some((index, parent_path)) = self
+            .nodes
+            .get(self.rendered)
+            .and_then(|path| path.split_last())
+        else {
+            return false;
+        };
+        let mut parent = self.tree.root();
+        for i in parent_path {
+            match parent.children().get(*i) {
+                Some(usvg::Node::Group(group)) => parent = group,
+                _ => break,
+            }
+        }
+        if let Some(node) = parent.children().get(*index)
+            && let Some(bbox) = node.abs_layer_bounding_box()
+        {
+            let transform = Transform::from_scale(self.scale, self.scale)
+                .pre_concat(parent.abs_transform())
+                // `render_node` moves the node to the origin of the pixmap
+                .pre_translate(bbox.x(), bbox.y());
+            resvg::render_node(node, transform, &mut self.pixmap.as_mut());
+        }
+        self.rendered += 1;
+        true
+    }
+
+    /// The rendered tree and pixmap
+    #[must_use]
+    pub fn finish(self) -> (usvg::Tree, Pixmap) {
+        (self.tree, self.pixmap)
+    }
+}
+
+/// Render the region of the tree starting at the svg point (`x`, `y`), scaled
+/// by `scale`, into a pixmap of `width` by `height` pixels
+/// # Errors
+/// Fails if the pixmap cannot be created
+pub fn render_region(
+    tree: &usvg::Tree,
+    scale: f32,
+    (x, y): (f32, f32),
+    (width, height): (u32, u32),
+) -> Result<Pixmap, String> {
+    let mut pixmap = Pixmap::new(width, height)
+        .ok_or_else(|| format!("Failed to create SVG Pixmap of size {width}x{height}"))?;
+    let transform = Transform::from_scale(scale, scale).post_translate(-x * scale, -y * scale);
+    resvg::render(tree, transform, &mut pixmap.as_mut());
+    Ok(pixmap)
+}
+
+/// Collect the index paths of the nodes rendered one at a time
+fn render_steps(group: &usvg::Group, path: &mut Vec<usize>, nodes: &mut Vec<Vec<usize>>) {
+    for (index, child) in group.children().iter().enumerate() {
+        path.push(index);
+        match child {
+            // drawn directly on the canvas, its children can be drawn one by one
+            usvg::Node::Group(child) if !child.should_isolate() => {
+                render_steps(child, path, nodes);
+            }
+            _ => nodes.push(path.clone()),
+        }
+        path.pop();
+    }
+}
+
+/// Copy of the options with the default resolvers, sharing the font database,
+/// to parse on another thread
+#[must_use]
+pub fn copy_options(options: &usvg::Options<'_>) -> usvg::Options<'static> {
+    usvg::Options {
+        resources_dir: options.resources_dir.clone(),
+        dpi: options.dpi,
+        font_family: options.font_family.clone(),
+        font_size: options.font_size,
+        languages: options.languages.clone(),
+        shape_rendering: options.shape_rendering,
+        text_rendering: options.text_rendering,
+        image_rendering: options.image_rendering,
+        default_size: options.default_size,
+        fontdb: options.fontdb.clone(),
+        style_sheet: options.style_sheet.clone(),
+        ..usvg::Options::default()
+    }
+}
+
+/// Parse and render a svg string, scaled by `scaler`
+/// # Errors
+/// Fails if the svg is invalid or if the pixmap cannot be created
+pub fn render_svg(svg: &str, options: &usvg::Options<'_>, scaler: u32) -> Result<Pixmap, String> {
+    let tree = usvg::Tree::from_str(svg, options).map_err(|e| e.to_string())?;
+    render_tree(&tree, scaler)
+}
+
+/// Error of a svg text, located in the text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SvgError {
+    /// Description of the error
+    pub message: String,
+    /// Byte range of the offending text
+    pub range: Range<usize>,
+    /// Line of the error, starting at 1
+    pub line: u32,
+    /// Column of the error, starting at 1
+    pub column: u32,
+}
+
+/// Parse a svg string with usvg, locating the error in the text if it fails
+/// # Errors
+/// Fails if the svg is invalid
+pub fn parse_svg(svg: &str, options: &usvg::Options<'_>) -> Result<usvg::Tree, SvgError> {
+    usvg::Tree::from_str(svg, options).map_err(|e| {
+        let range = match &e {
+            // reported at the start, the missing end is at the end of the text
+            usvg::Error::ParsingFailed(roxmltree::Error::UnclosedRootNode) => {
+                let end = svg.trim_end().len();
+                let start = svg[..end].char_indices().next_back().map_or(0, |(i, _)| i);
+                start..end
+            }
+            usvg::Error::ParsingFailed(e) => {
+                let position = e.pos();
+                token_range(svg, text_offset(svg, position.row, position.col))
+            }
+            // the size is given by the root element
+            usvg::Error::InvalidSize => svg.find("<svg").map_or(0..0, |start| start..start + 4),
+            _ => 0..0,
+        };
+        let (line, column) = text_position(svg, range.start);
+        SvgError {
+            message: e.to_string(),
+            range,
+            line,
+            column,
+        }
+    })
+}
+
+/// Byte offset of a line and a column in characters, both starting at 1
+fn text_offset(text: &str, line: u32, column: u32) -> usize {
+    let line_start = text
+        .match_indices('\n')
+        .nth(line.saturating_sub(2) as usize)
+        .filter(|_| line > 1)
+        .map_or(0, |(i, _)| i + 1);
+    text[line_start..]
+        .char_indices()
+        .nth(column.saturating_sub(1) as usize)
+        .map_or(text.len(), |(i, _)| line_start + i)
+}
+
+/// Line and column in characters of a byte offset, both starting at 1
+#[allow(clippy::cast_possible_truncation)]
+fn text_position(text: &str, offset: usize) -> (u32, u32) {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() as u32 + 1,
+        before[line_start..].chars().count() as u32 + 1,
+    )
+}
+
+/// Range of the token starting at `offset`, at least one character
+fn token_range(text: &str, offset: usize) -> Range<usize> {
+    let is_boundary = |c: char| c.is_whitespace() || "<>=\"'/".contains(c);
+    let rest = &text[offset..];
+    let length = match rest.find(is_boundary) {
+        Some(0) | None => rest.chars().next().map_or(0, char::len_utf8),
+        Some(length) => length,
+    };
+    if length == 0 {
+        // at the end of the text, point to its last character
+        let start = text.char_indices().next_back().map_or(0, |(i, _)| i);
+        return start..text.len();
+    }
+    offset..offset + length
+}
+
+/// Prefix of the ids given to the elements without id by [`element_bounding_boxes`]
+const BOUNDING_BOX_ID_PREFIX: &str = "galago-bbox-";
+
+/// Bounding boxes of the elements at `paths`, in svg canvas coordinates
+///
+/// `None` for the paths that are not rendered elements.
+/// # Errors
+/// Fails if the svg cannot be written or parsed
+pub fn element_bounding_boxes(
+    root: &Element,
+    paths: &[Vec<usize>],
+    options: &usvg::Options<'_>,
+) -> Result<Vec<Option<usvg::Rect>>, String> {
+    // usvg only keeps the ids, give one to each element to find it
+    let mut root = root.clone();
+    let mut ids = Vec::with_capacity(paths.len());
+    for (idx, path) in paths.iter().enumerate() {
+        let id = match node_at_mut(&mut root, path) {
+            Some(XMLNode::Element(element)) => Some(
+                element
+                    .attributes
+                    .entry("id".to_string())
+                    .or_insert_with(|| format!("{BOUNDING_BOX_ID_PREFIX}{idx}"))
+                    .clone(),
+            ),
+            _ => None,
+        };
+        ids.push(id);
+    }
+    let mut buf = Vec::new();
+    root.write_with_config(&mut buf, EmitterConfig::new())
+        .map_err(|e| e.to_string())?;
+    let svg = String::from_utf8(buf).map_err(|e| e.to_string())?;
+    let tree = usvg::Tree::from_str(&svg, options).map_err(|e| e.to_string())?;
+    Ok(ids
+        .into_iter()
+        .map(|id| {
+            id.and_then(|id| tree.node_by_id(&id))
+                .map(usvg::Node::abs_bounding_box)
+        })
+        .collect())
+}
+
+/// Channel difference ignored by the visual checks (anti-aliasing)
+pub const VISUAL_CHANNEL_TOLERANCE: u8 = 16;
+
+/// Ratio of pixels allowed to differ for two renders to be visually equal
+pub const VISUAL_MAX_RATIO: f64 = 0.005;
+
+/// Pixel comparison between two renders
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VisualDiff {
+    /// Width of the compared renders
+    pub width: u32,
+    /// Height of the compared renders
+    pub height: u32,
+    /// Number of pixels with a channel difference above the tolerance
+    pub different_pixels: u64,
+    /// Biggest channel difference found
+    pub max_channel_difference: u8,
+}
+
+impl VisualDiff {
+    /// Ratio of different pixels, between 0 and 1
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn different_ratio(&self) -> f64 {
+        let total = u64::from(self.width) * u64::from(self.height);
+        if total == 0 {
+            return 0.0;
+        }
+        self.different_pixels as f64 / total as f64
+    }
+
+    /// Check if at most `max_ratio` of the pixels are different
+    #[must_use]
+    pub fn is_equal(&self, max_ratio: f64) -> bool {
+        self.different_ratio() <= max_ratio
+    }
+}
+
+/// Check that two pixmaps have the same size
+fn check_same_size(a: &Pixmap, b: &Pixmap) -> Result<(), String> {
+    if a.width() != b.width() || a.height() != b.height() {
+        return Err(format!(
+            "Renders have different sizes: {}x{} and {}x{}",
+            a.width(),
+            a.height(),
+            b.width(),
+            b.height()
+        ));
+    }
+    Ok(())
+}
+
+/// Pad two pixmaps with transparent pixels on the right and bottom so that
+/// they have the same size
+/// # Errors
+/// Fails if a padded pixmap cannot be created
+pub fn pad_to_same_size(a: Pixmap, b: Pixmap) -> Result<(Pixmap, Pixmap), String> {
+    let (width, height) = (a.width().max(b.width()), a.height().max(b.height()));
+    let pad = |pixmap: Pixmap| -> Result<Pixmap, String> {
+        if pixmap.width() == width && pixmap.height() == height {
+            return Ok(pixmap);
+        }
+        let mut padded = Pixmap::new(width, height)
+            .ok_or_else(|| format!("Failed to create pixmap of size {width}x{height}"))?;
+        padded.draw_pixmap(
+            0,
+            0,
+            pixmap.as_ref(),
+            &PixmapPaint::default(),
+            Transform::identity(),
+            None,
+        );
+        Ok(padded)
+    };
+    Ok((pad(a)?, pad(b)?))
+}
+
+/// Compare two pixmaps of the same size
+///
+/// A pixel is different if one of its channels differs by more than `channel_tolerance`.
+/// # Errors
+/// Fails if the pixmaps do not have the same size
+pub fn compare_pixmaps(
+    a: &Pixmap,
+    b: &Pixmap,
+    channel_tolerance: u8,
+) -> Result<VisualDiff, String> {
+    check_same_size(a, b)?;
+    let mut diff = VisualDiff {
+        width: a.width(),
+        height: a.height(),
+        different_pixels: 0,
+        max_channel_difference: 0,
+    };
+    for (pixel_a, pixel_b) in a.data().chunks_exact(4).zip(b.data().chunks_exact(4)) {
+        let max = pixel_a
+            .iter()
+            .zip(pixel_b)
+            .map(|(ca, cb)| ca.abs_diff(*cb))
+            .max()
+            .unwrap_or(0);
+        diff.max_channel_difference = diff.max_channel_difference.max(max);
+        if max > channel_tolerance {
+            diff.different_pixels += 1;
+        }
+    }
+    Ok(diff)
+}
+
+/// Heatmap of the differences between two pixmaps of the same size
+///
+/// Pixels within `channel_tolerance` are transparent, the others are red
+/// with an opacity growing with the difference.
+/// # Errors
+/// Fails if the pixmaps do not have the same size
+pub fn diff_heatmap(a: &Pixmap, b: &Pixmap, channel_tolerance: u8) -> Result<Pixmap, String> {
+    check_same_size(a, b)?;
+    let mut heatmap = Pixmap::new(a.width(), a.height()).ok_or_else(|| {
+        format!(
+            "Failed to create heatmap of size {}x{}",
+            a.width(),
+            a.height()
+        )
+    })?;
+    for ((pixel_a, pixel_b), pixel) in a
+        .data()
+        .chunks_exact(4)
+        .zip(b.data().chunks_exact(4))
+        .zip(heatmap.data_mut().chunks_exact_mut(4))
+    {
+        let max = pixel_a
+            .iter()
+            .zip(pixel_b)
+            .map(|(ca, cb)| ca.abs_diff(*cb))
+            .max()
+            .unwrap_or(0);
+        if max > channel_tolerance {
+            // premultiplied red, at least half opaque to stay visible
+            let alpha = max.max(128);
+            pixel.copy_from_slice(&[alpha, 0, 0, alpha]);
+        }
+    }
+    Ok(heatmap)
+}
+
+/// Render two svg strings at the same scale and compare their pixels, the
+/// scaler being reduced so that the renders fit in `max_side` pixels per side
+/// # Errors
+/// Fails if one of the svg cannot be rendered or if the renders do not have the same size
+pub fn compare_svgs(
+    a: &str,
+    b: &str,
+    options: &usvg::Options<'_>,
+    scaler: u32,
+    max_side: u32,
+    channel_tolerance: u8,
+) -> Result<VisualDiff, String> {
+    let tree_a = usvg::Tree::from_str(a, options).map_err(|e| e.to_string())?;
+    let tree_b = usvg::Tree::from_str(b, options).map_err(|e| e.to_string())?;
+    let scale = common_scale(&tree_a, &tree_b, scaler, max_side);
+    compare_pixmaps(
+        &render_tree_at(&tree_a, scale)?,
+        &render_tree_at(&tree_b, scale)?,
+        channel_tolerance,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::optimize::simplify;
+    use crate::path::SvgPath;
+    use crate::shape::shape_to_path;
+
+    /// Wrap an element in a 100x100 svg
+    fn svg_with(element: &str) -> String {
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100" viewBox="0 0 100 100">{element}</svg>"#
+        )
+    }
+
+    /// Assert two svg strings look the same
+    fn assert_visually_equal(a: &str, b: &str) {
+        let diff = compare_svgs(
+            a,
+            b,
+            &usvg::Options::default(),
+            1,
+            u32::MAX,
+            VISUAL_CHANNEL_TOLERANCE,
+        )
+        .unwrap();
+        assert!(diff.is_equal(VISUAL_MAX_RATIO), "{diff:?}\n{a}\n{b}");
+    }
+
+    /// Apply `f` to the first child element of the svg
+    fn map_first_element(svg: &str, f: impl FnOnce(&mut Element)) -> String {
+        let mut root = Element::parse(svg.as_bytes()).unwrap();
+        if let Some(XMLNode::Element(e)) = root.children.first_mut() {
+            f(e);
+        }
+        let mut buf = Vec::new();
+        root.write(&mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_compare_detects_difference() {
+        let a = svg_with(r#"<rect x="10" y="10" width="50" height="50" fill="red"/>"#);
+        let b = svg_with(r#"<rect x="10" y="10" width="50" height="50" fill="blue"/>"#);
+        let diff = compare_svgs(
+            &a,
+            &b,
+            &usvg::Options::default(),
+            1,
+            u32::MAX,
+            VISUAL_CHANNEL_TOLERANCE,
+        )
+        .unwrap();
+        assert_eq!(diff.different_pixels, 2500);
+        assert!(!diff.is_equal(VISUAL_MAX_RATIO));
+    }
+
+    #[test]
+    fn test_compare_fits_max_side() {
+        let a = svg_with(r#"<rect x="10" y="10" width="50" height="50" fill="red"/>"#);
+        let b = svg_with(r#"<rect x="10" y="10" width="50" height="50" fill="blue"/>"#);
+        // 100x100 svg at scaler 4 fitted in 50 pixels
+        let diff = compare_svgs(
+            &a,
+            &b,
+            &usvg::Options::default(),
+            4,
+            50,
+            VISUAL_CHANNEL_TOLERANCE,
+        )
+        .unwrap();
+        assert_eq!((diff.width, diff.height), (50, 50));
+        assert_eq!(diff.different_pixels, 625);
+    }
+
+    #[test]
+    fn test_parse_svg_error() {
+        let options = usvg::Options::default();
+        let svg = "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"10\" height=\"10\">\n  <rect x=\"1\" x=\"2\"/>\n</svg>";
+        let e = parse_svg(svg, &options).unwrap_err();
+        assert_eq!((e.line, e.column), (2, 15));
+        assert_eq!(&svg[e.range], "x");
+        let e = parse_svg("<svg>\n<g>\n", &options).unwrap_err();
+        assert_eq!(e.line, 2);
+        assert_eq!(&"<svg>\n<g>\n"[e.range], ">");
+        let e = parse_svg(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"0\"/>",
+            &options,
+        )
+        .unwrap_err();
+        assert_eq!(e.range, 0..4);
+        assert!(parse_svg(&svg_with(""), &options).is_ok());
+    }
+
+    #[test]
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    fn test_fit_scale() {
+        let small = usvg::Size::from_wh(100.0, 50.0).unwrap();
+        assert!((fit_scale(small, 6, 8192) - 6.0).abs() < f32::EPSILON);
+        let wide = usvg::Size::from_wh(10000.0, 10.0).unwrap();
+        let scale = fit_scale(wide, 10, 8192);
+        assert!((scale - 0.8192).abs() < 1e-4);
+        let big = usvg::Size::from_wh(10000.0, 10000.0).unwrap();
+        let scale = fit_scale(big, 10, 16384);
+        let side = (10000.0 * scale) as u64;
+        assert!(side * side <= MAX_RENDER_PIXELS);
+        assert!(side > 5000);
+    }
+
+    #[test]
+    fn test_render_thin_svg() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100000" height="1"><rect width="100000" height="1"/></svg>"#;
+        let tree = usvg::Tree::from_str(svg, &usvg::Options::default()).unwrap();
+        let scale = fit_scale(tree.size(), 1, 8192);
+        let pixmap = render_tree_at(&tree, scale).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (8192, 1));
+        assert!(render_tree_at(&tree, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_tree_renderer() {
+        let options = usvg::Options::default();
+        let svg = svg_with(
+            r#"<g transform="translate(2 2)"><rect width="4" height="4" fill="red"/><g opacity="0.5"><circle cx="5" cy="5" r="3"/></g></g><path d="M0 0 L10 10" stroke="blue"/>"#,
+        );
+        let tree = usvg::Tree::from_str(&svg, &options).unwrap();
+        let expected = render_tree(&tree, 2).unwrap();
+        let mut renderer = TreeRenderer::new(tree, 2.0).unwrap();
+        assert!(renderer.progress() < 1.0);
+        while renderer.step() {}
+        assert!(renderer.is_done());
+        assert!((renderer.progress() - 1.0).abs() < f32::EPSILON);
+        let (tree, pixmap) = renderer.finish();
+        assert_eq!(expected.data(), pixmap.data());
+
+        let region = render_region(&tree, 2.0, (2.0, 3.0), (16, 12)).unwrap();
+        let crop = expected
+            .clone_rect(resvg::tiny_skia::IntRect::from_xywh(4, 6, 16, 12).unwrap())
+            .unwrap();
+        assert_eq!(crop.data(), region.data());
+    }
+
+    #[test]
+    fn test_diff_heatmap() {
+        let options = usvg::Options::default();
+        let a = render_svg(
+            &svg_with(r#"<rect x="0" y="0" width="10" height="10" fill="red"/>"#),
+            &options,
+            1,
+        )
+        .unwrap();
+        let b = render_svg(
+            &svg_with(r#"<rect x="0" y="0" width="10" height="20" fill="red"/>"#),
+            &options,
+            1,
+        )
+        .unwrap();
+        let heatmap = diff_heatmap(&a, &b, VISUAL_CHANNEL_TOLERANCE).unwrap();
+        let alpha = |x, y| heatmap.pixel(x, y).unwrap().alpha();
+        assert_eq!(alpha(5, 5), 0);
+        assert_eq!(alpha(5, 15), 255);
+        assert_eq!(alpha(50, 50), 0);
+        let small = Pixmap::new(10, 10).unwrap();
+        assert!(diff_heatmap(&a, &small, VISUAL_CHANNEL_TOLERANCE).is_err());
+    }
+
+    #[test]
+    fn test_pad_to_same_size() {
+        let options = usvg::Options::default();
+        let wide = render_svg(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10"><rect width="20" height="10"/></svg>"#,
+            &options,
+            1,
+        )
+        .unwrap();
+        let tall = render_svg(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="20"><rect width="10" height="20"/></svg>"#,
+            &options,
+            1,
+        )
+        .unwrap();
+        let (wide, tall) = pad_to_same_size(wide, tall).unwrap();
+        assert_eq!((wide.width(), wide.height()), (20, 20));
+        assert_eq!((tall.width(), tall.height()), (20, 20));
+        assert_eq!(wide.pixel(15, 5).unwrap().alpha(), 255);
+        assert_eq!(wide.pixel(5, 15).unwrap().alpha(), 0);
+        assert_eq!(tall.pixel(5, 15).unwrap().alpha(), 255);
+        let diff = compare_pixmaps(&wide, &tall, VISUAL_CHANNEL_TOLERANCE).unwrap();
+        assert_eq!(diff.different_pixels, 200);
+    }
+
+    #[test]
+    fn test_element_bounding_boxes() {
+        let root = Element::parse(
+            svg_with(r#"<rect x="10" y="20" width="30" height="40"/><g id="g" transform="translate(5 5)"><circle r="5"/></g><desc>text</desc>"#)
+                .as_bytes(),
+        )
+        .unwrap();
+        let paths = [vec![0], vec![1], vec![1, 0], vec![2], vec![9]];
+        let boxes = element_bounding_boxes(&root, &paths, &usvg::Options::default()).unwrap();
+        let rect = boxes[0].unwrap();
+        assert_eq!(
+            (rect.x(), rect.y(), rect.width(), rect.height()),
+            (10.0, 20.0, 30.0, 40.0)
+        );
+        let group = boxes[1].unwrap();
+        assert_eq!((group.x(), group.y(), group.width()), (0.0, 0.0, 10.0));
+        assert_eq!(boxes[2], boxes[1]);
+        assert!(boxes[3].is_none() && boxes[4].is_none());
+    }
+
+    #[test]
+    fn test_convert_to_path_is_visually_equal() {
+        for shape in [
+            r#"<rect x="10" y="20" width="50" height="30" fill="red"/>"#,
+            r#"<circle cx="50" cy="50" r="40" fill="red"/>"#,
+            r#"<ellipse cx="50" cy="50" rx="40" ry="20" fill="red"/>"#,
+            r#"<line x1="0" y1="0" x2="80" y2="20" stroke="red" stroke-width="4"/>"#,
+            r#"<polygon points="0 100 50 25 50 75" fill="red"/>"#,
+        ] {
+            let svg = svg_with(shape);
+            let converted = map_first_element(&svg, |e| shape_to_path(e).unwrap());
+            assert_visually_equal(&svg, &converted);
+        }
+    }
+
+    #[test]
+    fn test_path_reverse_is_visually_equal() {
+        let d = "M10 10C20 0 40 0 50 10S80 20 90 10Q95 50 90 90T10 90A20 20 0 0 1 10 50Z";
+        let svg = svg_with(&format!(
+            r#"<path d="{d}" fill="red" stroke="blue" stroke-width="3"/>"#
+        ));
+        let reversed = SvgPath::parse(d).unwrap().reversed().to_string();
+        let svg_reversed = svg_with(&format!(
+            r#"<path d="{reversed}" fill="red" stroke="blue" stroke-width="3"/>"#
+        ));
+        assert_visually_equal(&svg, &svg_reversed);
+    }
+
+    #[test]
+    fn test_simplify_is_visually_equal() {
+        let svg = include_str!("../assets/galago.svg");
+        let options = usvg::Options::default();
+        let simplified = simplify(svg, &options, &usvg::WriteOptions::default()).unwrap();
+        assert_visually_equal(svg, &simplified);
+    }
+}
@@ -0,0 +1,266 @@
+//! Render job
+//! Parse and rasterize a svg without blocking the UI: on a thread on native,
+//! a few nodes per frame on wasm
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use bladvak::eframe::egui::{ColorImage, Context};
+use resvg::usvg;
+
+use crate::render::{SvgError, TreeRenderer, auto_scaler, fit_scale, parse_svg};
+
+/// Nodes rendered per frame when there is no thread
+#[cfg(target_arch = "wasm32")]
+const NODES_PER_FRAME: usize = 64;
+
+/// Why a render job produced no image
+pub(crate) enum RenderFailure {
+    /// The svg is invalid
+    Invalid(SvgError),
+    /// The svg is valid but cannot be rasterized
+    Failed(String),
+}
+
+/// A finished render
+pub(crate) struct RenderedSvg {
+    /// Parsed svg, to render parts of it at other scales
+    pub(crate) tree: Arc<usvg::Tree>,
+    /// Image of the whole svg
+    pub(crate) image: ColorImage,
+    /// Requested scaler
+    pub(crate) scaler: u32,
+    /// Scale of the image, below the scaler if the image would be too big
+    pub(crate) scale: f32,
+}
+
+/// Rendered svg or the failure
+pub(crate) type RenderResult = Result<RenderedSvg, RenderFailure>;
+
+/// Parse `svg` and prepare its render, picking the scaler if `auto_scale` is
+/// set, and downscaling it to fit in `max_side` pixels
+fn prepare(
+    svg: &str,
+    options: &usvg::Options<'_>,
+    auto_scale: bool,
+    scaler: u32,
+    max_side: u32,
+) -> Result<(TreeRenderer, u32), RenderFailure> {
+    let rtree = parse_svg(svg, options).map_err(RenderFailure::Invalid)?;
+    let scaler = if auto_scale {
+        auto_scaler(rtree.size())
+    } else {
+        scaler
+    };
+    let scale = fit_scale(rtree.size(), scaler, max_side);
+    let renderer = TreeRenderer::new(rtree, scale).map_err(RenderFailure::Failed)?;
+    Ok((renderer, scaler))
+}
+
+/// Largest texture side of the GPU
+#[allow(clippy::cast_possible_truncation)]
+pub(crate) fn max_texture_side(ctx: &Context) -> u32 {
+    ctx.input(|i| i.max_texture_side).min(u32::MAX as usize) as u32
+}
+
+/// Image of a finished render
+fn finish((renderer, scaler): (TreeRenderer, u32)) -> RenderedSvg {
+    let scale = renderer.scale();
+    let (tree, pixmap) = renderer.finish();
+    let (w, h) = (pixmap.width(), pixmap.height());
+    RenderedSvg {
+        tree: Arc::new(tree),
+        image: ColorImage::from_rgba_unmultiplied([w as _, h as _], pixmap.data()),
+        scaler,
+        scale,
+    }
+}
+
+/// Work done on a thread on native, right away on wasm, cancelled when dropped
+pub(crate) struct BackgroundJob<T> {
+    /// Set when the result is not wanted anymore
+    cancelled: Arc<AtomicBool>,
+    /// Result sent by the worker thread
+    #[cfg(not(target_arch = "wasm32"))]
+    receiver: std::sync::mpsc::Receiver<T>,
+    /// Result of the work
+    #[cfg(target_arch = "wasm32")]
+    result: Option<T>,
+}
+
+impl<T: Send + 'static> BackgroundJob<T> {
+    /// Run `work` on a thread, it can stop early and return `None` when the flag is set
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn start(
+        ctx: &Context,
+        work: impl FnOnce(&AtomicBool) -> Option<T> + Send + 'static,
+    ) -> Self {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let worker_cancelled = Arc::clone(&cancelled);
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            if let Some(result) = work(&worker_cancelled) {
+                // the receiver is dropped if the job was cancelled meanwhile
+                let _ = sender.send(result);
+                ctx.request_repaint();
+            }
+        });
+        Self {
+            cancelled,
+            receiver,
+        }
+    }
+
+    /// Run `work` now, there is no thread
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn start(
+        _ctx: &Context,
+        work: impl FnOnce(&AtomicBool) -> Option<T> + Send + 'static,
+    ) -> Self {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let result = work(&cancelled);
+        Self { cancelled, result }
+    }
+
+    /// Result of the work once it is done
+    /// # Errors
+    /// Fails if the worker stopped without sending a result
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn poll(&mut self) -> Result<Option<T>, ()> {
+        match self.receiver.try_recv() {
+            Ok(result) => Ok(Some(result)),
+            Err(std::sync::mpsc::TryRecvError::Empty) => Ok(None),
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => Err(()),
+        }
+    }
+
+    /// Result of the work
+    /// # Errors
+    /// Fails if the work returned no result
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn poll(&mut self) -> Result<Option<T>, ()> {
+        self.result.take().map(Some).ok_or(())
+    }
+}
+
+impl<T> std::fmt::Debug for BackgroundJob<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BackgroundJob")
+            .field("cancelled", &self.cancelled)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Drop for BackgroundJob<T> {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Render of a svg string, cancelled when dropped
+pub(crate) struct RenderJob {
+    /// Rendered svg string
+    pub(crate) svg: String,
+    /// Progress of the render, bits of a float between 0 and 1
+    progress: Arc<AtomicU32>,
+    /// Render running on a thread
+    #[cfg(not(target_arch = "wasm32"))]
+    job: BackgroundJob<RenderResult>,
+    /// Render in progress, or its failure
+    #[cfg(target_arch = "wasm32")]
+    renderer: Option<Result<(TreeRenderer, u32), RenderFailure>>,
+}
+
+impl RenderJob {
+    /// Start the render of `svg` on a thread
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn start(
+        ctx: &Context,
+        svg: &str,
+        options: &usvg::Options<'_>,
+        auto_scale: bool,
+        scaler: u32,
+    ) -> Self {
+        let progress = Arc::new(AtomicU32::new(0));
+        let worker_progress = Arc::clone(&progress);
+        let worker_svg = svg.to_string();
+        let options = crate::render::copy_options(options);
+        let max_side = max_texture_side(ctx);
+        let job = BackgroundJob::start(ctx, move |cancelled| {
+            match prepare(&worker_svg, &options, auto_scale, scaler, max_side) {
+                Ok((mut renderer, scaler)) => {
+                    while renderer.step() {
+                        if cancelled.load(Ordering::Relaxed) {
+                            return None;
+                        }
+                        worker_progress.store(renderer.progress().to_bits(), Ordering::Relaxed);
+                    }
+                    Some(Ok(finish((renderer, scaler))))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        });
+        Self {
+            svg: svg.to_string(),
+            progress,
+            job,
+        }
+    }
+
+    /// Parse `svg` now, the nodes are rendered by the next calls to `poll`
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn start(
+        ctx: &Context,
+        svg: &str,
+        options: &usvg::Options<'_>,
+        auto_scale: bool,
+        scaler: u32,
+    ) -> Self {
+        Self {
+            svg: svg.to_string(),
+            progress: Arc::new(AtomicU32::new(0)),
+            renderer: Some(prepare(
+                svg,
+                options,
+                auto_scale,
+                scaler,
+                max_texture_side(ctx),
+            )),
+        }
+    }
+
+    /// Result of the job once it is done
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn poll(&mut self, _ctx: &Context) -> Option<RenderResult> {
+        self.job.poll().unwrap_or_else(|()| {
+            Some(Err(RenderFailure::Failed(
+                "The render job stopped".to_string(),
+            )))
+        })
+    }
+
+    /// Render a few nodes, returns the result once the job is done
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn poll(&mut self, ctx: &Context) -> Option<RenderResult> {
+        if let Some(Ok((renderer, _))) = &mut self.renderer {
+            for _ in 0..NODES_PER_FRAME {
+                if !renderer.step() {
+                    break;
+                }
+            }
+            self.progress
+                .store(renderer.progress().to_bits(), Ordering::Relaxed);
+            if !renderer.is_done() {
+                ctx.request_repaint();
+                return None;
+            }
+        }
+        self.renderer.take().map(|renderer| renderer.map(finish))
+    }
+
+    /// Fraction of the render done, between 0 and 1
+    pub(crate) fn progress(&self) -> f32 {
+        f32::from_bits(self.progress.load(Ordering::Relaxed))
+    }
+}
@@ -0,0 +1,873 @@
+//! Schema
+//! SVG 1.1 and SVG 2 elements, their attributes and their allowed children
+
+use xmltree::Element;
+
+use crate::optimize::EDITOR_ATTRIBUTES;
+use crate::style::PRESENTATION_ATTRIBUTES;
+
+/// Namespace of the svg elements
+pub const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+
+/// An svg element
+#[derive(Debug)]
+pub struct ElementSchema {
+    /// Name of the element
+    pub name: &'static str,
+    /// Attributes specific to the element
+    attributes: &'static [&'static str],
+    /// Attribute groups shared with other elements
+    groups: &'static [&'static [&'static str]],
+    /// Groups of the elements allowed as children
+    children: &'static [&'static [&'static str]],
+}
+
+impl ElementSchema {
+    /// All the attributes of the element
+    pub fn attributes(&self) -> impl Iterator<Item = &'static str> {
+        self.groups
+            .iter()
+            .flat_map(|group| group.iter())
+            .chain(self.attributes)
+            .copied()
+    }
+
+    /// Names of the elements allowed as children
+    pub fn children(&self) -> impl Iterator<Item = &'static str> {
+        self.children.iter().flat_map(|group| group.iter()).copied()
+    }
+
+    /// Check if the attribute is valid on the element
+    ///
+    /// The xml parser drops the attribute prefixes, so attributes are compared
+    /// by their local name. Namespace declarations, aria and data attributes and
+    /// editor attributes are always accepted.
+    #[must_use]
+    pub fn has_attribute(&self, name: &str) -> bool {
+        if name == "xmlns"
+            || name.starts_with("xmlns:")
+            || name == "role"
+            || name.starts_with("aria-")
+            || name.starts_with("data-")
+        {
+            return true;
+        }
+        let name = local_name(name);
+        EDITOR_ATTRIBUTES.contains(&name)
+            || self
+                .attributes()
+                .any(|attribute| local_name(attribute) == name)
+    }
+
+    /// Check if the element is allowed as a child
+    #[must_use]
+    pub fn has_child(&self, name: &str) -> bool {
+        self.children().any(|child| child == name)
+    }
+}
+
+/// Name without its namespace prefix
+fn local_name(name: &str) -> &str {
+    name.rsplit_once(':').map_or(name, |(_, local)| local)
+}
+
+/// Attributes of all the elements
+const CORE: &[&str] = &[
+    "id",
+    "class",
+    "style",
+    "lang",
+    "tabindex",
+    "xml:lang",
+    "xml:space",
+    "xml:base",
+];
+
+/// Conditional processing attributes
+const CONDITIONAL: &[&str] = &["requiredExtensions", "requiredFeatures", "systemLanguage"];
+
+/// Event attributes of the rendered elements
+const EVENTS: &[&str] = &[
+    "onactivate",
+    "onclick",
+    "onfocusin",
+    "onfocusout",
+    "onload",
+    "onmousedown",
+    "onmousemove",
+    "onmouseout",
+    "onmouseover",
+    "onmouseup",
+];
+
+/// Presentation attributes added by SVG 2 and the css specifications
+const SVG2_PRESENTATION: &[&str] = &[
+    "isolation",
+    "mix-blend-mode",
+    "paint-order",
+    "transform-origin",
+    "vector-effect",
+];
+
+/// Xlink attributes of the elements with a `href`
+const XLINK: &[&str] = &[
+    "xlink:href",
+    "xlink:title",
+    "xlink:show",
+    "xlink:actuate",
+    "xlink:type",
+    "xlink:role",
+    "xlink:arcrole",
+];
+
+/// Attributes of the filter primitives
+const FILTER_PRIMITIVE: &[&str] = &["x", "y", "width", "height", "result"];
+
+/// Attributes of the animation elements
+const ANIMATION: &[&str] = &[
+    "href",
+    "attributeName",
+    "attributeType",
+    "begin",
+    "dur",
+    "end",
+    "min",
+    "max",
+    "restart",
+    "repeatCount",
+    "repeatDur",
+    "fill",
+    "calcMode",
+    "values",
+    "keyTimes",
+    "keySplines",
+    "from",
+    "to",
+    "by",
+    "additive",
+    "accumulate",
+    "onbegin",
+    "onend",
+    "onrepeat",
+];
+
+/// Attribute groups of the rendered elements
+const RENDERED: &[&[&str]] = &[
+    CORE,
+    CONDITIONAL,
+    EVENTS,
+    PRESENTATION_ATTRIBUTES,
+    SVG2_PRESENTATION,
+];
+
+/// Attribute groups of the rendered elements with a `href`
+const RENDERED_LINK: &[&[&str]] = &[
+    CORE,
+    CONDITIONAL,
+    EVENTS,
+    PRESENTATION_ATTRIBUTES,
+    SVG2_PRESENTATION,
+    XLINK,
+];
+
+/// Attribute groups of the elements that are referenced, not rendered
+const REFERENCED: &[&[&str]] = &[CORE, PRESENTATION_ATTRIBUTES, SVG2_PRESENTATION];
+
+/// Attribute groups of the referenced elements with a `href`
+const REFERENCED_LINK: &[&[&str]] = &[CORE, PRESENTATION_ATTRIBUTES, SVG2_PRESENTATION, XLINK];
+
+/// Attribute groups of the filter primitives
+const PRIMITIVE: &[&[&str]] = &[
+    CORE,
+    PRESENTATION_ATTRIBUTES,
+    SVG2_PRESENTATION,
+    FILTER_PRIMITIVE,
+];
+
+/// Attribute groups of the animation elements
+const ANIMATE: &[&[&str]] = &[CORE, CONDITIONAL, XLINK, ANIMATION];
+
+/// Descriptive elements
+const DESCRIPTIVE: &[&str] = &["desc", "metadata", "title"];
+
+/// Animation elements
+const ANIMATIONS: &[&str] = &["animate", "animateMotion", "animateTransform", "set"];
+
+/// Basic shapes
+const SHAPES: &[&str] = &[
+    "circle", "ellipse", "line", "path", "polygon", "polyline", "rect",
+];
+
+/// Structural elements
+const STRUCTURAL: &[&str] = &["defs", "g", "svg", "symbol", "use"];
+
+/// Gradient elements
+const GRADIENTS: &[&str] = &["linearGradient", "radialGradient"];
+
+/// Other elements allowed in the containers
+const CONTAINED: &[&str] = &[
+    "a",
+    "clipPath",
+    "filter",
+    "foreignObject",
+    "image",
+    "marker",
+    "mask",
+    "pattern",
+    "script",
+    "style",
+    "switch",
+    "text",
+    "view",
+];
+
+/// Filter primitives
+const FILTER_PRIMITIVES: &[&str] = &[
+    "feBlend",
+    "feColorMatrix",
+    "feComponentTransfer",
+    "feComposite",
+    "feConvolveMatrix",
+    "feDiffuseLighting",
+    "feDisplacementMap",
+    "feDropShadow",
+    "feFlood",
+    "feGaussianBlur",
+    "feImage",
+    "feMerge",
+    "feMorphology",
+    "feOffset",
+    "feSpecularLighting",
+    "feTile",
+    "feTurbulence",
+];
+
+/// Light sources
+const LIGHTS: &[&str] = &["feDistantLight", "fePointLight", "feSpotLight"];
+
+/// Children of the container elements
+const CONTAINER: &[&[&str]] = &[
+    DESCRIPTIVE,
+    ANIMATIONS,
+    SHAPES,
+    STRUCTURAL,
+    GRADIENTS,
+    CONTAINED,
+];
+
+/// Children of the shapes and images
+const GRAPHIC: &[&[&str]] = &[DESCRIPTIVE, ANIMATIONS];
+
+/// Children of the text content elements
+const TEXT_CONTENT: &[&[&str]] = &[DESCRIPTIVE, ANIMATIONS, &["a", "textPath", "tspan"]];
+
+/// Children of the gradients
+const GRADIENT: &[&[&str]] = &[DESCRIPTIVE, &["animate", "animateTransform", "set", "stop"]];
+
+/// Children of the filter primitives
+const PRIMITIVE_CHILDREN: &[&[&str]] = &[&["animate", "set"]];
+
+/// Children of the lighting filter primitives
+const LIGHTING: &[&[&str]] = &[DESCRIPTIVE, LIGHTS, &["animate", "set"]];
+
+/// Elements without children
+const EMPTY: &[&[&str]] = &[];
+
+/// Attributes of the transfer functions
+const FUNCTION: &[&str] = &[
+    "type",
+    "tableValues",
+    "slope",
+    "intercept",
+    "amplitude",
+    "exponent",
+    "offset",
+];
+
+/// Shorthand to declare an element
+const fn element(
+    name: &'static str,
+    attributes: &'static [&'static str],
+    groups: &'static [&'static [&'static str]],
+    children: &'static [&'static [&'static str]],
+) -> ElementSchema {
+    ElementSchema {
+        name,
+        attributes,
+        groups,
+        children,
+    }
+}
+
+/// The svg elements, sorted by name
+pub const ELEMENTS: &[ElementSchema] = &[
+    element(
+        "a",
+        &[
+            "href",
+            "target",
+            "download",
+            "hreflang",
+            "ping",
+            "referrerpolicy",
+            "rel",
+            "type",
+            "transform",
+        ],
+        RENDERED_LINK,
+        CONTAINER,
+    ),
+    element("animate", &[], ANIMATE, &[DESCRIPTIVE]),
+    element(
+        "animateMotion",
+        &["path", "keyPoints", "rotate", "origin"],
+        ANIMATE,
+        &[DESCRIPTIVE, &["mpath"]],
+    ),
+    element("animateTransform", &["type"], ANIMATE, &[DESCRIPTIVE]),
+    element(
+        "circle",
+        &["cx", "cy", "r", "pathLength", "transform"],
+        RENDERED,
+        GRAPHIC,
+    ),
+    element(
+        "clipPath",
+        &["clipPathUnits", "transform"],
+        REFERENCED,
+        &[DESCRIPTIVE, ANIMATIONS, SHAPES, &["text", "use"]],
+    ),
+    element("defs", &["transform"], RENDERED, CONTAINER),
+    element("desc", &[], &[CORE], EMPTY),
+    element(
+        "ellipse",
+        &["cx", "cy", "rx", "ry", "pathLength", "transform"],
+        RENDERED,
+        GRAPHIC,
+    ),
+    element(
+        "feBlend",
+        &["in", "in2", "mode"],
+        PRIMITIVE,
+        PRIMITIVE_CHILDREN,
+    ),
+    element(
+        "feColorMatrix",
+        &["in", "type", "values"],
+        PRIMITIVE,
+        PRIMITIVE_CHILDREN,
+    ),
+    element(
+        "feComponentTransfer",
+        &["in"],
+        PRIMITIVE,
+        &[&["feFuncA", "feFuncB", "feFuncG", "feFuncR"]],
+    ),
+    element(
+        "feComposite",
+        &["in", "in2", "operator", "k1", "k2", "k3", "k4"],
+        PRIMITIVE,
+        PRIMITIVE_CHILDREN,
+    ),
+    element(
+        "feConvolveMatrix",
+        &[
+            "in",
+            "order",
+            "kernelMatrix",
+            "divisor",
+            "bias",
+            "targetX",
+            "targetY",
+            "edgeMode",
+            "kernelUnitLength",
+            "preserveAlpha",
+        ],
+        PRIMITIVE,
+        PRIMITIVE_CHILDREN,
+    ),
+    element(
+        "feDiffuseLighting",
+        &["in", "surfaceScale", "diffuseConstant", "kernelUnitLength"],
+        PRIMITIVE,
+        LIGHTING,
+    ),
+    element(
+        "feDisplacementMap",
+        &["in", "in2", "scale", "xChannelSelector", "yChannelSelector"],
+        PRIMITIVE,
+        PRIMITIVE_CHILDREN,
+    ),
+    element(
+        "feDistantLight",
+        &["azimuth", "elevation"],
+        &[CORE],
+        PRIMITIVE_CHILDREN,
+    ),
+    element(
+        "feDropShadow",
+        &["in", "dx", "dy", "stdDeviation"],
+        PRIMITIVE,
+        PRIMITIVE_CHILDREN,
+    ),
+    element("feFlood", &[], PRIMITIVE, PRIMITIVE_CHILDREN),
+    element("feFuncA", FUNCTION, &[CORE], PRIMITIVE_CHILDREN),
+    element("feFuncB", FUNCTION, &[CORE], PRIMITIVE_CHILDREN),
+    element("feFuncG", FUNCTION, &[CORE], PRIMITIVE_CHILDREN),
+    element("feFuncR", FUNCTION, &[CORE], PRIMITIVE_CHILDREN),
+    element(
+        "feGaussianBlur",
+        &["in", "stdDeviation", "edgeMode"],
+        PRIMITIVE,
+        PRIMITIVE_CHILDREN,
+    ),
+    element(
+        "feImage",
+        &["href", "preserveAspectRatio", "crossorigin"],
+        &[
+            CORE,
+            PRESENTATION_ATTRIBUTES,
+            SVG2_PRESENTATION,
+            FILTER_PRIMITIVE,
+            XLINK,
+        ],
+        &[&["animate", "animateTransform", "set"]],
+    ),
+    element("feMerge", &[], PRIMITIVE, &[&["feMergeNode"]]),
+    element("feMergeNode", &["in"], &[CORE], PRIMITIVE_CHILDREN),
+    element(
+        "feMorphology",
+        &["in", "operator", "radius"],
+        PRIMITIVE,
+        PRIMITIVE_CHILDREN,
+    ),
+    element(
+        "feOffset",
+        &["in", "dx", "dy"],
+        PRIMITIVE,
+        PRIMITIVE_CHILDREN,
+    ),
+    element(
+        "fePointLight",
+        &["x", "y", "z"],
+        &[CORE],
+        PRIMITIVE_CHILDREN,
+    ),
+    element(
+        "feSpecularLighting",
+        &[
+            "in",
+            "surfaceScale",
+            "specularConstant",
+            "specularExponent",
+            "kernelUnitLength",
+        ],
+        PRIMITIVE,
+        LIGHTING,
+    ),
+    element(
+        "feSpotLight",
+        &[
+            "x",
+            "y",
+            "z",
+            "pointsAtX",
+            "pointsAtY",
+            "pointsAtZ",
+            "specularExponent",
+            "limitingConeAngle",
+        ],
+        &[CORE],
+        PRIMITIVE_CHILDREN,
+    ),
+    element("feTile", &["in"], PRIMITIVE, PRIMITIVE_CHILDREN),
+    element(
+        "feTurbulence",
+        &["baseFrequency", "numOctaves", "seed", "stitchTiles", "type"],
+        PRIMITIVE,
+        PRIMITIVE_CHILDREN,
+    ),
+    element(
+        "filter",
+        &[
+            "x",
+            "y",
+            "width",
+            "height",
+            "filterUnits",
+            "primitiveUnits",
+            "filterRes",
+            "href",
+        ],
+        REFERENCED_LINK,
+        &[DESCRIPTIVE, FILTER_PRIMITIVES, &["animate", "set"]],
+    ),
+    element(
+        "foreignObject",
+        &["x", "y", "width", "height", "transform"],
+        RENDERED,
+        EMPTY,
+    ),
+    element("g", &["transform"], RENDERED, CONTAINER),
+    element(
+        "image",
+        &[
+            "href",
+            "x",
+            "y",
+            "width",
+            "height",
+            "preserveAspectRatio",
+            "crossorigin",
+            "transform",
+        ],
+        RENDERED_LINK,
+        GRAPHIC,
+    ),
+    element(
+        "line",
+        &["x1", "y1", "x2", "y2", "pathLength", "transform"],
+        RENDERED,
+        GRAPHIC,
+    ),
+    element(
+        "linearGradient",
+        &[
+            "x1",
+            "y1",
+            "x2",
+            "y2",
+            "gradientUnits",
+            "gradientTransform",
+            "spreadMethod",
+            "href",
+        ],
+        REFERENCED_LINK,
+        GRADIENT,
+    ),
+    element(
+        "marker",
+        &[
+            "viewBox",
+            "preserveAspectRatio",
+            "refX",
+            "refY",
+            "markerUnits",
+            "markerWidth",
+            "markerHeight",
+            "orient",
+        ],
+        REFERENCED,
+        CONTAINER,
+    ),
+    element(
+        "mask",
+        &["x", "y", "width", "height", "maskUnits", "maskContentUnits"],
+        REFERENCED,
+        CONTAINER,
+    ),
+    element("metadata", &[], &[CORE], EMPTY),
+    element("mpath", &["href"], &[CORE, XLINK], &[DESCRIPTIVE]),
+    element("path", &["d", "pathLength", "transform"], RENDERED, GRAPHIC),
+    element(
+        "pattern",
+        &[
+            "x",
+            "y",
+            "width",
+            "height",
+            "patternUnits",
+            "patternContentUnits",
+            "patternTransform",
+            "viewBox",
+            "preserveAspectRatio",
+            "href",
+        ],
+        REFERENCED_LINK,
+        CONTAINER,
+    ),
+    element(
+        "polygon",
+        &["points", "pathLength", "transform"],
+        RENDERED,
+        GRAPHIC,
+    ),
+    element(
+        "polyline",
+        &["points", "pathLength", "transform"],
+        RENDERED,
+        GRAPHIC,
+    ),
+    element(
+        "radialGradient",
+        &[
+            "cx",
+            "cy",
+            "r",
+            "fx",
+            "fy",
+            "fr",
+            "gradientUnits",
+            "gradientTransform",
+            "spreadMethod",
+            "href",
+        ],
+        REFERENCED_LINK,
+        GRADIENT,
+    ),
+    element(
+        "rect",
+        &[
+            "x",
+            "y",
+            "width",
+            "height",
+            "rx",
+            "ry",
+            "pathLength",
+            "transform",
+        ],
+        RENDERED,
+        GRAPHIC,
+    ),
+    element(
+        "script",
+        &["type", "href", "crossorigin"],
+        &[CORE, XLINK],
+        EMPTY,
+    ),
+    element("set", &[], ANIMATE, &[DESCRIPTIVE]),
+    element("stop", &["offset"], REFERENCED, &[&["animate", "set"]]),
+    element("style", &["type", "media", "title"], &[CORE], EMPTY),
+    element(
+        "svg",
+        &[
+            "x",
+            "y",
+            "width",
+            "height",
+            "viewBox",
+            "preserveAspectRatio",
+            "zoomAndPan",
+            "version",
+            "baseProfile",
+            "contentScriptType",
+            "contentStyleType",
+            "transform",
+            "onabort",
+            "onerror",
+            "onresize",
+            "onscroll",
+            "onunload",
+            "onzoom",
+        ],
+        RENDERED,
+        CONTAINER,
+    ),
+    element(
+        "switch",
+        &["transform"],
+        RENDERED,
+        &[
+            DESCRIPTIVE,
+            ANIMATIONS,
+            SHAPES,
+            &[
+                "a",
+                "foreignObject",
+                "g",
+                "image",
+                "svg",
+                "switch",
+                "text",
+                "use",
+            ],
+        ],
+    ),
+    element(
+        "symbol",
+        &[
+            "viewBox",
+            "preserveAspectRatio",
+            "x",
+            "y",
+            "width",
+            "height",
+            "refX",
+            "refY",
+        ],
+        REFERENCED,
+        CONTAINER,
+    ),
+    element(
+        "text",
+        &[
+            "x",
+            "y",
+            "dx",
+            "dy",
+            "rotate",
+            "textLength",
+            "lengthAdjust",
+            "transform",
+        ],
+        RENDERED,
+        TEXT_CONTENT,
+    ),
+    element(
+        "textPath",
+        &[
+            "href",
+            "path",
+            "method",
+            "spacing",
+            "startOffset",
+            "side",
+            "textLength",
+            "lengthAdjust",
+        ],
+        RENDERED_LINK,
+        &[DESCRIPTIVE, ANIMATIONS, &["a", "tspan"]],
+    ),
+    element("title", &[], &[CORE], EMPTY),
+    element(
+        "tspan",
+        &["x", "y", "dx", "dy", "rotate", "textLength", "lengthAdjust"],
+        RENDERED,
+        &[DESCRIPTIVE, ANIMATIONS, &["a", "tspan"]],
+    ),
+    element(
+        "use",
+        &["href", "x", "y", "width", "height", "transform"],
+        RENDERED_LINK,
+        GRAPHIC,
+    ),
+    element(
+        "view",
+        &["viewBox", "preserveAspectRatio", "zoomAndPan", "viewTarget"],
+        &[CORE],
+        &[DESCRIPTIVE],
+    ),
+];
+
+/// Schema of the element `name`, `None` if it is not an svg element
+#[must_use]
+pub fn element_schema(name: &str) -> Option<&'static ElementSchema> {
+    ELEMENTS
+        .binary_search_by(|schema| schema.name.cmp(name))
+        .ok()
+        .map(|index| &ELEMENTS[index])
+}
+
+/// Check if the element is in the svg namespace, or has no namespace
+#[must_use]
+pub fn is_svg_element(element: &Element) -> bool {
+    element
+        .namespace
+        .as_deref()
+        .is_none_or(|namespace| namespace == SVG_NAMESPACE)
+}
+
+/// Warning if the attribute is not valid on the element
+#[must_use]
+pub fn attribute_warning(element: &Element, attribute: &str) -> Option<String> {
+    if !is_svg_element(element) {
+        return None;
+    }
+    let schema = element_schema(&element.name)?;
+    (!schema.has_attribute(attribute))
+        .then(|| format!("`{attribute}` is not an attribute of <{}>", element.name))
+}
+
+/// Warning if the element is unknown or not allowed in its parent
+#[must_use]
+pub fn element_warning(parent: Option<&Element>, name: &str) -> Option<String> {
+    if element_schema(name).is_none() {
+        return Some(format!("<{name}> is not an svg element"));
+    }
+    let parent = parent.filter(|parent| is_svg_element(parent))?;
+    let schema = element_schema(&parent.name)?;
+    (!schema.has_child(name)).then(|| format!("<{name}> is not allowed in <{}>", parent.name))
+}
+
+/// Candidates completing `text`: the ones starting with it, then the ones
+/// containing it, ignoring the case
+#[must_use]
+pub fn complete<'a>(text: &str, candidates: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
+    let text = text.to_lowercase();
+    let mut starting = Vec::new();
+    let mut containing = Vec::new();
+    for candidate in candidates {
+        let lower = candidate.to_lowercase();
+        if lower == text || starting.contains(&candidate) || containing.contains(&candidate) {
+            continue;
+        }
+        if lower.starts_with(&text) {
+            starting.push(candidate);
+        } else if lower.contains(&text) {
+            containing.push(candidate);
+        }
+    }
+    starting.sort_unstable();
+    containing.sort_unstable();
+    starting.append(&mut containing);
+    starting
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn test_elements_sorted() {
+        assert!(ELEMENTS.windows(2).all(|w| w[0].name < w[1].name));
+        for schema in ELEMENTS {
+            for child in schema.children() {
+                assert!(element_schema(child).is_some(), "{child}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_schema() {
+        let rect = element_schema("rect").unwrap();
+        assert!(rect.has_attribute("rx"));
+        assert!(rect.has_attribute("fill"));
+        assert!(rect.has_attribute("xml:space"));
+        assert!(rect.has_attribute("xmlns:xlink"));
+        assert!(rect.has_attribute("aria-label"));
+        assert!(rect.has_attribute("data-name"));
+        assert!(!rect.has_attribute("cx"));
+        assert!(!rect.has_attribute("href"));
+        assert!(element_schema("use").unwrap().has_attribute("xlink:href"));
+        assert!(element_schema("g").unwrap().has_child("rect"));
+        assert!(!rect.has_child("rect"));
+        assert!(element_schema("blink").is_none());
+
+        let root = Element::parse(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:i="http://www.inkscape.org/namespaces/inkscape"><rect cx="1" i:label="a"/><i:tool cx="1"/></svg>"#
+                .as_bytes(),
+        )
+        .unwrap();
+        let rect = root.get_child("rect").unwrap();
+        assert!(attribute_warning(rect, "cx").is_some());
+        assert!(attribute_warning(rect, "label").is_none());
+        let tool = root.get_child("tool").unwrap();
+        assert!(attribute_warning(tool, "cx").is_none());
+        assert!(element_warning(Some(&root), "circle").is_none());
+        assert!(element_warning(Some(rect), "circle").is_some());
+        assert!(element_warning(None, "blink").is_some());
+    }
+
+    #[test]
+    fn test_complete() {
+        let candidates = ["stroke-width", "width", "stroke", "x"];
+        assert_eq!(
+            complete("wid", candidates.into_iter()),
+            ["width", "stroke-width"]
+        );
+        assert_eq!(complete("STROKE", candidates.into_iter()), ["stroke-width"]);
+        assert!(complete("y", candidates.into_iter()).is_empty());
+    }
+}
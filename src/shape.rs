@@ -0,0 +1,138 @@
+//! Shape conversion
+//! Convert basic shapes (`rect`, `circle`, ...) to `path` elements and back
+
+use xmltree::Element;
+
+use crate::path::{
+    Primitive, SvgPath, circle_to_path, ellipse_to_path, line_to_path, polygon_to_path,
+    polyline_to_path, rect_to_path,
+};
+
+/// Geometry attributes of each basic shape
+const SHAPE_ATTRIBUTES: &[(&str, &[&str])] = &[
+    ("rect", &["x", "y", "width", "height"]),
+    ("circle", &["cx", "cy", "r"]),
+    ("ellipse", &["cx", "cy", "rx", "ry"]),
+    ("line", &["x1", "y1", "x2", "y2"]),
+    ("polyline", &["points"]),
+    ("polygon", &["points"]),
+];
+
+/// Geometry attributes of a basic shape, `None` if the name is not a basic shape
+#[must_use]
+pub fn shape_attributes(name: &str) -> Option<&'static [&'static str]> {
+    SHAPE_ATTRIBUTES
+        .iter()
+        .find(|(shape, _)| *shape == name)
+        .map(|(_, attributes)| *attributes)
+}
+
+/// Check if the element is a basic shape with all its geometry attributes
+#[must_use]
+pub fn is_convertible_to_path(element: &Element) -> bool {
+    shape_attributes(&element.name).is_some_and(|attributes| {
+        attributes
+            .iter()
+            .all(|attr| element.attributes.contains_key(*attr))
+    })
+}
+
+/// Path data of a basic shape
+/// # Errors
+/// Fails if the element is not a basic shape or if an attribute is missing or invalid
+pub fn shape_path_data(element: &Element) -> Result<String, String> {
+    let get = |key: &str| {
+        element
+            .attributes
+            .get(key)
+            .map(String::as_str)
+            .ok_or_else(|| format!("Missing attribute '{key}' on <{}>", element.name))
+    };
+    match element.name.as_str() {
+        "rect" => Ok(rect_to_path(
+            get("x")?,
+            get("y")?,
+            get("width")?,
+            get("height")?,
+        )),
+        "circle" => circle_to_path(get("cx")?, get("cy")?, get("r")?),
+        "ellipse" => ellipse_to_path(get("cx")?, get("cy")?, get("rx")?, get("ry")?),
+        "line" => Ok(line_to_path(get("x1")?, get("y1")?, get("x2")?, get("y2")?)),
+        "polyline" => polyline_to_path(get("points")?).map_err(|e| e.to_string()),
+        "polygon" => polygon_to_path(get("points")?).map_err(|e| e.to_string()),
+        name => Err(format!("Cannot convert <{name}> to path")),
+    }
+}
+
+/// Convert a basic shape element to a `path` element
+/// # Errors
+/// Fails if the element is not a basic shape or if an attribute is missing or invalid
+pub fn shape_to_path(element: &mut Element) -> Result<(), String> {
+    let path_data = shape_path_data(element)?;
+    if let Some(attributes) = shape_attributes(&element.name) {
+        for attr in attributes {
+            element.attributes.shift_remove(*attr);
+        }
+    }
+    element.name = "path".to_string();
+    element.attributes.insert("d".to_string(), path_data);
+    Ok(())
+}
+
+/// Detect the basic shape described by a `path` element
+#[must_use]
+pub fn detect_primitive(element: &Element) -> Option<Primitive> {
+    if element.name != "path" {
+        return None;
+    }
+    element
+        .attributes
+        .get("d")
+        .and_then(|d| SvgPath::parse(d).ok())
+        .and_then(|path| path.to_primitive())
+}
+
+/// Convert a `path` element to the equivalent basic shape element
+///
+/// Returns the primitive if the element was converted.
+pub fn path_to_primitive(element: &mut Element) -> Option<Primitive> {
+    let primitive = detect_primitive(element)?;
+    element.name = primitive.element_name().to_string();
+    element.attributes.shift_remove("d");
+    for (key, value) in primitive.attributes() {
+        element.attributes.insert(key.to_string(), value);
+    }
+    Some(primitive)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn test_shape_round_trip() {
+        let mut element =
+            Element::parse(r#"<rect x="1" y="2" width="3" height="4" fill="red"/>"#.as_bytes())
+                .unwrap();
+        assert!(is_convertible_to_path(&element));
+        shape_to_path(&mut element).unwrap();
+        assert_eq!(element.name, "path");
+        assert_eq!(element.attributes.get("d").unwrap(), "M 1 2 h 3 v 4 h -3 Z");
+        assert_eq!(element.attributes.get("fill").unwrap(), "red");
+
+        assert!(path_to_primitive(&mut element).is_some());
+        assert_eq!(element.name, "rect");
+        assert!(!element.attributes.contains_key("d"));
+        assert_eq!(element.attributes.get("width").unwrap(), "3");
+        assert_eq!(element.attributes.get("fill").unwrap(), "red");
+    }
+
+    #[test]
+    fn test_shape_missing_attribute() {
+        let mut element = Element::parse(r#"<circle cx="1" cy="2"/>"#.as_bytes()).unwrap();
+        assert!(!is_convertible_to_path(&element));
+        assert!(shape_to_path(&mut element).is_err());
+        assert_eq!(element.name, "circle");
+    }
+}
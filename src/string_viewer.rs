@@ -1,13 +1,23 @@
 //! String Viewer
 
 use bladvak::app::BladvakPanel;
-use bladvak::eframe::egui::{self, Color32, Frame};
+use bladvak::eframe::egui::text::{ByteIndex, CCursor, CCursorRange, LayoutJob, LayoutSection};
+use bladvak::eframe::egui::text_edit::{TextEditOutput, TextEditState};
+use bladvak::eframe::egui::{self, Color32, Frame, Stroke};
 use bladvak::egui_extras::syntax_highlighting::CodeTheme;
 use bladvak::{AppError, ErrorManager, egui_extras};
-use resvg::usvg::WriteOptions;
+use resvg::usvg::Options;
+use std::ops::Range;
 use std::sync::Arc;
 
 use crate::GalagoApp;
+use crate::diff_viewer::DiffCache;
+use crate::document::Document;
+use crate::optimize::{IndentStyle, OptimizeOptions, Pass, SimplifyOptions, optimize, simplify};
+use crate::render::{
+    SvgError, VISUAL_CHANNEL_TOLERANCE, VISUAL_MAX_RATIO, compare_svgs, copy_options,
+};
+use crate::render_job::{BackgroundJob, max_texture_side};
 
 /// String Viewer
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -17,6 +27,22 @@ pub struct StringViewer {
 
     /// Font size for the theme
     pub theme_font_size: f32,
+
+    /// Options of the optimizer
+    #[serde(default)]
+    pub optimize_options: OptimizeOptions,
+
+    /// Options of the usvg simplification
+    #[serde(default)]
+    pub simplify_options: SimplifyOptions,
+
+    /// Diff between the svg and the simplify preview
+    #[serde(skip)]
+    preview_diff: DiffCache,
+
+    /// Byte range of the svg to select and scroll to
+    #[serde(skip)]
+    pub(crate) jump_to: Option<Range<usize>>,
 }
 
 /// Default font size
@@ -27,6 +53,10 @@ impl Default for StringViewer {
         Self {
             theme: CodeTheme::dark(DEFAULT_FONT_SIZE),
             theme_font_size: DEFAULT_FONT_SIZE,
+            optimize_options: OptimizeOptions::default(),
+            simplify_options: SimplifyOptions::default(),
+            preview_diff: DiffCache::default(),
+            jump_to: None,
         }
     }
 }
@@ -68,6 +98,29 @@ impl BladvakPanel for StringViewerPanel {
         }
         app.string_viewer.theme.ui(ui);
         app.string_viewer.theme.clone().store_in_memory(ui.ctx());
+        ui.separator();
+        ui.label("Simplify options");
+        let options = &mut app.string_viewer.simplify_options;
+        indent_combo(ui, "Indent", &mut options.indent);
+        indent_combo(ui, "Attributes indent", &mut options.attributes_indent);
+        ui.checkbox(&mut options.use_single_quote, "Use single quotes");
+        ui.horizontal(|ui| {
+            ui.label("Id prefix");
+            ui.text_edit_singleline(&mut options.id_prefix);
+        });
+        ui.add(
+            egui::Slider::new(&mut options.coordinates_precision, 0..=12)
+                .text("Coordinates precision"),
+        );
+        ui.add(
+            egui::Slider::new(&mut options.transforms_precision, 0..=12)
+                .text("Transforms precision"),
+        );
+        ui.checkbox(&mut options.preserve_text, "Preserve text")
+            .on_hover_text("Keep text elements instead of converting them to paths");
+        if ui.button("Reset simplify options").clicked() {
+            *options = SimplifyOptions::default();
+        }
     }
 
     fn has_ui(&self) -> bool {
@@ -79,52 +132,95 @@ impl BladvakPanel for StringViewerPanel {
     }
 }
 
-impl GalagoApp {
-    /// Show the String Viewer
-    pub fn show_svg_string(&mut self, ui: &mut egui::Ui, error_manager: &mut ErrorManager) {
-        Frame::new().show(ui, |ui| {
-            let Some(document) = self.documents.get_current_doc_mut() else {
-                return;
-            };
-            let mut layouter = |ui: &egui::Ui, buf: &dyn egui::TextBuffer, wrap_width: f32| {
-                let mut layout_job = egui_extras::syntax_highlighting::highlight(
-                    ui.ctx(),
-                    ui.style(),
-                    &self.string_viewer.theme,
-                    buf.as_str(),
-                    "svg",
-                );
-                layout_job.wrap.max_width = wrap_width;
-                ui.fonts_mut(|f| f.layout_job(layout_job))
-            };
-            let height = ui.ctx().viewport_rect().height();
-            egui::ScrollArea::vertical()
-                .max_height(height / 2.0 - 40.0)
-                .show(ui, |ui| {
+impl StringViewer {
+    /// Show the svg text with its line numbers, underlining the parse error
+    fn show_editor(&mut self, ui: &mut egui::Ui, document: &mut Document) {
+        let error = document.svg_render.error(&document.svg).cloned();
+        let editor_id = egui::Id::new("svg_string_editor");
+        let jump = self
+            .jump_to
+            .take()
+            .and_then(|range| select_range(ui.ctx(), editor_id, &document.svg, range));
+        let mut layouter = |ui: &egui::Ui, buf: &dyn egui::TextBuffer, wrap_width: f32| {
+            let mut layout_job = egui_extras::syntax_highlighting::highlight(
+                ui.ctx(),
+                ui.style(),
+                &self.theme,
+                buf.as_str(),
+                "svg",
+            );
+            if let Some(error) = &error
+                && buf.as_str().get(error.range.clone()).is_some()
+            {
+                underline_range(&mut layout_job, &error.range, Color32::RED);
+            }
+            layout_job.wrap.max_width = wrap_width;
+            ui.fonts_mut(|f| f.layout_job(layout_job))
+        };
+        let font_id = egui::FontId::monospace(self.theme_font_size);
+        let height = ui.ctx().viewport_rect().height();
+        egui::ScrollArea::vertical()
+            .max_height(height / 2.0 - 40.0)
+            .show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    let digits = document.svg.lines().count().max(1).to_string().len();
+                    #[allow(clippy::cast_precision_loss)]
+                    let gutter_width =
+                        ui.fonts_mut(|f| f.glyph_width(&font_id, '0')) * (digits + 2) as f32;
+                    let gutter_left = ui.cursor().left();
+                    ui.add_space(gutter_width);
                     let multiliner = egui::TextEdit::multiline(&mut document.svg)
-                        .font(egui::FontId::monospace(self.string_viewer.theme_font_size)) // for cursor height
+                        .id(editor_id)
+                        .font(font_id.clone()) // for cursor height
                         .code_editor()
                         .desired_rows(10)
                         .lock_focus(true)
                         .desired_width(f32::INFINITY);
-
-                    if document.svg_is_valid {
-                        ui.add(multiliner.layouter(&mut layouter));
-                    } else {
-                        ui.add(multiliner.text_color(Color32::RED)).on_hover_text(
-                            "The SVG string is not correct, please check the syntax.",
-                        );
+                    let output = multiliner.layouter(&mut layouter).show(ui);
+                    let gutter = egui::Rangef::new(gutter_left, gutter_left + gutter_width);
+                    show_gutter(ui, &output, gutter, &font_id, error.as_ref());
+                    if let Some(cursor) = jump {
+                        let rect = output
+                            .galley
+                            .pos_from_cursor(cursor)
+                            .translate(output.galley_pos.to_vec2());
+                        ui.scroll_to_rect(rect, Some(egui::Align::Center));
                     }
                 });
+            });
+        if let Some(range) = show_error(ui, error.as_ref(), document.svg_is_valid) {
+            self.jump_to = Some(range);
+        }
+    }
+}
+
+impl GalagoApp {
+    /// Show the String Viewer
+    pub fn show_svg_string(&mut self, ui: &mut egui::Ui, error_manager: &mut ErrorManager) {
+        Frame::new().show(ui, |ui| {
+            let Some(document) = self.documents.get_current_doc_mut() else {
+                return;
+            };
+            self.string_viewer.show_editor(ui, document);
             ui.horizontal(|ui| {
                 if ui.button("Copy svg").clicked() {
                     ui.ctx().copy_text(document.svg.clone());
                 }
                 document.should_reset_view = ui.button("Reset view").clicked();
 
-                if ui.button("Simplify").clicked() {
-                    match resvg::usvg::Tree::from_str(&document.svg, &self.usvg_options) {
-                        Ok(tree) => document.svg = tree.to_string(&WriteOptions::default()),
+                if ui
+                    .button("Simplify")
+                    .on_hover_text("Preview the svg rewritten by usvg")
+                    .clicked()
+                {
+                    match simplify(
+                        &document.svg,
+                        &self.usvg_options,
+                        &self.string_viewer.simplify_options.write_options(),
+                    ) {
+                        Ok(simplified) => {
+                            document.simplify_preview = Some((document.svg.clone(), simplified));
+                        }
                         Err(e) => {
                             error_manager.add_error(AppError::new_with_source(
                                 "Cannot simplify the svg",
@@ -134,6 +230,308 @@ impl GalagoApp {
                     }
                 }
             });
+            show_simplify_preview(
+                ui,
+                document,
+                &mut self.string_viewer.preview_diff,
+                self.string_viewer.theme_font_size,
+            );
+            ui.collapsing("Optimizer", |ui| {
+                show_optimizer(
+                    ui,
+                    document,
+                    &mut self.string_viewer.optimize_options,
+                    error_manager,
+                );
+            });
+            show_visual_check(ui, document, &self.usvg_options);
         });
     }
 }
+
+/// Show the parse error under the text edit, returns its range if it is clicked
+fn show_error(
+    ui: &mut egui::Ui,
+    error: Option<&SvgError>,
+    svg_is_valid: bool,
+) -> Option<Range<usize>> {
+    let Some(error) = error else {
+        if !svg_is_valid {
+            ui.colored_label(Color32::RED, "The svg cannot be rendered");
+        }
+        return None;
+    };
+    let text = egui::RichText::new(format!("{}:{} {}", error.line, error.column, error.message))
+        .color(Color32::RED);
+    ui.selectable_label(false, text)
+        .on_hover_text("Show the error in the text")
+        .clicked()
+        .then(|| error.range.clone())
+}
+
+/// Underline the byte `range` of the laid out text
+fn underline_range(job: &mut LayoutJob, range: &Range<usize>, color: Color32) {
+    let mut sections = Vec::with_capacity(job.sections.len() + 2);
+    for section in job.sections.drain(..) {
+        let section_range = section.byte_range.start.0..section.byte_range.end.0;
+        let start = range.start.clamp(section_range.start, section_range.end);
+        let end = range.end.clamp(section_range.start, section_range.end);
+        if start == end {
+            sections.push(section);
+            continue;
+        }
+        // split the section around the underlined part
+        for (part, is_underlined) in [
+            (section_range.start..start, false),
+            (start..end, true),
+            (end..section_range.end, false),
+        ] {
+            if part.is_empty() {
+                continue;
+            }
+            let mut format = section.format.clone();
+            if is_underlined {
+                format.underline = Stroke::new(1.5, color);
+                format.background = color.gamma_multiply(0.2);
+            }
+            sections.push(LayoutSection {
+                leading_space: if part.start == section_range.start {
+                    section.leading_space
+                } else {
+                    0.0
+                },
+                byte_range: ByteIndex(part.start)..ByteIndex(part.end),
+                format,
+            });
+        }
+    }
+    job.sections = sections;
+}
+
+/// Paint the line numbers of the text edit in the `gutter` x range, the line
+/// of the error is marked and shows the error on hover
+fn show_gutter(
+    ui: &egui::Ui,
+    output: &TextEditOutput,
+    gutter: egui::Rangef,
+    font_id: &egui::FontId,
+    error: Option<&SvgError>,
+) {
+    let clip = ui.clip_rect();
+    let mut line = 1;
+    let mut starts_line = true;
+    for row in &output.galley.rows {
+        let rect = row.rect().translate(output.galley_pos.to_vec2());
+        if starts_line && rect.bottom() >= clip.top() && rect.top() <= clip.bottom() {
+            let row_rect = egui::Rect::from_x_y_ranges(gutter, rect.y_range());
+            let is_error = error.is_some_and(|e| e.line == line);
+            let color = if is_error {
+                Color32::RED
+            } else {
+                ui.visuals().weak_text_color()
+            };
+            let text = if is_error {
+                format!("● {line}")
+            } else {
+                line.to_string()
+            };
+            ui.painter().text(
+                row_rect.right_top() - egui::vec2(4.0, 0.0),
+                egui::Align2::RIGHT_TOP,
+                text,
+                font_id.clone(),
+                color,
+            );
+            if let Some(error) = error.filter(|_| is_error) {
+                ui.interact(row_rect, ui.id().with("gutter_error"), egui::Sense::hover())
+                    .on_hover_text(&error.message);
+            }
+        }
+        starts_line = row.ends_with_newline;
+        if starts_line {
+            line += 1;
+        }
+    }
+}
+
+/// Select the byte `range` of `text` in the text edit `id` and focus it,
+/// returns the cursor at the start of the selection
+fn select_range(
+    ctx: &egui::Context,
+    id: egui::Id,
+    text: &str,
+    range: Range<usize>,
+) -> Option<CCursor> {
+    let selected = text.get(range.clone())?;
+    let start = CCursor::new(text[..range.start].chars().count());
+    let end = CCursor::new(start.index + selected.chars().count());
+    let mut state = TextEditState::load(ctx, id).unwrap_or_default();
+    state
+        .cursor
+        .set_char_range(Some(CCursorRange::two(start, end)));
+    state.store(ctx, id);
+    ctx.memory_mut(|memory| memory.request_focus(id));
+    Some(start)
+}
+
+/// Show the pending simplification as a line diff with Apply and Cancel buttons
+fn show_simplify_preview(
+    ui: &mut egui::Ui,
+    document: &mut Document,
+    diff: &mut DiffCache,
+    font_size: f32,
+) {
+    let Some((source, simplified)) = &document.simplify_preview else {
+        return;
+    };
+    if *source != document.svg {
+        // the svg was edited since, the preview does not apply to it anymore
+        document.simplify_preview = None;
+        return;
+    }
+    diff.update(&document.svg, simplified);
+    let (removed, added) = diff.stats();
+    ui.label(format!(
+        "Simplify preview: {} bytes -> {} bytes, -{removed} +{added} lines",
+        document.svg.len(),
+        simplified.len()
+    ));
+    diff.show_lines(ui, font_size);
+    ui.horizontal(|ui| {
+        if ui.button("Apply").clicked()
+            && let Some((_, simplified)) = document.simplify_preview.take()
+        {
+            document.apply_optimization(simplified);
+        } else if ui.button("Cancel").clicked() {
+            document.simplify_preview = None;
+        }
+    });
+}
+
+/// Show the optimizer passes and the Optimize button
+fn show_optimizer(
+    ui: &mut egui::Ui,
+    document: &mut Document,
+    options: &mut OptimizeOptions,
+    error_manager: &mut ErrorManager,
+) {
+    for pass in Pass::ALL {
+        let mut enabled = options.is_enabled(pass);
+        if ui
+            .checkbox(&mut enabled, pass.name())
+            .on_hover_text(pass.description())
+            .changed()
+        {
+            options.set_enabled(pass, enabled);
+        }
+    }
+    ui.add_enabled(
+        options.is_enabled(Pass::RoundNumbers),
+        egui::Slider::new(&mut options.precision, 0..=8).text("Precision"),
+    );
+    ui.checkbox(&mut options.indent, "Indent output");
+    if ui
+        .button("Optimize")
+        .on_hover_text("Run the enabled passes on the svg")
+        .clicked()
+    {
+        match optimize(&document.svg, options) {
+            Ok(report) => document.apply_optimization(report.svg),
+            Err(e) => {
+                error_manager.add_error(format!("Cannot optimize the svg: {e}"));
+            }
+        }
+    }
+}
+
+/// Show the last optimization sizes and the visual equality check
+fn show_visual_check(ui: &mut egui::Ui, document: &mut Document, usvg_options: &Options<'_>) {
+    document.drop_stale_optimization();
+    if let Some((before, after)) = document.optimization_sizes {
+        ui.label(format!(
+            "Last optimization: {before} bytes -> {after} bytes"
+        ));
+    }
+    let Some(pre_optimization_svg) = &document.pre_optimization_svg else {
+        return;
+    };
+    ui.horizontal(|ui| {
+        if ui
+            .button("Verify visual equality")
+            .on_hover_text("Compare the render before and after the optimization")
+            .clicked()
+        {
+            let before = pre_optimization_svg.clone();
+            let after = document.svg.clone();
+            let options = copy_options(usvg_options);
+            let max_side = max_texture_side(ui.ctx());
+            document.visual_check = None;
+            // replacing the job cancels the running one
+            document.visual_check_job = Some(BackgroundJob::start(ui.ctx(), move |_| {
+                Some(compare_svgs(
+                    &before,
+                    &after,
+                    &options,
+                    1,
+                    max_side,
+                    VISUAL_CHANNEL_TOLERANCE,
+                ))
+            }));
+        }
+        if let Some(job) = &mut document.visual_check_job {
+            match job.poll() {
+                Ok(Some(result)) => {
+                    document.visual_check = Some(result);
+                    document.visual_check_job = None;
+                }
+                Ok(None) => {
+                    ui.spinner();
+                }
+                Err(()) => {
+                    document.visual_check = Some(Err("The visual check stopped".to_string()));
+                    document.visual_check_job = None;
+                }
+            }
+        }
+        match &document.visual_check {
+            Some(Ok(diff)) if diff.is_equal(VISUAL_MAX_RATIO) => {
+                ui.colored_label(Color32::GREEN, "Visually equal");
+            }
+            Some(Ok(diff)) => {
+                ui.colored_label(
+                    Color32::RED,
+                    format!(
+                        "{} pixels differ ({:.2}%)",
+                        diff.different_pixels,
+                        diff.different_ratio() * 100.0
+                    ),
+                );
+            }
+            Some(Err(e)) => {
+                ui.colored_label(Color32::RED, e);
+            }
+            None => {}
+        }
+    });
+}
+
+/// Combo box to pick an indentation style
+fn indent_combo(ui: &mut egui::Ui, label: &str, indent: &mut IndentStyle) {
+    let text = |indent: IndentStyle| match indent {
+        IndentStyle::None => "None".to_string(),
+        IndentStyle::Spaces(n) => format!("{n} spaces"),
+        IndentStyle::Tabs => "Tabs".to_string(),
+    };
+    egui::ComboBox::from_label(label)
+        .selected_text(text(*indent))
+        .show_ui(ui, |ui| {
+            for choice in [
+                IndentStyle::None,
+                IndentStyle::Spaces(2),
+                IndentStyle::Spaces(4),
+                IndentStyle::Tabs,
+            ] {
+                ui.selectable_value(indent, choice, text(choice));
+            }
+        });
+}
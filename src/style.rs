@@ -0,0 +1,375 @@
+//! Style
+//! Inline `style` declarations and their conversion to presentation attributes
+
+use simplecss::DeclarationTokenizer;
+use xmltree::{Element, XMLNode};
+
+use crate::css::{element_paths, matched_rules, stylesheet_text};
+use crate::node::node_at_mut;
+
+/// Properties that can also be written as presentation attributes
+pub const PRESENTATION_ATTRIBUTES: &[&str] = &[
+    "alignment-baseline",
+    "baseline-shift",
+    "clip",
+    "clip-path",
+    "clip-rule",
+    "color",
+    "color-interpolation",
+    "color-interpolation-filters",
+    "color-profile",
+    "color-rendering",
+    "cursor",
+    "direction",
+    "display",
+    "dominant-baseline",
+    "enable-background",
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "filter",
+    "flood-color",
+    "flood-opacity",
+    "font",
+    "font-family",
+    "font-size",
+    "font-size-adjust",
+    "font-stretch",
+    "font-style",
+    "font-variant",
+    "font-weight",
+    "glyph-orientation-horizontal",
+    "glyph-orientation-vertical",
+    "image-rendering",
+    "kerning",
+    "letter-spacing",
+    "lighting-color",
+    "marker-end",
+    "marker-mid",
+    "marker-start",
+    "mask",
+    "opacity",
+    "overflow",
+    "pointer-events",
+    "shape-rendering",
+    "stop-color",
+    "stop-opacity",
+    "stroke",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-miterlimit",
+    "stroke-opacity",
+    "stroke-width",
+    "text-anchor",
+    "text-decoration",
+    "text-rendering",
+    "unicode-bidi",
+    "visibility",
+    "word-spacing",
+    "writing-mode",
+];
+
+/// A declaration of a `style` attribute
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StyleDeclaration {
+    /// Property name
+    pub name: String,
+    /// Property value
+    pub value: String,
+    /// Marked as `!important`
+    pub important: bool,
+}
+
+/// Check if the property can be written as a presentation attribute
+#[must_use]
+pub fn is_presentation_attribute(name: &str) -> bool {
+    PRESENTATION_ATTRIBUTES.contains(&name)
+}
+
+/// Byte offsets of the `;` ending the declarations, outside of quotes and parentheses
+fn declaration_ends(style: &str) -> Vec<usize> {
+    let mut ends = Vec::new();
+    let mut quote = None;
+    let mut depth = 0usize;
+    for (offset, c) in style.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth = depth.saturating_sub(1),
+            (None, ';') if depth == 0 => ends.push(offset),
+            _ => {}
+        }
+    }
+    ends.push(style.len());
+    ends
+}
+
+/// Parse the declarations of a `style` attribute up to the first invalid one,
+/// returns them with the unparsed rest of the text
+#[must_use]
+pub fn parse_style_with_rest(style: &str) -> (Vec<StyleDeclaration>, &str) {
+    let mut declarations = Vec::new();
+    let mut start = 0;
+    for end in declaration_ends(style) {
+        let text = &style[start..end];
+        if !text.trim().is_empty() {
+            let mut tokenizer = DeclarationTokenizer::from(text);
+            match (tokenizer.next(), tokenizer.next()) {
+                (Some(declaration), None) => declarations.push(StyleDeclaration {
+                    name: declaration.name.to_string(),
+                    value: declaration.value.to_string(),
+                    important: declaration.important,
+                }),
+                _ => return (declarations, style[start..].trim()),
+            }
+        }
+        start = (end + 1).min(style.len());
+    }
+    (declarations, "")
+}
+
+/// Parse the declarations of a `style` attribute, stopping at the first invalid one
+#[must_use]
+pub fn parse_style(style: &str) -> Vec<StyleDeclaration> {
+    parse_style_with_rest(style).0
+}
+
+/// Write declarations as a `style` attribute
+#[must_use]
+pub fn write_style(declarations: &[StyleDeclaration]) -> String {
+    declarations
+        .iter()
+        .map(|declaration| {
+            let important = if declaration.important {
+                " !important"
+            } else {
+                ""
+            };
+            format!("{}:{}{important}", declaration.name, declaration.value)
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Write declarations as a `style` attribute, followed by the unparsed rest
+/// of the original text
+#[must_use]
+pub fn write_style_with_rest(declarations: &[StyleDeclaration], rest: &str) -> String {
+    let mut style = write_style(declarations);
+    if !rest.is_empty() {
+        if !style.is_empty() {
+            style.push(';');
+        }
+        style.push_str(rest);
+    }
+    style
+}
+
+/// Move the declarations of the `style` attribute that are presentation
+/// attributes into attributes, `!important` ones and the ones in `ruled` stay in the style
+///
+/// Returns `true` if the element changed.
+pub fn style_to_attributes(element: &mut Element, ruled: &[String]) -> bool {
+    let Some(style) = element.attributes.get("style") else {
+        return false;
+    };
+    let (declarations, rest) = parse_style_with_rest(style);
+    let rest = rest.to_string();
+    let (moved, kept): (Vec<_>, Vec<_>) = declarations.into_iter().partition(|d| {
+        !d.important && is_presentation_attribute(&d.name) && !ruled.contains(&d.name)
+    });
+    if moved.is_empty() {
+        return false;
+    }
+    // the style has priority over the attributes
+    for declaration in moved {
+        element
+            .attributes
+            .insert(declaration.name, declaration.value);
+    }
+    // the unparsed rest stays in the style, it may hide valid declarations
+    if kept.is_empty() && rest.is_empty() {
+        element.attributes.shift_remove("style");
+    } else {
+        element
+            .attributes
+            .insert("style".to_string(), write_style_with_rest(&kept, &rest));
+    }
+    true
+}
+
+/// Move the presentation attributes into the `style` attribute, except the ones in `ruled`
+///
+/// Returns `true` if the element changed.
+pub fn attributes_to_style(element: &mut Element, ruled: &[String]) -> bool {
+    let style = element.attributes.get("style").cloned().unwrap_or_default();
+    let (mut declarations, rest) = parse_style_with_rest(&style);
+    let names = element
+        .attributes
+        .keys()
+        .filter(|name| is_presentation_attribute(name) && !ruled.contains(name))
+        .cloned()
+        .collect::<Vec<_>>();
+    if names.is_empty() {
+        return false;
+    }
+    for name in names {
+        let Some(value) = element.attributes.shift_remove(&name) else {
+            continue;
+        };
+        // an existing declaration has priority over the attribute
+        if !declarations.iter().any(|d| d.name == name) {
+            declarations.push(StyleDeclaration {
+                name,
+                value,
+                important: false,
+            });
+        }
+    }
+    element.attributes.insert(
+        "style".to_string(),
+        write_style_with_rest(&declarations, rest),
+    );
+    true
+}
+
+/// Apply `convert` to the element and all its descendants, returns the number
+/// of changed elements
+///
+/// `convert` gets the properties set by the stylesheet rules matching the element:
+/// the rules come between the attributes and the `style` in the cascade, so these
+/// properties cannot move from one to the other.
+pub fn convert_all(root: &mut Element, convert: fn(&mut Element, &[String]) -> bool) -> usize {
+    let stylesheet = stylesheet_text(root);
+    let mut paths = Vec::new();
+    element_paths(root, &mut Vec::new(), &mut paths);
+    // match everything before editing, rules may select on attributes
+    let ruled = paths
+        .into_iter()
+        .map(|path| {
+            let names = if stylesheet.trim().is_empty() {
+                Vec::new()
+            } else {
+                matched_rules(root, &stylesheet, &path)
+                    .into_iter()
+                    .flat_map(|rule| rule.declarations)
+                    .map(|declaration| declaration.name)
+                    .collect()
+            };
+            (path, names)
+        })
+        .collect::<Vec<_>>();
+    let mut count = 0;
+    for (path, names) in ruled {
+        let element = if path.is_empty() {
+            &mut *root
+        } else {
+            match node_at_mut(root, &path) {
+                Some(XMLNode::Element(element)) => element,
+                _ => continue,
+            }
+        };
+        count += usize::from(convert(element, &names));
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn test_parse_style() {
+        let declarations = parse_style("fill:#fff; stroke : red !important;;");
+        assert_eq!(declarations.len(), 2);
+        assert_eq!(declarations[1].name, "stroke");
+        assert_eq!(declarations[1].value, "red");
+        assert!(declarations[1].important);
+        assert_eq!(
+            write_style(&declarations),
+            "fill:#fff;stroke:red !important"
+        );
+    }
+
+    #[test]
+    fn test_parse_style_with_rest() {
+        let (declarations, rest) =
+            parse_style_with_rest("fill:url('a;b'); stroke red; opacity:0.5");
+        assert_eq!(declarations.len(), 1);
+        assert_eq!(declarations[0].value, "url('a;b')");
+        assert_eq!(rest, "stroke red; opacity:0.5");
+        assert_eq!(parse_style_with_rest("fill:red;").1, "");
+    }
+
+    #[test]
+    fn test_style_conversion() {
+        let mut root = Element::parse(
+            r#"<svg><rect fill="blue" style="fill:red;mix-blend-mode:multiply"/><g style="stroke:red"><path stroke-width="2"/></g></svg>"#
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(convert_all(&mut root, style_to_attributes), 2);
+        let rect = root.get_child("rect").unwrap();
+        assert_eq!(rect.attributes["fill"], "red");
+        assert_eq!(rect.attributes["style"], "mix-blend-mode:multiply");
+        let g = root.get_child("g").unwrap();
+        assert_eq!(g.attributes["stroke"], "red");
+        assert!(!g.attributes.contains_key("style"));
+
+        assert_eq!(convert_all(&mut root, attributes_to_style), 3);
+        let rect = root.get_child("rect").unwrap();
+        assert_eq!(rect.attributes["style"], "mix-blend-mode:multiply;fill:red");
+        assert!(!rect.attributes.contains_key("fill"));
+        let path = root.get_child("g").unwrap().get_child("path").unwrap();
+        assert_eq!(path.attributes["style"], "stroke-width:2");
+    }
+
+    #[test]
+    fn test_style_conversion_with_invalid_declaration() {
+        let mut root = Element::parse(
+            r#"<svg><rect style="fill:red; 1bad; stroke:blue; opacity:0.5"/></svg>"#.as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(convert_all(&mut root, style_to_attributes), 1);
+        let rect = root.get_child("rect").unwrap();
+        assert_eq!(rect.attributes["fill"], "red");
+        assert_eq!(rect.attributes["style"], "1bad; stroke:blue; opacity:0.5");
+
+        let rect = root.get_mut_child("rect").unwrap();
+        assert!(attributes_to_style(rect, &[]));
+        assert!(!rect.attributes.contains_key("fill"));
+        assert_eq!(
+            rect.attributes["style"],
+            "fill:red;1bad; stroke:blue; opacity:0.5"
+        );
+    }
+
+    #[test]
+    fn test_style_conversion_with_stylesheet() {
+        let mut root = Element::parse(
+            r#"<svg><style>rect{fill:green}</style><rect style="fill:red;stroke:blue"/><circle fill="red"/></svg>"#
+                .as_bytes(),
+        )
+        .unwrap();
+        // the style wins over the rule, the attribute would not
+        assert_eq!(convert_all(&mut root, style_to_attributes), 1);
+        let rect = root.get_child("rect").unwrap();
+        assert_eq!(rect.attributes["style"], "fill:red");
+        assert_eq!(rect.attributes["stroke"], "blue");
+
+        let mut root = Element::parse(
+            r#"<svg><style>rect{fill:green}</style><rect fill="red" stroke="blue"/></svg>"#
+                .as_bytes(),
+        )
+        .unwrap();
+        // the rule wins over the attribute, the style would not
+        assert_eq!(convert_all(&mut root, attributes_to_style), 1);
+        let rect = root.get_child("rect").unwrap();
+        assert_eq!(rect.attributes["fill"], "red");
+        assert_eq!(rect.attributes["style"], "stroke:blue");
+    }
+}
@@ -1,6 +1,12 @@
 //! Svg Render
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 use std::{path::PathBuf, sync::Arc};
 
+use crate::GalagoApp;
+use crate::render::{SvgError, render_region};
+use crate::render_job::{BackgroundJob, RenderFailure, RenderJob, RenderedSvg};
 use bladvak::{
     AppError, ErrorManager,
     app::BladvakPanel,
@@ -9,12 +15,70 @@ use bladvak::{
     utils::grid::Grid,
 };
 use egui::{
-    Color32, Context, CornerRadius, ImageData, ImageFit, ImageSize, Rect, Sense, TextureHandle,
-    TextureOptions, Vec2, WidgetInfo, WidgetType, pos2,
+    Color32, ColorImage, Context, CornerRadius, ImageData, ImageFit, ImageSize, Pos2, Rect, Sense,
+    TextureHandle, TextureOptions, Vec2, WidgetInfo, WidgetType, pos2,
 };
-use resvg::tiny_skia::Pixmap;
+use resvg::usvg;
 
-use crate::GalagoApp;
+/// Idle time after the last change before rendering, in seconds
+const RENDER_DELAY: f64 = 0.3;
+
+/// Time between two repaints showing the progress of a job
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Side of a tile in pixels
+const TILE_SIZE: u32 = 256;
+
+/// Highest scale of the tiles
+const MAX_TILE_SCALE: u32 = 128;
+
+/// Tiles rendered by a job
+const TILES_PER_JOB: usize = 4;
+
+/// Tiles kept in the cache before dropping the hidden ones
+const MAX_TILES: usize = 128;
+
+/// Scale and position in the grid of a tile
+type TileKey = (u32, i32, i32);
+
+/// Rendered tiles, or why they failed
+type RenderedTiles = Vec<(TileKey, Result<ColorImage, String>)>;
+
+/// Render the tiles of `keys`, stops early when cancelled
+#[allow(clippy::cast_precision_loss)]
+fn render_tiles(
+    tree: &usvg::Tree,
+    keys: Vec<TileKey>,
+    cancelled: &AtomicBool,
+) -> Option<RenderedTiles> {
+    keys.into_iter()
+        .map(|key @ (scale, x, y)| {
+            if cancelled.load(Ordering::Relaxed) {
+                return None;
+            }
+            let tile_units = TILE_SIZE as f32 / scale as f32;
+            let origin = (x as f32 * tile_units, y as f32 * tile_units);
+            let image =
+                render_region(tree, scale as f32, origin, (TILE_SIZE, TILE_SIZE)).map(|pixmap| {
+                    ColorImage::from_rgba_unmultiplied([TILE_SIZE as usize; 2], pixmap.data())
+                });
+            Some((key, image))
+        })
+        .collect()
+}
+
+/// Warning if the render is smaller than requested by the scaler
+#[allow(clippy::cast_precision_loss)]
+fn downscale_warning(rendered: &RenderedSvg) -> Option<String> {
+    if rendered.scale >= rendered.scaler as f32 {
+        return None;
+    }
+    let [w, h] = rendered.image.size;
+    Some(format!(
+        "The render is limited to {w}x{h} pixels (scale {:.2} instead of {}), zoom in to see the details",
+        rendered.scale, rendered.scaler
+    ))
+}
 
 /// Svg Render Struct
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -23,10 +87,46 @@ pub struct SvgRender {
     #[serde(skip)]
     texture_save: Option<TextureHandle>,
 
-    /// Cached svg string
+    /// Scale of the texture, below the scaler if it was downscaled
+    #[serde(skip)]
+    texture_scale: f32,
+
+    /// Why the texture was downscaled
+    #[serde(skip)]
+    warning: Option<String>,
+
+    /// Parsed svg of the texture
+    #[serde(skip)]
+    tree: Option<Arc<usvg::Tree>>,
+
+    /// Parts of the texture rendered at the zoom of the viewer
+    #[serde(skip)]
+    tiles: HashMap<TileKey, TextureHandle>,
+
+    /// Tiles being rendered in the background
+    #[serde(skip)]
+    tiles_job: Option<(Vec<TileKey>, BackgroundJob<RenderedTiles>)>,
+
+    /// Svg string of the texture, or of the error
     #[serde(skip)]
     cached_svg: Option<String>,
 
+    /// Parse error of the cached svg, the texture is the last valid render
+    #[serde(skip)]
+    error: Option<SvgError>,
+
+    /// The cached svg is valid but failed to render
+    #[serde(skip)]
+    failed: bool,
+
+    /// Svg waiting to be rendered and the time of its last change
+    #[serde(skip)]
+    pending: Option<(String, f64)>,
+
+    /// Running render job
+    #[serde(skip)]
+    job: Option<RenderJob>,
+
     /// Whether to auto scale the SVG
     auto_scale: bool,
 
@@ -37,7 +137,16 @@ impl Default for SvgRender {
     fn default() -> Self {
         Self {
             texture_save: None,
+            texture_scale: 1.0,
+            warning: None,
+            tree: None,
+            tiles: HashMap::new(),
+            tiles_job: None,
             cached_svg: None,
+            error: None,
+            failed: false,
+            pending: None,
+            job: None,
             auto_scale: true,
             scaler: 1,
         }
@@ -48,6 +157,8 @@ impl std::fmt::Debug for SvgRender {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("SvgRender")
             .field("cached_svg", &self.cached_svg)
+            .field("error", &self.error)
+            .field("is_rendering", &self.job.is_some())
             .field("auto_scale", &self.auto_scale)
             .field("scaler", &self.scaler)
             .finish_non_exhaustive()
@@ -60,16 +171,288 @@ impl SvgRender {
         self.cached_svg = None;
     }
 
-    /// Show the rendered svg
+    /// Render `svg` in the background once it stopped changing for a moment
+    ///
+    /// The texture stays the last valid render until the job is done, a job of
+    /// an older svg is cancelled. The first render and the stale ones start
+    /// without waiting.
+    /// # Errors
+    /// Return error if fails to render svg, `None` if the svg is invalid
+    pub(crate) fn update(
+        &mut self,
+        ctx: &Context,
+        svg: &str,
+        options: &usvg::Options<'_>,
+    ) -> Result<(), Option<AppError>> {
+        let mut result = self.poll_job(ctx);
+        let is_running = self.job.as_ref().is_some_and(|job| job.svg == svg);
+        if self.cached_svg.as_deref() != Some(svg) && !is_running {
+            let now = ctx.input(|i| i.time);
+            if self
+                .pending
+                .as_ref()
+                .is_none_or(|(pending, _)| pending != svg)
+            {
+                self.pending = Some((svg.to_string(), now));
+            }
+            let idle = self
+                .pending
+                .as_ref()
+                .map_or(0.0, |(_, changed)| now - changed);
+            if self.cached_svg.is_none() || idle >= RENDER_DELAY {
+                log::debug!("Rendering SVG");
+                self.pending = None;
+                // replacing the job cancels the stale one
+                self.job = Some(RenderJob::start(
+                    ctx,
+                    svg,
+                    options,
+                    self.auto_scale,
+                    self.scaler,
+                ));
+                result = result.and(self.poll_job(ctx));
+            } else {
+                ctx.request_repaint_after(Duration::from_secs_f64(RENDER_DELAY - idle));
+            }
+        }
+        if self.job.is_some() {
+            ctx.request_repaint_after(PROGRESS_INTERVAL);
+        }
+        let is_cached = self.cached_svg.as_deref() == Some(svg);
+        if result.is_ok() && is_cached && (self.error.is_some() || self.failed) {
+            return Err(None);
+        }
+        result
+    }
+
+    /// Apply the result of the job if it is done
+    /// # Errors
+    /// Return error if the svg failed to render
+    fn poll_job(&mut self, ctx: &Context) -> Result<(), Option<AppError>> {
+        let Some(job) = &mut self.job else {
+            return Ok(());
+        };
+        let Some(result) = job.poll(ctx) else {
+            return Ok(());
+        };
+        self.cached_svg = Some(job.svg.clone());
+        self.job = None;
+        self.failed = false;
+        match result {
+            Ok(rendered) => {
+                self.warning = downscale_warning(&rendered);
+                if let Some(warning) = &self.warning {
+                    log::warn!("{warning}");
+                }
+                self.texture_save = Some(ctx.load_texture(
+                    "svg",
+                    ImageData::Color(Arc::new(rendered.image)),
+                    TextureOptions::default(),
+                ));
+                self.texture_scale = rendered.scale;
+                if self.auto_scale {
+                    self.scaler = rendered.scaler;
+                }
+                self.tree = Some(rendered.tree);
+                self.tiles.clear();
+                self.tiles_job = None;
+                self.error = None;
+                Ok(())
+            }
+            Err(RenderFailure::Invalid(error)) => {
+                self.error = Some(error);
+                Ok(())
+            }
+            Err(RenderFailure::Failed(error)) => {
+                self.error = None;
+                self.failed = true;
+                Err(Some(error.into()))
+            }
+        }
+    }
+
+    /// Why the texture was downscaled, if it was
+    pub(crate) fn warning(&self) -> Option<&str> {
+        self.warning.as_deref()
+    }
+
+    /// Check if the texture is not the render of `svg`
+    pub(crate) fn is_outdated(&self, svg: &str) -> bool {
+        self.texture_save.is_some() && self.cached_svg.as_deref() != Some(svg)
+    }
+
+    /// Progress of the running render job, between 0 and 1
+    pub(crate) fn progress(&self) -> Option<f32> {
+        self.job.as_ref().map(RenderJob::progress)
+    }
+
+    /// Parse error of `svg`, if it is the last updated svg and it is invalid
+    pub(crate) fn error(&self, svg: &str) -> Option<&SvgError> {
+        self.error
+            .as_ref()
+            .filter(|_| self.cached_svg.as_deref() == Some(svg))
+    }
+
+    /// Texture of the last render
+    pub(crate) fn texture(&self) -> Option<&TextureHandle> {
+        self.texture_save.as_ref()
+    }
+
+    /// Size of the rendered svg in svg units
+    pub(crate) fn size(&self) -> Option<Vec2> {
+        self.texture_save
+            .as_ref()
+            .map(|texture| self.display_size(texture))
+    }
+
+    /// Size of the render in svg units
+    #[allow(clippy::cast_precision_loss)]
+    fn display_size(&self, texture: &TextureHandle) -> Vec2 {
+        let [w, h] = texture.size();
+        Vec2::new(w as f32, h as f32) / self.texture_scale
+    }
+
+    /// Paint the render at `min` with the given opacity, over what is already drawn
+    pub(crate) fn paint_overlay(&self, ui: &egui::Ui, min: Pos2, opacity: f32) {
+        let Some(texture_save) = &self.texture_save else {
+            return;
+        };
+        let rect = Rect::from_min_size(min, self.display_size(texture_save));
+        let uv = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+        ui.painter().image(
+            texture_save.id(),
+            rect,
+            uv,
+            Color32::WHITE.gamma_multiply(opacity),
+        );
+    }
+
+    /// Load the tiles of the job if it is done
+    fn poll_tiles(&mut self, ctx: &Context) {
+        let Some((_, job)) = &mut self.tiles_job else {
+            return;
+        };
+        match job.poll() {
+            Ok(Some(tiles)) => {
+                self.tiles_job = None;
+                for (key @ (scale, x, y), image) in tiles {
+                    match image {
+                        Ok(image) => {
+                            let name = format!("svg_tile_{scale}_{x}_{y}");
+                            let texture = ctx.load_texture(name, image, TextureOptions::LINEAR);
+                            self.tiles.insert(key, texture);
+                        }
+                        Err(e) => {
+                            log::error!("SVG tile render error: {e}");
+                            // no more tiles until the next render
+                            self.tree = None;
+                        }
+                    }
+                }
+            }
+            Ok(None) => {}
+            Err(()) => self.tiles_job = None,
+        }
+    }
+
+    /// Paint the visible tiles of the render at `rect`, rendered at the zoom of
+    /// the scene, the missing ones are rendered in the background
+    ///
+    /// Returns `true` if the tiles cover the visible part of the render.
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
     #[allow(clippy::cast_precision_loss)]
-    pub fn show(&self, ui: &mut egui::Ui) -> Result<egui::Response, ()> {
-        if let Some(texture_save) = &self.texture_save {
+    fn paint_tiles(&mut self, ui: &egui::Ui, rect: Rect) -> bool {
+        let Some(tree) = self.tree.clone() else {
+            return false;
+        };
+        let zoom = ui
+            .ctx()
+            .layer_transform_to_global(ui.layer_id())
+            .map_or(1.0, |transform| transform.scaling);
+        let pixels_per_unit = zoom * ui.ctx().pixels_per_point();
+        // the texture is sharp enough
+        if pixels_per_unit <= self.texture_scale {
+            return false;
+        }
+        let scale = (pixels_per_unit.ceil() as u32)
+            .next_power_of_two()
+            .min(MAX_TILE_SCALE);
+        let visible = ui.clip_rect().intersect(rect);
+        if !visible.is_positive() {
+            return false;
+        }
+        let tile_units = TILE_SIZE as f32 / scale as f32;
+        let first = ((visible.min - rect.min) / tile_units).floor();
+        let last = ((visible.max - rect.min) / tile_units).ceil();
+        let columns = first.x as i32..last.x as i32;
+        let rows = first.y as i32..last.y as i32;
+        let is_visible = |&(tile_scale, x, y): &TileKey| {
+            tile_scale == scale && columns.contains(&x) && rows.contains(&y)
+        };
+        if self.tiles.len() > MAX_TILES {
+            self.tiles.retain(|key, _| is_visible(key));
+        }
+        // dropping the job of tiles scrolled out of view cancels it
+        if let Some((keys, _)) = &self.tiles_job
+            && !keys.iter().any(is_visible)
+        {
+            self.tiles_job = None;
+        }
+        let keys = rows
+            .flat_map(|y| columns.clone().map(move |x| (scale, x, y)))
+            .collect::<Vec<_>>();
+        if self.tiles_job.is_none() {
+            let missing = keys
+                .iter()
+                .filter(|key| !self.tiles.contains_key(key))
+                .take(TILES_PER_JOB)
+                .copied()
+                .collect::<Vec<_>>();
+            if !missing.is_empty() {
+                let job_keys = missing.clone();
+                let job = BackgroundJob::start(ui.ctx(), move |cancelled| {
+                    render_tiles(&tree, job_keys, cancelled)
+                });
+                self.tiles_job = Some((missing, job));
+            }
+        }
+        self.poll_tiles(ui.ctx());
+        if self.tree.is_none() {
+            return false;
+        }
+        let painter = ui.painter().with_clip_rect(visible);
+        let uv = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+        let mut is_covered = true;
+        for key @ (_, x, y) in keys {
+            let Some(texture) = self.tiles.get(&key) else {
+                is_covered = false;
+                continue;
+            };
+            let origin = Vec2::new(x as f32, y as f32) * tile_units;
+            let tile = Rect::from_min_size(rect.min + origin, Vec2::splat(tile_units));
+            painter.add(
+                RectShape::filled(tile, CornerRadius::ZERO, Color32::WHITE)
+                    .with_texture(texture.id(), uv),
+            );
+        }
+        // the next tiles are started once the job is done
+        if !is_covered && self.tiles_job.is_none() {
+            ui.ctx().request_repaint();
+        }
+        is_covered
+    }
+
+    /// Show the rendered svg, sharpened by tiles when zoomed in
+    #[allow(clippy::cast_precision_loss)]
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Result<egui::Response, ()> {
+        if let Some(texture_save) = self.texture_save.clone() {
             let texture_size = texture_save.size();
             let image_size = ImageSize {
                 maintain_aspect_ratio: true,
                 max_size: Vec2::INFINITY,
                 fit: ImageFit::Original {
-                    scale: 1.0 / self.scaler as f32,
+                    scale: 1.0 / self.texture_scale,
                 },
             };
             let ui_size = image_size.calc_size(
@@ -83,12 +466,16 @@ impl SvgRender {
                 info
             });
             if ui.is_rect_visible(rect) {
-                let painter = ui.painter();
-                let uv = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
-                painter.add(
-                    RectShape::filled(rect, CornerRadius::ZERO, Color32::WHITE)
-                        .with_texture(texture_save.id(), uv),
-                );
+                // the texture is hidden once the sharper tiles are all there
+                let texture_shape = ui.painter().add(egui::Shape::Noop);
+                if !self.paint_tiles(ui, rect) {
+                    let uv = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+                    ui.painter().set(
+                        texture_shape,
+                        RectShape::filled(rect, CornerRadius::ZERO, Color32::WHITE)
+                            .with_texture(texture_save.id(), uv),
+                    );
+                }
 
                 // rect
                 // let visuals = ui.style().interact_selectable(&response, true);
@@ -126,6 +513,7 @@ impl GalagoApp {
             for one_doc in &mut self.documents {
                 one_doc.svg_render.auto_scale = self.settings.auto_scale;
                 one_doc.svg_render.stale_render();
+                one_doc.saved_render.stale_render();
             }
         }
         if ui
@@ -139,6 +527,7 @@ impl GalagoApp {
                 one_doc.svg_render.auto_scale = self.settings.auto_scale;
                 one_doc.svg_render.scaler = self.settings.global_scaler;
                 one_doc.svg_render.stale_render();
+                one_doc.saved_render.stale_render();
             }
         }
         ui.collapsing(
@@ -166,67 +555,31 @@ impl GalagoApp {
     /// Update the svg
     /// # Errors
     /// Return error if fails to render svg
-    #[allow(clippy::cast_sign_loss)]
-    #[allow(clippy::cast_possible_truncation)]
-    #[allow(clippy::cast_precision_loss)]
     pub fn update_svg(&mut self, ctx: &Context) -> Result<(), Option<AppError>> {
         let Some(document) = self.documents.get_current_doc_mut() else {
             return Ok(());
         };
-        if document.svg_render.texture_save.is_some()
-            && document
-                .svg_render
-                .cached_svg
-                .as_deref()
-                .is_some_and(|cached| document.svg == cached)
-        {
-            return Ok(());
-        }
-        log::debug!("Rendering SVG");
-
-        if let Ok(rtree) = resvg::usvg::Tree::from_str(&document.svg, &self.usvg_options) {
-            if document.svg_render.auto_scale {
-                // Calculate the sizer based on the SVG size
-                let size = rtree.size().width().max(rtree.size().height()) as u32;
-                if size < 500 {
-                    document.svg_render.scaler = 6;
-                } else if size < 1000 {
-                    document.svg_render.scaler = 4;
-                } else if size < 2000 {
-                    document.svg_render.scaler = 2;
-                } else {
-                    document.svg_render.scaler = 1;
-                }
-            }
-            let (w, h) = (
-                rtree.size().width() as u32 * document.svg_render.scaler,
-                rtree.size().height() as u32 * document.svg_render.scaler,
-            );
-            let mut pixmap = Pixmap::new(w, h).ok_or_else(|| {
-                Some(AppError::new(format!(
-                    "Failed to create SVG Pixmap of size {w}x{h}"
-                )))
-            })?;
-
-            let transform = resvg::tiny_skia::Transform {
-                sx: document.svg_render.scaler as f32,
-                sy: document.svg_render.scaler as f32,
-                ..Default::default()
-            };
-            resvg::render(&rtree, transform, &mut pixmap.as_mut());
-
-            let image = egui::ColorImage::from_rgba_unmultiplied([w as _, h as _], pixmap.data());
+        document
+            .svg_render
+            .update(ctx, &document.svg, &self.usvg_options)
+    }
 
-            let texture_loaded = ctx.load_texture(
-                "svg",
-                ImageData::Color(Arc::new(image)),
-                TextureOptions::default(),
-            );
-            document.svg_render.texture_save = Some(texture_loaded);
-            document.svg_render.cached_svg = Some(document.svg.clone());
-            return Ok(());
+    /// Update the render of the saved svg, used by the onion skin
+    pub(crate) fn update_saved_svg(&mut self, ctx: &Context) {
+        let Some(document) = self.documents.get_current_doc_mut() else {
+            return;
+        };
+        document.saved_render.auto_scale = document.svg_render.auto_scale;
+        if !document.saved_render.auto_scale {
+            document.saved_render.scaler = document.svg_render.scaler;
+        }
+        if let Err(Some(err)) =
+            document
+                .saved_render
+                .update(ctx, &document.saved_svg, &self.usvg_options)
+        {
+            log::error!("Saved SVG render error: {err}");
         }
-        Err(None)
     }
 }
 
@@ -21,6 +21,8 @@ impl GalagoApp {
             return Err("No svg document".into());
         };
         let bytes = document.svg.as_bytes();
-        bladvak::utils::save_file(bytes, path_file)
+        bladvak::utils::save_file(bytes, path_file)?;
+        document.saved_svg.clone_from(&document.svg);
+        Ok(())
     }
 }
@@ -4,18 +4,29 @@ use bladvak::ErrorManager;
 use bladvak::app::BladvakPanel;
 use bladvak::eframe::egui::{self, Color32, Frame, Window};
 use bladvak::egui_extras::{Column, TableBuilder};
-use std::collections::HashMap;
+use resvg::usvg;
+use std::collections::{BTreeSet, HashMap};
 use svgtypes::PathSegment;
-use xmltree::{Element, EmitterConfig};
+use xmltree::{Element, XMLNode};
 
 use crate::GalagoApp;
-use crate::path::{
-    SvgPath, circle_to_path, ellipse_to_path, line_to_path, polygon_to_path, polyline_to_path,
-    rect_to_path,
+use crate::attribute_editor::{autocomplete_edit, show_attribute_value};
+use crate::document::{Document, SvgTree};
+use crate::layer::layers;
+use crate::node::{
+    duplicate_node, group_nodes, move_nodes_to, node_at_mut, normalize_selection,
+    path_after_insertion, path_after_removal, remap_path, remove_nodes, shift_node, ungroup_node,
 };
+use crate::path::SvgPath;
+use crate::render::{copy_options, element_bounding_boxes};
+use crate::render_job::BackgroundJob;
+use crate::schema::{attribute_warning, element_schema, element_warning, is_svg_element};
+use crate::shape::{detect_primitive, is_convertible_to_path, path_to_primitive, shape_to_path};
+use crate::style::{attributes_to_style, convert_all, style_to_attributes};
 
 /// `TreeViewer` Struct
 #[derive(serde::Deserialize, serde::Serialize)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct TreeViewer {
     /// Is multi line
     is_multi_line: bool,
@@ -26,9 +37,50 @@ pub struct TreeViewer {
     /// Edit as inputs
     edit_path_as_input: bool,
 
-    /// Index of the group to edit
+    /// Path of the path element open in the edition window
     #[serde(skip)]
-    ref_group: Option<usize>,
+    edited_path: Option<Vec<usize>>,
+
+    /// Paths of the selected nodes
+    #[serde(skip)]
+    pub(crate) selection: Vec<Vec<usize>>,
+
+    /// Last clicked node, start of the shift selection
+    #[serde(skip)]
+    anchor: Option<Vec<usize>>,
+
+    /// Node to open and scroll to
+    #[serde(skip)]
+    reveal: Option<Vec<usize>>,
+
+    /// Index of the document of the selection
+    #[serde(skip)]
+    selection_document: usize,
+
+    /// Indexes of the root children that are locked layers
+    #[serde(skip)]
+    locked_indexes: BTreeSet<usize>,
+
+    /// Revision of the tree the paths of the nodes point to
+    #[serde(skip)]
+    tree_revision: u64,
+
+    /// Copy of the tree the paths of the nodes point to, to follow the nodes
+    /// when the tree changes
+    #[serde(skip)]
+    selection_tree: Option<Element>,
+
+    /// Svg and selection of the bounding boxes
+    #[serde(skip)]
+    boxes_source: Option<(String, Vec<Vec<usize>>)>,
+
+    /// Bounding boxes of the selection
+    #[serde(skip)]
+    selection_boxes: Vec<usvg::Rect>,
+
+    /// Computation of the bounding boxes of the selection
+    #[serde(skip)]
+    boxes_job: Option<BackgroundJob<Vec<usvg::Rect>>>,
 
     /// Translate x
     translate_x: f64,
@@ -46,12 +98,81 @@ pub struct TreeViewer {
     rotate: f64,
     /// Round to value
     round_to: u64,
-    /// Name of the tew element to add
+    /// Name of the new element, or content of the new node, to add
     new_element_name: String,
+    /// Kind of the new node to add
+    new_node_kind: NewNodeKind,
 
     /// Attributes of the tree viewer
     #[serde(skip)]
-    attributes_temp: HashMap<usize, String>,
+    attributes_temp: HashMap<Vec<usize>, String>,
+
+    /// Structural edit to apply after showing the tree
+    #[serde(skip)]
+    action: Option<TreeAction>,
+
+    /// The tree was edited this frame and must be written back
+    #[serde(skip)]
+    modified: bool,
+}
+
+/// Kind of node added by the tree viewer
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+enum NewNodeKind {
+    /// Element with the given name
+    Element,
+    /// Text node
+    Text,
+    /// Comment
+    Comment,
+    /// CDATA section, e.g. the content of a `<style>` or `<script>`
+    CData,
+}
+
+impl NewNodeKind {
+    /// Label of the kind
+    fn label(self) -> &'static str {
+        match self {
+            Self::Element => "Element",
+            Self::Text => "Text",
+            Self::Comment => "Comment",
+            Self::CData => "CDATA",
+        }
+    }
+
+    /// Build the node from the name or content typed by the user
+    fn node(self, content: &str) -> XMLNode {
+        match self {
+            Self::Element => XMLNode::Element(Element::new(content)),
+            Self::Text => XMLNode::Text(content.to_string()),
+            Self::Comment => XMLNode::Comment(content.to_string()),
+            Self::CData => XMLNode::CData(content.to_string()),
+        }
+    }
+}
+
+/// Structural edit of the tree, nodes are given by their index paths
+#[derive(Debug, Clone)]
+enum TreeAction {
+    /// Move nodes to the position `index` of the children of `parent`
+    Move {
+        /// Paths of the moved nodes
+        from: Vec<Vec<usize>>,
+        /// Path of the new parent
+        parent: Vec<usize>,
+        /// Index in the new parent
+        index: usize,
+    },
+    /// Wrap sibling nodes in a new group
+    Group(Vec<Vec<usize>>),
+    /// Replace the groups by their children
+    Ungroup(Vec<Vec<usize>>),
+    /// Swap the nodes with their next (`true`) or previous sibling element
+    Shift(Vec<Vec<usize>>, bool),
+    /// Insert a copy of the nodes after them
+    Duplicate(Vec<Vec<usize>>),
+    /// Remove the nodes
+    Delete(Vec<Vec<usize>>),
 }
 
 impl Default for TreeViewer {
@@ -60,7 +181,17 @@ impl Default for TreeViewer {
             is_multi_line: true,
             is_editable: false,
             edit_path_as_input: false,
-            ref_group: None,
+            edited_path: None,
+            selection: Vec::new(),
+            anchor: None,
+            reveal: None,
+            selection_document: 0,
+            locked_indexes: BTreeSet::new(),
+            tree_revision: 0,
+            selection_tree: None,
+            boxes_source: None,
+            selection_boxes: Vec::new(),
+            boxes_job: None,
             translate_x: 0.0,
             translate_y: 0.0,
             scale_x: 1.0,
@@ -71,16 +202,111 @@ impl Default for TreeViewer {
             round_to: 1,
             attributes_temp: HashMap::new(),
             new_element_name: String::new(),
+            new_node_kind: NewNodeKind::Element,
+            action: None,
+            modified: false,
         }
     }
 }
 
 impl TreeViewer {
+    /// Clear the selection when the current document changes, follow the selected
+    /// nodes when its tree is parsed again or written by another viewer
+    pub(crate) fn sync_document(&mut self, document_index: usize, document: &mut Document) {
+        // parse the edited text now to know its revision
+        let _ = document.tree.sync(&document.svg);
+        let revision = document.tree.revision();
+        if self.selection_document != document_index {
+            self.selection_document = document_index;
+            self.selection.clear();
+            self.anchor = None;
+            self.reveal = None;
+            self.edited_path = None;
+        } else if self.tree_revision == revision {
+            return;
+        }
+        self.tree_revision = revision;
+        self.attributes_temp.clear();
+        // an invalid text keeps the selection until it is valid again
+        let Ok(root) = document.tree.sync(&document.svg) else {
+            return;
+        };
+        if let Some(old) = &self.selection_tree {
+            let remap = |path: &Vec<usize>| remap_path(old, root, path);
+            self.selection = self.selection.iter().filter_map(remap).collect();
+            self.anchor = self.anchor.as_ref().and_then(remap);
+            self.reveal = self.reveal.as_ref().and_then(remap);
+            self.edited_path = self.edited_path.as_ref().and_then(remap);
+        }
+        self.selection_tree = Some(root.clone());
+    }
+
+    /// Select the node at `path`, opening its parents and scrolling to it
+    pub(crate) fn reveal(&mut self, path: &[usize]) {
+        if path.is_empty() {
+            // the root element has no header
+            self.selection.clear();
+            self.anchor = None;
+            return;
+        }
+        self.selection = vec![path.to_vec()];
+        self.anchor = Some(path.to_vec());
+        self.reveal = Some(path.to_vec());
+    }
+
+    /// Bounding boxes of the selected elements of `svg`, in svg canvas coordinates
+    ///
+    /// They are computed in the background, the previous boxes are returned meanwhile.
+    pub(crate) fn selection_boxes(
+        &mut self,
+        ctx: &egui::Context,
+        svg: &str,
+        options: &usvg::Options<'_>,
+    ) -> &[usvg::Rect] {
+        if let Some(job) = &mut self.boxes_job {
+            match job.poll() {
+                Ok(Some(boxes)) => {
+                    self.selection_boxes = boxes;
+                    self.boxes_job = None;
+                }
+                Ok(None) => {}
+                Err(()) => self.boxes_job = None,
+            }
+        }
+        let is_current = self
+            .boxes_source
+            .as_ref()
+            .is_some_and(|(source, selection)| source == svg && *selection == self.selection);
+        if !is_current {
+            self.boxes_source = Some((svg.to_string(), self.selection.clone()));
+            if self.selection.is_empty() {
+                self.selection_boxes.clear();
+                self.boxes_job = None;
+            } else {
+                let svg = svg.to_string();
+                let selection = self.selection.clone();
+                let options = copy_options(options);
+                // replacing the job cancels the stale one
+                self.boxes_job = Some(BackgroundJob::start(ctx, move |_| {
+                    let boxes = Element::parse(svg.as_bytes())
+                        .map_err(|e| e.to_string())
+                        .and_then(|root| element_bounding_boxes(&root, &selection, &options))
+                        .map(|boxes| boxes.into_iter().flatten().collect())
+                        .unwrap_or_default();
+                    Some(boxes)
+                }));
+            }
+        }
+        &self.selection_boxes
+    }
+
     /// Show Tree Viewer
     pub fn show(
         &mut self,
         ui: &mut egui::Ui,
         svg_str: &mut String,
+        tree: &mut SvgTree,
+        locked_layers: &BTreeSet<String>,
         error_manager: &mut ErrorManager,
     ) {
         Frame::new()
@@ -95,52 +321,40 @@ impl TreeViewer {
                     .id_salt("tree_viewer")
                     .show(ui, |ui| {
                         ui.set_min_width(ui.available_width());
-                        match &mut Element::parse(svg_str.as_bytes()) {
+                        match tree.sync(svg_str) {
                             Ok(e) => {
                                 // edit width and height and viewbox
                                 ui.checkbox(&mut self.is_editable, "Editable (auto-write)");
+                                ui.add_enabled_ui(self.is_editable, |ui| {
+                                    self.show_style_conversion(ui, e);
+                                });
                                 ui.collapsing("SVG", |ui| {
                                     ui.add_enabled_ui(self.is_editable, |ui| {
-                                        self.show_attributes(ui, e, 0);
+                                        let before = e.attributes.clone();
+                                        self.show_attributes(ui, e, &[]);
+                                        self.modified |= e.attributes != before;
                                     });
                                 });
 
+                                let locked_indexes = layers(e)
+                                    .into_iter()
+                                    .filter(|layer| locked_layers.contains(&layer.name))
+                                    .map(|layer| layer.index)
+                                    .collect::<BTreeSet<_>>();
+                                self.locked_indexes.clone_from(&locked_indexes);
                                 self.show_group(
                                     ui,
                                     &mut e.children,
-                                    None,
+                                    &[],
                                     error_manager,
                                     self.is_editable,
+                                    &locked_indexes,
                                 );
+                                self.show_edited_path(ui.ctx(), e);
+                                self.apply_action(e, error_manager);
                                 ui.add_enabled_ui(self.is_editable, |ui| {
-                                    ui.horizontal(|ui| {
-                                        ui.text_edit_singleline(&mut self.new_element_name);
-                                        if ui
-                                            .button("Add key")
-                                            .on_hover_text("Add new attribute")
-                                            .clicked()
-                                            && !self.new_element_name.is_empty()
-                                        {
-                                            e.children.push(xmltree::XMLNode::Element(
-                                                Element::new(&self.new_element_name),
-                                            ));
-                                            self.new_element_name = String::default();
-                                        }
-                                    });
+                                    self.show_add_node(ui, e);
                                 });
-
-                                if self.is_editable {
-                                    let mut buf = Vec::new();
-                                    let writer_config = EmitterConfig {
-                                        perform_indent: true,
-                                        ..EmitterConfig::new()
-                                    };
-                                    if e.write_with_config(&mut buf, writer_config).is_ok()
-                                        && let Ok(s) = String::from_utf8(buf)
-                                    {
-                                        *svg_str = s;
-                                    }
-                                }
                             }
                             Err(e) => {
                                 ui.label(format!("Error: {e}"));
@@ -148,35 +362,290 @@ impl TreeViewer {
                         }
                     });
             });
+        // write only when the tree changed, the text stays untouched otherwise
+        if std::mem::take(&mut self.modified) {
+            if let Err(err) = tree.write(svg_str) {
+                // the edit was discarded, the paths may point to other nodes
+                self.selection.clear();
+                self.anchor = None;
+                self.edited_path = None;
+                error_manager.add_error(err);
+            }
+            // the paths of this viewer follow its own edits
+            self.selection_tree = tree.sync(svg_str).ok().cloned();
+            self.tree_revision = tree.revision();
+        }
+    }
+
+    /// Buttons converting the `style` attributes of the whole document
+    fn show_style_conversion(&mut self, ui: &mut egui::Ui, root: &mut Element) {
+        ui.horizontal(|ui| {
+            if ui
+                .button("Style to attributes")
+                .on_hover_text("Convert style to presentation attributes in the whole document, except the properties set by a stylesheet rule")
+                .clicked()
+            {
+                self.modified |= convert_all(root, style_to_attributes) > 0;
+            }
+            if ui
+                .button("Attributes to style")
+                .on_hover_text("Convert presentation attributes to style in the whole document, except the properties set by a stylesheet rule")
+                .clicked()
+            {
+                self.modified |= convert_all(root, attributes_to_style) > 0;
+            }
+        });
+    }
+
+    /// Add a new node in the selected element, or in the root element
+    fn show_add_node(&mut self, ui: &mut egui::Ui, root: &mut Element) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("new_node_kind")
+                .selected_text(self.new_node_kind.label())
+                .show_ui(ui, |ui| {
+                    for kind in [
+                        NewNodeKind::Element,
+                        NewNodeKind::Text,
+                        NewNodeKind::Comment,
+                        NewNodeKind::CData,
+                    ] {
+                        ui.selectable_value(&mut self.new_node_kind, kind, kind.label());
+                    }
+                });
+            let is_locked = matches!(self.selection.as_slice(), [path] if self.is_locked(path));
+            let parent = match self.selection.as_slice() {
+                [path] => match node_at_mut(root, path) {
+                    Some(XMLNode::Element(element)) => element,
+                    _ => root,
+                },
+                _ => root,
+            };
+            let is_element = self.new_node_kind == NewNodeKind::Element;
+            if is_element {
+                let children = element_schema(&parent.name)
+                    .filter(|_| is_svg_element(parent))
+                    .map(|schema| schema.children().collect::<Vec<_>>())
+                    .unwrap_or_default();
+                autocomplete_edit(ui, &mut self.new_element_name, &children);
+                if !self.new_element_name.is_empty()
+                    && let Some(warning) = element_warning(Some(parent), &self.new_element_name)
+                {
+                    ui.colored_label(Color32::YELLOW, "⚠")
+                        .on_hover_text(warning);
+                }
+            } else {
+                ui.text_edit_singleline(&mut self.new_element_name);
+            }
+            let hover_text = if is_locked {
+                "The selected node is in a locked layer"
+            } else {
+                "Add the node in the selected element, or in the root element"
+            };
+            if ui
+                .add_enabled(!is_locked, egui::Button::new("Add node"))
+                .on_hover_text(hover_text)
+                .on_disabled_hover_text(hover_text)
+                .clicked()
+                && !(is_element && self.new_element_name.is_empty())
+            {
+                let node = self.new_node_kind.node(&self.new_element_name);
+                parent.children.push(node);
+                self.new_element_name = String::default();
+                self.modified = true;
+            }
+        });
+    }
+
+    /// Select the node at `path`, ctrl toggles it and shift selects the siblings
+    /// between the last clicked node and this one
+    fn click_node(&mut self, path: &[usize], modifiers: egui::Modifiers) {
+        if modifiers.shift
+            && let Some(anchor) = &self.anchor
+            && let (Some((anchor_index, anchor_parent)), Some((index, parent))) =
+                (anchor.split_last(), path.split_last())
+            && anchor_parent == parent
+        {
+            let (first, last) = (*anchor_index.min(index), *anchor_index.max(index));
+            for sibling in first..=last {
+                let mut sibling_path = parent.to_vec();
+                sibling_path.push(sibling);
+                if !self.selection.contains(&sibling_path) {
+                    self.selection.push(sibling_path);
+                }
+            }
+            return;
+        }
+        if modifiers.command {
+            if let Some(position) = self.selection.iter().position(|p| p == path) {
+                self.selection.remove(position);
+            } else {
+                self.selection.push(path.to_vec());
+            }
+        } else {
+            self.selection = vec![path.to_vec()];
+        }
+        self.anchor = Some(path.to_vec());
+    }
+
+    /// Check if the node at `path` is in a locked layer, or is one
+    fn is_locked(&self, path: &[usize]) -> bool {
+        path.first()
+            .is_some_and(|index| self.locked_indexes.contains(index))
+    }
+
+    /// Nodes targeted by an operation on `path`: the selection if it contains `path`,
+    /// without the nodes of the locked layers
+    fn targets(&self, path: &[usize]) -> Vec<Vec<usize>> {
+        let mut targets = if self.selection.iter().any(|p| p == path) {
+            self.selection.clone()
+        } else {
+            vec![path.to_vec()]
+        };
+        targets.retain(|target| !self.is_locked(target));
+        targets
+    }
+
+    /// Header of a node with a drag handle and a context menu, dropping nodes
+    /// on a group moves them inside, on another node moves them before
+    fn show_node_header(
+        &mut self,
+        ui: &mut egui::Ui,
+        label: &str,
+        path: &[usize],
+        group_len: Option<usize>,
+        is_editable: bool,
+        add_body: impl FnOnce(&mut Self, &mut egui::Ui),
+    ) {
+        let id = ui.make_persistent_id(("node", path));
+        let is_selected = self.selection.iter().any(|p| p == path);
+        let mut state =
+            egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, false);
+        if self
+            .reveal
+            .as_ref()
+            .is_some_and(|reveal| reveal.len() > path.len() && reveal.starts_with(path))
+        {
+            state.set_open(true);
+        }
+        let (_toggle, header, _body) = state
+            .show_header(ui, |ui| {
+                if is_editable {
+                    ui.dnd_drag_source(id.with("drag"), self.targets(path), |ui| {
+                        ui.label("☰");
+                    })
+                    .response
+                    .on_hover_text("Drag to move the node");
+                }
+                if ui
+                    .selectable_label(is_selected, label)
+                    .on_hover_text("Click to select, ctrl to toggle, shift for a range")
+                    .clicked()
+                {
+                    let modifiers = ui.input(|i| i.modifiers);
+                    self.click_node(path, modifiers);
+                }
+            })
+            .body(|ui| add_body(self, ui));
+        if self.reveal.as_deref() == Some(path) {
+            header.response.scroll_to_me(Some(egui::Align::Center));
+            self.reveal = None;
+        }
+        if !is_editable {
+            return;
+        }
+        let response = header.response.interact(egui::Sense::click());
+        if response.dnd_hover_payload::<Vec<Vec<usize>>>().is_some() {
+            let y = if group_len.is_some() {
+                response.rect.center().y
+            } else {
+                response.rect.top()
+            };
+            ui.painter().hline(
+                response.rect.x_range(),
+                y,
+                egui::Stroke::new(2.0, ui.visuals().selection.bg_fill),
+            );
+        }
+        if let Some(from) = response.dnd_release_payload::<Vec<Vec<usize>>>() {
+            let (index, parent) = path.split_last().unwrap_or((&0, &[]));
+            self.action = Some(match group_len {
+                Some(len) => TreeAction::Move {
+                    from: from.to_vec(),
+                    parent: path.to_vec(),
+                    index: len,
+                },
+                None => TreeAction::Move {
+                    from: from.to_vec(),
+                    parent: parent.to_vec(),
+                    index: *index,
+                },
+            });
+        }
+        response.context_menu(|ui| {
+            let targets = self.targets(path);
+            if targets.len() > 1 {
+                ui.label(format!("{} selected nodes", targets.len()));
+            }
+            let actions = [
+                ("Group", TreeAction::Group(targets.clone())),
+                ("Bring forward", TreeAction::Shift(targets.clone(), true)),
+                ("Send backward", TreeAction::Shift(targets.clone(), false)),
+                ("Duplicate", TreeAction::Duplicate(targets.clone())),
+                ("Delete", TreeAction::Delete(targets.clone())),
+            ];
+            if group_len.is_some() && ui.button("Ungroup").clicked() {
+                self.action = Some(TreeAction::Ungroup(targets));
+            }
+            for (text, action) in actions {
+                if ui.button(text).clicked() {
+                    self.action = Some(action);
+                }
+            }
+        });
     }
 
     /// Show the svg groups
+    ///
+    /// `locked_layers` are indexes of `nodes` that cannot be edited
     #[allow(clippy::too_many_lines)]
     fn show_group(
         &mut self,
         ui: &mut egui::Ui,
-        nodes: &mut Vec<xmltree::XMLNode>,
-        curr_idx: Option<usize>,
+        nodes: &mut [xmltree::XMLNode],
+        parent_path: &[usize],
         error_manager: &mut ErrorManager,
         is_editable: bool,
+        locked_layers: &BTreeSet<usize>,
     ) {
-        let current_idx = curr_idx.unwrap_or(0);
-        let mut idx_to_remove = None;
         for (idx, node) in nodes.iter_mut().enumerate() {
+            let is_editable = is_editable && !locked_layers.contains(&idx);
+            let mut path = parent_path.to_vec();
+            path.push(idx);
             match node {
                 xmltree::XMLNode::Element(g) => match g.name.clone().as_str() {
                     "g" => {
-                        egui::CollapsingHeader::new("Group")
-                            .id_salt(format!("group_{idx}"))
-                            .show(ui, |ui| {
-                                self.show_group(
+                        let header = if locked_layers.contains(&idx) {
+                            "Group 🔒"
+                        } else {
+                            "Group"
+                        };
+                        self.show_node_header(
+                            ui,
+                            header,
+                            &path,
+                            Some(g.children.len()),
+                            is_editable,
+                            |viewer, ui| {
+                                viewer.show_group(
                                     ui,
                                     &mut g.children,
-                                    Some(current_idx + idx),
+                                    &path,
                                     error_manager,
                                     is_editable,
+                                    &BTreeSet::new(),
                                 );
-                            });
+                            },
+                        );
                     }
                     e => {
                         let name = if let Some(id) = g.attributes.get("id") {
@@ -184,186 +653,280 @@ impl TreeViewer {
                         } else {
                             e
                         };
-                        egui::CollapsingHeader::new(format!("Element: {name}"))
-                            .id_salt(format!("element_{idx}"))
-                            .show(ui, |ui| {
+                        self.show_node_header(
+                            ui,
+                            &format!("Element: {name}"),
+                            &path,
+                            None,
+                            is_editable,
+                            |viewer, ui| {
                                 ui.add_enabled_ui(is_editable, |ui| {
-                                    if e == "path" {
-                                        ui.horizontal(|ui| {
-                                            if ui.button("edit").clicked() {
-                                                match self.ref_group {
-                                                    Some(i) if i == idx => {
-                                                        self.ref_group = None; // Deselect if already selected
-                                                    }
-                                                    _ => {
-                                                        // Select the current group
-                                                        self.ref_group = Some(idx);
-                                                    }
-                                                }
-                                            }
-                                            ui.scope(|ui| {
-                                                ui.style_mut()
-                                                    .visuals
-                                                    .widgets
-                                                    .hovered
-                                                    .weak_bg_fill = Color32::RED;
-
-                                                if ui.button("delete").clicked() {
-                                                    idx_to_remove = Some(idx);
-                                                }
-                                            });
-                                        });
-                                    } else if e == "circle" {
-                                        // Convert circle to path logic here
-                                        // For example, you can create a path string based on circle attributes
-                                        if let Some(cx) = g.attributes.get("cx")
-                                            && let Some(cy) = g.attributes.get("cy")
-                                            && let Some(r) = g.attributes.get("r")
-                                            && ui.button("Convert to path").clicked()
-                                        {
-                                            match circle_to_path(cx, cy, r) {
-                                                Ok(path_data) => {
-                                                    g.name = "path".to_string();
-                                                    g.attributes.insert("d".to_string(), path_data);
-                                                    g.attributes.shift_remove("cx");
-                                                    g.attributes.shift_remove("cy");
-                                                    g.attributes.shift_remove("r");
-                                                }
-                                                Err(err) => {
-                                                    error_manager.add_error(err);
-                                                }
-                                            }
-                                        }
-                                    } else if e == "ellipse" {
-                                        // Convert ellipse to path logic here
-                                        if let Some(cx) = g.attributes.get("cx")
-                                            && let Some(cy) = g.attributes.get("cy")
-                                            && let Some(rx) = g.attributes.get("rx")
-                                            && let Some(ry) = g.attributes.get("ry")
-                                            && ui.button("Convert to path").clicked()
-                                        {
-                                            match ellipse_to_path(cx, cy, rx, ry) {
-                                                Ok(path_data) => {
-                                                    g.name = "path".to_string();
-                                                    g.attributes.insert("d".to_string(), path_data);
-                                                    g.attributes.shift_remove("cx");
-                                                    g.attributes.shift_remove("cy");
-                                                    g.attributes.shift_remove("rx");
-                                                    g.attributes.shift_remove("ry");
-                                                }
-                                                Err(err) => {
-                                                    error_manager.add_error(err);
-                                                }
-                                            }
-                                        }
-                                    } else if e == "polyline"
-                                        && ui.button("Convert to path").clicked()
-                                    {
-                                        // Convert polyline to path logic here
-                                        if let Some(points) = g.attributes.get("points") {
-                                            match polyline_to_path(points) {
-                                                Ok(path_data) => {
-                                                    g.name = "path".to_string();
-                                                    g.attributes.insert("d".to_string(), path_data);
-                                                    g.attributes.shift_remove("points");
-                                                }
-                                                Err(err) => {
-                                                    error_manager.add_error(err.to_string());
-                                                }
-                                            }
-                                        }
-                                    } else if e == "line" {
-                                        // Convert line to path logic here
-                                        if let Some(x1) = g.attributes.get("x1")
-                                            && let Some(y1) = g.attributes.get("y1")
-                                            && let Some(x2) = g.attributes.get("x2")
-                                            && let Some(y2) = g.attributes.get("y2")
-                                            && ui.button("Convert to path").clicked()
-                                        {
-                                            let path_data = line_to_path(x1, y1, x2, y2);
-                                            g.name = "path".to_string();
-                                            g.attributes.insert("d".to_string(), path_data);
-                                            g.attributes.shift_remove("x1");
-                                            g.attributes.shift_remove("y1");
-                                            g.attributes.shift_remove("x2");
-                                            g.attributes.shift_remove("y2");
-                                        }
-                                    } else if e == "polygon"
-                                        && ui.button("Convert to path").clicked()
-                                    {
-                                        // Convert polygon to path logic here
-                                        if let Some(points) = g.attributes.get("points") {
-                                            match polygon_to_path(points) {
-                                                Ok(path_data) => {
-                                                    g.name = "path".to_string();
-                                                    g.attributes.insert("d".to_string(), path_data);
-                                                    g.attributes.shift_remove("points");
-                                                }
-                                                Err(err) => {
-                                                    error_manager.add_error(err.to_string());
-                                                }
-                                            }
-                                        }
-                                    } else if e == "rect" {
-                                        // Convert rectangle to path logic here
-                                        if let Some(x) = g.attributes.get("x")
-                                            && let Some(y) = g.attributes.get("y")
-                                            && let Some(width) = g.attributes.get("width")
-                                            && let Some(height) = g.attributes.get("height")
-                                            && ui.button("Convert to path").clicked()
-                                        {
-                                            {
-                                                let path_data = rect_to_path(x, y, width, height);
-                                                g.name = "path".to_string();
-                                                g.attributes.insert("d".to_string(), path_data);
-                                                g.attributes.shift_remove("x");
-                                                g.attributes.shift_remove("y");
-                                                g.attributes.shift_remove("width");
-                                                g.attributes.shift_remove("height");
-                                            }
-                                        }
-                                    }
-                                    self.show_attributes(ui, g, idx + 1);
+                                    let before = (g.name.clone(), g.attributes.clone());
+                                    viewer.show_element_tools(ui, g, &path, error_manager);
+                                    viewer.show_attributes(ui, g, &path);
+                                    viewer.modified |=
+                                        g.name != before.0 || g.attributes != before.1;
                                 });
-                            });
-                        if let Some(index) = self.ref_group
-                            && *e == *"path"
-                            && index == idx
-                        {
-                            self.show_current_edition(ui.ctx(), g);
-                        }
+                            },
+                        );
                     }
                 },
-                xmltree::XMLNode::Text(t) => {
-                    egui::CollapsingHeader::new("Text")
-                        .id_salt(format!("text_{idx}"))
-                        .show(ui, |ui| {
-                            ui.label(format!("Text: {t}"));
-                        });
+                xmltree::XMLNode::Text(text) => {
+                    self.show_text_node(ui, "Text", text, &path, is_editable);
                 }
-                xmltree::XMLNode::Comment(comment_value) => {
-                    egui::CollapsingHeader::new("Comment")
-                        .id_salt(format!("comment_{idx}"))
-                        .show(ui, |ui| {
-                            ui.add_enabled_ui(is_editable, |ui| {
-                                ui.text_edit_singleline(comment_value);
+                xmltree::XMLNode::Comment(comment) => {
+                    self.show_text_node(ui, "Comment", comment, &path, is_editable);
+                }
+                xmltree::XMLNode::CData(data) => {
+                    self.show_text_node(ui, "CDATA", data, &path, is_editable);
+                }
+                xmltree::XMLNode::ProcessingInstruction(target, data) => {
+                    let label = format!("Processing instruction: {target}");
+                    self.show_node_header(ui, &label, &path, None, is_editable, |viewer, ui| {
+                        ui.add_enabled_ui(is_editable, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label("target");
+                                viewer.modified |= ui.text_edit_singleline(target).changed();
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("data");
+                                let mut value = data.clone().unwrap_or_default();
+                                if ui.text_edit_singleline(&mut value).changed() {
+                                    *data = (!value.is_empty()).then_some(value);
+                                    viewer.modified = true;
+                                }
                             });
                         });
+                    });
+                }
+            }
+        }
+    }
+
+    /// Text, comment or CDATA node, edited as text
+    fn show_text_node(
+        &mut self,
+        ui: &mut egui::Ui,
+        kind: &str,
+        text: &mut String,
+        path: &[usize],
+        is_editable: bool,
+    ) {
+        let preview = text.trim().lines().next().unwrap_or_default();
+        let label = format!("{kind}: {}", preview.chars().take(40).collect::<String>());
+        self.show_node_header(ui, &label, path, None, is_editable, |viewer, ui| {
+            ui.add_enabled_ui(is_editable, |ui| {
+                let response = if viewer.is_multi_line || text.contains('\n') {
+                    ui.add(egui::TextEdit::multiline(text).code_editor())
+                } else {
+                    ui.text_edit_singleline(text)
+                };
+                viewer.modified |= response.changed();
+            });
+        });
+    }
+
+    /// Buttons of an element: edit, delete and shape conversions
+    fn show_element_tools(
+        &mut self,
+        ui: &mut egui::Ui,
+        g: &mut Element,
+        path: &[usize],
+        error_manager: &mut ErrorManager,
+    ) {
+        ui.horizontal(|ui| {
+            if g.name == "path" && ui.button("edit").clicked() {
+                if self.edited_path.as_deref() == Some(path) {
+                    self.edited_path = None; // Close if already opened
+                } else {
+                    self.edited_path = Some(path.to_vec());
                 }
-                _ => {
-                    ui.label(format!("Unknown node: {node:?}"));
+            }
+            ui.scope(|ui| {
+                ui.style_mut().visuals.widgets.hovered.weak_bg_fill = Color32::RED;
+
+                if ui.button("delete").clicked() {
+                    self.action = Some(TreeAction::Delete(self.targets(path)));
+                }
+            });
+        });
+        if g.name == "path" {
+            // Convert path to the shorter primitive element
+            if let Some(primitive) = detect_primitive(g)
+                && ui
+                    .button("Convert to primitive")
+                    .on_hover_text(format!("Convert path to <{}>", primitive.element_name()))
+                    .clicked()
+            {
+                path_to_primitive(g);
+                if self.edited_path.as_deref() == Some(path) {
+                    self.edited_path = None;
                 }
             }
+        } else if is_convertible_to_path(g)
+            && ui.button("Convert to path").clicked()
+            && let Err(err) = shape_to_path(g)
+        {
+            error_manager.add_error(err);
         }
-        if let Some(idx) = idx_to_remove {
-            nodes.remove(idx);
+    }
+
+    /// Show the edition window of the edited path element, if any
+    fn show_edited_path(&mut self, ctx: &egui::Context, root: &mut Element) {
+        let Some(path) = self.edited_path.clone() else {
+            return;
+        };
+        match node_at_mut(root, &path) {
+            Some(XMLNode::Element(g)) if g.name == "path" => {
+                let before = g.attributes.get("d").cloned();
+                self.show_current_edition(ctx, g);
+                self.modified |= g.attributes.get("d") != before.as_ref();
+            }
+            _ => self.edited_path = None,
+        }
+    }
+
+    /// Remove the nodes of the locked layers from the action
+    /// # Errors
+    /// Fails if nodes are moved into a locked layer or if no node is left
+    fn unlocked_action(&self, action: TreeAction) -> Result<TreeAction, String> {
+        let unlocked = |paths: Vec<Vec<usize>>| -> Result<Vec<Vec<usize>>, String> {
+            let paths = paths
+                .into_iter()
+                .filter(|path| !self.is_locked(path))
+                .collect::<Vec<_>>();
+            if paths.is_empty() {
+                Err("The nodes are in a locked layer".to_string())
+            } else {
+                Ok(paths)
+            }
+        };
+        Ok(match action {
+            TreeAction::Move {
+                from,
+                parent,
+                index,
+            } => {
+                if self.is_locked(&parent) {
+                    return Err("Cannot move nodes into a locked layer".to_string());
+                }
+                TreeAction::Move {
+                    from: unlocked(from)?,
+                    parent,
+                    index,
+                }
+            }
+            TreeAction::Group(paths) => TreeAction::Group(unlocked(paths)?),
+            TreeAction::Ungroup(paths) => TreeAction::Ungroup(unlocked(paths)?),
+            TreeAction::Shift(paths, forward) => TreeAction::Shift(unlocked(paths)?, forward),
+            TreeAction::Duplicate(paths) => TreeAction::Duplicate(unlocked(paths)?),
+            TreeAction::Delete(paths) => TreeAction::Delete(unlocked(paths)?),
+        })
+    }
+
+    /// Apply the pending structural edit on the root element and update the
+    /// selection to the edited nodes
+    fn apply_action(&mut self, root: &mut Element, error_manager: &mut ErrorManager) {
+        let Some(action) = self.action.take() else {
+            return;
+        };
+        // paths may have changed, close the edition window
+        self.edited_path = None;
+        self.anchor = None;
+        let action = match self.unlocked_action(action) {
+            Ok(action) => action,
+            Err(err) => {
+                error_manager.add_error(err);
+                return;
+            }
+        };
+        // the action is applied to a copy, a failing step leaves the tree untouched
+        let mut edited = root.clone();
+        let result = match action {
+            TreeAction::Move {
+                from,
+                parent,
+                index,
+            } => move_nodes_to(&mut edited, &from, &parent, index),
+            TreeAction::Group(paths) => {
+                let paths = normalize_selection(&paths);
+                match paths.first().and_then(|p| p.split_last()) {
+                    Some((_, parent))
+                        if paths
+                            .iter()
+                            .all(|p| p.split_last().map(|l| l.1) == Some(parent)) =>
+                    {
+                        let indexes = paths.iter().filter_map(|p| p.last().copied());
+                        let indexes = indexes.collect::<Vec<_>>();
+                        group_nodes(&mut edited, parent, &indexes).map(|group| vec![group])
+                    }
+                    _ => Err("Only sibling nodes can be grouped".to_string()),
+                }
+            }
+            // the later nodes are edited first, the new paths of the earlier edits follow
+            TreeAction::Ungroup(paths) => normalize_selection(&paths).iter().rev().try_fold(
+                Vec::new(),
+                |children: Vec<Vec<usize>>, path| {
+                    let count = ungroup_node(&mut edited, path)?;
+                    let mut children = children
+                        .iter()
+                        .filter_map(|child| path_after_removal(child, path))
+                        .map(|child| path_after_insertion(&child, path, count))
+                        .collect::<Vec<_>>();
+                    if let Some((index, parent)) = path.split_last() {
+                        children.extend((*index..index + count).map(|child| {
+                            let mut child_path = parent.to_vec();
+                            child_path.push(child);
+                            child_path
+                        }));
+                    }
+                    Ok(children)
+                },
+            ),
+            TreeAction::Shift(paths, forward) => {
+                let mut paths = normalize_selection(&paths);
+                if forward {
+                    paths.reverse();
+                }
+                paths
+                    .iter()
+                    .map(|path| shift_node(&mut edited, path, forward))
+                    .collect()
+            }
+            TreeAction::Duplicate(paths) => normalize_selection(&paths).iter().rev().try_fold(
+                Vec::new(),
+                |copies: Vec<Vec<usize>>, path| {
+                    let copy = duplicate_node(&mut edited, path)?;
+                    let mut copies = copies
+                        .iter()
+                        .map(|other| path_after_insertion(other, &copy, 1))
+                        .collect::<Vec<_>>();
+                    copies.push(copy);
+                    Ok(copies)
+                },
+            ),
+            TreeAction::Delete(paths) => {
+                remove_nodes(&mut edited, &paths);
+                Ok(Vec::new())
+            }
+        };
+        match result {
+            Ok(selection) => {
+                *root = edited;
+                self.selection = selection;
+                self.modified = true;
+            }
+            Err(err) => {
+                self.selection.clear();
+                error_manager.add_error(err);
+            }
         }
     }
 
     /// Show current edition of the path
     #[allow(clippy::too_many_lines)]
     fn show_current_edition(&mut self, ctx: &egui::Context, g: &mut Element) {
-        let mut is_open = self.ref_group.is_some();
+        let mut is_open = self.edited_path.is_some();
         Window::new("Edition")
             .scroll(true)
             .open(&mut is_open)
@@ -538,12 +1101,12 @@ impl TreeViewer {
                 }
             });
         if !is_open {
-            self.ref_group = None; // Reset the reference group when the edition window is closed
+            self.edited_path = None; // Reset the edited path when the edition window is closed
         }
     }
 
     /// Show the attributes of an Element
-    fn show_attributes(&mut self, ui: &mut egui::Ui, e: &mut Element, idx: usize) {
+    fn show_attributes(&mut self, ui: &mut egui::Ui, e: &mut Element, path: &[usize]) {
         TableBuilder::new(ui)
             .column(Column::auto())
             .column(Column::remainder())
@@ -557,35 +1120,72 @@ impl TreeViewer {
             })
             .body(|mut body| {
                 let mut remove_idx = None;
-                for (key, value) in &mut e.attributes {
+                let warnings = e
+                    .attributes
+                    .keys()
+                    .map(|key| attribute_warning(e, key))
+                    .collect::<Vec<_>>();
+                let candidates = element_schema(&e.name)
+                    .filter(|_| is_svg_element(e))
+                    .map(|schema| {
+                        schema
+                            .attributes()
+                            .filter(|name| !e.attributes.contains_key(*name))
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+                // the alpha of a color goes to its opacity attribute
+                let opacities = e
+                    .attributes
+                    .iter()
+                    .filter(|(key, _)| key.ends_with("-opacity"))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect::<HashMap<_, _>>();
+                let mut opacity_edit = None;
+                for ((key, value), warning) in e.attributes.iter_mut().zip(warnings) {
                     body.row(0.0, |mut row| {
                         row.col(|ui| {
-                            ui.scope(|ui| {
-                                ui.style_mut().visuals.widgets.hovered.weak_bg_fill = Color32::RED;
-
-                                if ui
-                                    .button(key)
-                                    .on_hover_text("Remove this attribute")
-                                    .clicked()
-                                {
-                                    remove_idx = Some(key.clone());
+                            ui.horizontal(|ui| {
+                                ui.scope(|ui| {
+                                    ui.style_mut().visuals.widgets.hovered.weak_bg_fill =
+                                        Color32::RED;
+
+                                    if ui
+                                        .button(key)
+                                        .on_hover_text("Remove this attribute")
+                                        .clicked()
+                                    {
+                                        remove_idx = Some(key.clone());
+                                    }
+                                });
+                                if let Some(warning) = warning {
+                                    ui.colored_label(Color32::YELLOW, "⚠")
+                                        .on_hover_text(warning);
                                 }
                             });
                         });
                         row.col(|ui| {
-                            if self.is_multi_line {
-                                ui.text_edit_multiline(value);
-                            } else {
-                                ui.text_edit_singleline(value);
+                            if let Some(edit) =
+                                show_attribute_value(ui, key, value, self.is_multi_line, |name| {
+                                    opacities.get(name).cloned()
+                                })
+                            {
+                                opacity_edit = Some(edit);
                             }
                         });
                     });
                 }
-                let key_attr = self.attributes_temp.entry(idx).or_default();
+                let key_attr = self.attributes_temp.entry(path.to_vec()).or_default();
                 body.row(0.0, |mut row| {
                     row.col(|ui| {
                         ui.horizontal(|ui| {
-                            ui.text_edit_singleline(key_attr);
+                            autocomplete_edit(ui, key_attr, &candidates);
+                            if !key_attr.is_empty()
+                                && let Some(warning) = attribute_warning(e, key_attr)
+                            {
+                                ui.colored_label(Color32::YELLOW, "⚠")
+                                    .on_hover_text(warning);
+                            }
                         });
                     });
                     row.col(|ui| {
@@ -604,6 +1204,9 @@ impl TreeViewer {
                 if let Some(idx) = remove_idx {
                     e.attributes.shift_remove(&idx);
                 }
+                if let Some((name, opacity)) = opacity_edit {
+                    e.attributes.insert(name.to_string(), opacity);
+                }
             });
     }
 }
@@ -641,9 +1244,17 @@ impl BladvakPanel for TreeViewerPanel {
     }
 
     fn ui(&self, app: &mut Self::App, ui: &mut egui::Ui, error_manager: &mut ErrorManager) {
+        let document_index = app.documents.get_current_index();
         let Some(document) = app.documents.get_current_doc_mut() else {
             return;
         };
-        app.tree_viewer.show(ui, &mut document.svg, error_manager);
+        app.tree_viewer.sync_document(document_index, document);
+        app.tree_viewer.show(
+            ui,
+            &mut document.svg,
+            &mut document.tree,
+            &document.locked_layers,
+            error_manager,
+        );
     }
 }